@@ -0,0 +1,423 @@
+//! Text encodings of JSON input and output: UTF-8, UTF-16 and UTF-32, detected from byte order mark
+//! or from the pattern of zero bytes at the beginning of input.
+
+use anyhow::{Error, Result};
+use std::{
+    collections::VecDeque,
+    error, fmt,
+    io::{self, BufReader, Bytes, Read, Write},
+};
+
+/// Replacement char of invalid byte sequences decoded lossily.
+const REPLACEMENT_CHAR: char = '\u{fffd}';
+
+/// Text encoding of JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Encoding {
+    /// Get name of encoding.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+        }
+    }
+
+    /// Detect encoding from the first 4 bytes(or fewer) of input, along with the length of byte order mark if any.<br>
+    /// Without byte order mark, the pattern of zero bytes is used, since JSON text starts with ASCII chars(RFC 4627 section 3).
+    pub fn detect(bytes: &[u8]) -> (Encoding, usize) {
+        match bytes {
+            [0xef, 0xbb, 0xbf, ..] => (Encoding::Utf8, 3),
+            [0x00, 0x00, 0xfe, 0xff, ..] => (Encoding::Utf32Be, 4),
+            [0xff, 0xfe, 0x00, 0x00, ..] => (Encoding::Utf32Le, 4),
+            [0xfe, 0xff, ..] => (Encoding::Utf16Be, 2),
+            [0xff, 0xfe, ..] => (Encoding::Utf16Le, 2),
+            [0x00, 0x00, 0x00, _, ..] => (Encoding::Utf32Be, 0),
+            [_, 0x00, 0x00, 0x00, ..] => (Encoding::Utf32Le, 0),
+            [0x00, _, ..] => (Encoding::Utf16Be, 0),
+            [_, 0x00, ..] => (Encoding::Utf16Le, 0),
+            _ => (Encoding::Utf8, 0),
+        }
+    }
+
+    /// Encode a string in the encoding and write it to an instance which implements trait std::io::Write.
+    pub fn write_str<W: Write>(&self, writer: &mut W, s: &str) -> io::Result<()> {
+        match self {
+            Encoding::Utf8 => writer.write_all(s.as_bytes()),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let mut bytes = Vec::with_capacity(s.len() * 2);
+                for unit in s.encode_utf16() {
+                    match self {
+                        Encoding::Utf16Le => bytes.extend_from_slice(&unit.to_le_bytes()),
+                        _ => bytes.extend_from_slice(&unit.to_be_bytes()),
+                    }
+                }
+                writer.write_all(&bytes)
+            }
+            Encoding::Utf32Le | Encoding::Utf32Be => {
+                let mut bytes = Vec::with_capacity(s.len() * 4);
+                for c in s.chars() {
+                    match self {
+                        Encoding::Utf32Le => bytes.extend_from_slice(&u32::from(c).to_le_bytes()),
+                        _ => bytes.extend_from_slice(&u32::from(c).to_be_bytes()),
+                    }
+                }
+                writer.write_all(&bytes)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    /// Implement Display trait for Encoding
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error raised when input contains an invalid byte sequence, carrying the encoding
+/// and the byte offset where the sequence starts.<br>
+/// It can be recovered from an anyhow::Error by downcast_ref::&lt;DecodeError&gt;().
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    pub encoding: Encoding,
+    pub offset: usize,
+}
+
+impl fmt::Display for DecodeError {
+    /// Implement Display trait for DecodeError
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid {} byte sequence at byte offset {}",
+            self.encoding, self.offset
+        )
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// Char decoder of a byte reader, detecting encoding and skipping byte order mark if any.
+pub(crate) struct CharDecoder<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    /// Bytes read ahead but not decoded yet.
+    pending: VecDeque<u8>,
+    /// Encoding of input, None until detected.
+    encoding: Option<Encoding>,
+    /// Whether encoding has been detected and byte order mark skipped.
+    is_detected: bool,
+    /// Decode invalid byte sequences as replacement char instead of failing.
+    lossy: bool,
+    /// Count of bytes decoded so far.
+    offset: usize,
+}
+
+impl<R: Read> CharDecoder<R> {
+    /// Create a char decoder of specified encoding, or detected encoding if None.
+    pub(crate) fn new(reader: R, encoding: Option<Encoding>, lossy: bool) -> Self {
+        CharDecoder {
+            bytes: BufReader::new(reader).bytes(),
+            pending: VecDeque::new(),
+            encoding,
+            is_detected: false,
+            lossy,
+            offset: 0,
+        }
+    }
+
+    /// Count of bytes decoded so far, including byte order mark.
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Read ahead until specified count of bytes are pending, or end of input.
+    fn fill(&mut self, count: usize) -> Result<()> {
+        while self.pending.len() < count {
+            match self.bytes.next() {
+                None => break,
+                Some(b) => self.pending.push_back(b?),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Consume specified count of pending bytes.
+    fn take(&mut self, count: usize) {
+        let count = count.min(self.pending.len());
+        self.offset += count;
+        self.pending.drain(..count);
+    }
+
+    /// Detect encoding if not specified, and skip byte order mark if any.
+    fn detect(&mut self) -> Result<Encoding> {
+        self.fill(4)?;
+        let bytes: Vec<u8> = self.pending.iter().copied().collect();
+        let (detected, bom_len) = Encoding::detect(&bytes);
+        let encoding = self.encoding.unwrap_or(detected);
+        if encoding == detected {
+            self.take(bom_len);
+        }
+
+        self.encoding = Some(encoding);
+        self.is_detected = true;
+        Ok(encoding)
+    }
+
+    /// Handle an invalid byte sequence of specified length, either decoded lossily or failing.
+    fn invalid(&mut self, encoding: Encoding, len: usize) -> Result<Option<char>> {
+        if !self.lossy {
+            let decode_error = DecodeError {
+                encoding,
+                offset: self.offset,
+            };
+            return Err(Error::new(decode_error));
+        }
+
+        self.take(len.max(1));
+        Ok(Some(REPLACEMENT_CHAR))
+    }
+
+    /// Decode next char of UTF-8.
+    fn next_utf8(&mut self) -> Result<Option<char>> {
+        self.fill(1)?;
+        let lead = match self.pending.front() {
+            None => return Ok(None),
+            Some(b) => *b,
+        };
+        let (len, min) = match lead {
+            0x00..=0x7f => {
+                self.take(1);
+                return Ok(Some(char::from(lead)));
+            }
+            0xc2..=0xdf => (2, 0x80),
+            0xe0..=0xef => (3, 0x800),
+            0xf0..=0xf4 => (4, 0x10000),
+            _ => return self.invalid(Encoding::Utf8, 1),
+        };
+
+        self.fill(len)?;
+        let mut code = u32::from(lead) & (0x7f >> len);
+        for i in 1..len {
+            match self.pending.get(i) {
+                Some(b) if b & 0xc0 == 0x80 => code = (code << 6) | u32::from(b & 0x3f),
+                // the invalid sequence ends before the first byte which is not a continuation byte
+                _ => return self.invalid(Encoding::Utf8, i),
+            }
+        }
+        match char::from_u32(code) {
+            Some(c) if code >= min => {
+                self.take(len);
+                Ok(Some(c))
+            }
+            _ => self.invalid(Encoding::Utf8, len),
+        }
+    }
+
+    /// Decode next UTF-16 code unit, None at the end of input, or Err with the count of trailing bytes.
+    fn next_utf16_unit(
+        &mut self,
+        offset: usize,
+        is_le: bool,
+    ) -> Result<Option<Result<u16, usize>>> {
+        self.fill(offset + 2)?;
+        let bytes = [self.pending.get(offset), self.pending.get(offset + 1)];
+        let unit = match bytes {
+            [None, _] => return Ok(None),
+            [Some(_), None] => return Ok(Some(Err(1))),
+            [Some(a), Some(b)] if is_le => u16::from_le_bytes([*a, *b]),
+            [Some(a), Some(b)] => u16::from_be_bytes([*a, *b]),
+        };
+
+        Ok(Some(Ok(unit)))
+    }
+
+    /// Decode next char of UTF-16.
+    fn next_utf16(&mut self, encoding: Encoding) -> Result<Option<char>> {
+        let is_le = encoding == Encoding::Utf16Le;
+        let high = match self.next_utf16_unit(0, is_le)? {
+            None => return Ok(None),
+            Some(Err(len)) => return self.invalid(encoding, len),
+            Some(Ok(unit)) => unit,
+        };
+        if !(0xd800..=0xdfff).contains(&high) {
+            self.take(2);
+            return Ok(char::from_u32(u32::from(high)));
+        }
+        if high >= 0xdc00 {
+            return self.invalid(encoding, 2);
+        }
+
+        match self.next_utf16_unit(2, is_le)? {
+            Some(Ok(low)) if (0xdc00..=0xdfff).contains(&low) => {
+                self.take(4);
+                let code = 0x10000 + ((u32::from(high) - 0xd800) << 10) + (u32::from(low) - 0xdc00);
+                Ok(char::from_u32(code))
+            }
+            _ => self.invalid(encoding, 2),
+        }
+    }
+
+    /// Decode next char of UTF-32.
+    fn next_utf32(&mut self, encoding: Encoding) -> Result<Option<char>> {
+        self.fill(4)?;
+        let len = self.pending.len().min(4);
+        if len == 0 {
+            return Ok(None);
+        }
+        if len < 4 {
+            return self.invalid(encoding, len);
+        }
+
+        let bytes = [
+            self.pending[0],
+            self.pending[1],
+            self.pending[2],
+            self.pending[3],
+        ];
+        let code = match encoding {
+            Encoding::Utf32Le => u32::from_le_bytes(bytes),
+            _ => u32::from_be_bytes(bytes),
+        };
+        match char::from_u32(code) {
+            Some(c) => {
+                self.take(4);
+                Ok(Some(c))
+            }
+            None => self.invalid(encoding, 4),
+        }
+    }
+
+    /// Decode next char, None at the end of input.
+    pub(crate) fn next_char(&mut self) -> Result<Option<char>> {
+        let encoding = match self.encoding {
+            Some(encoding) if self.is_detected => encoding,
+            _ => self.detect()?,
+        };
+        match encoding {
+            Encoding::Utf8 => self.next_utf8(),
+            Encoding::Utf16Le | Encoding::Utf16Be => self.next_utf16(encoding),
+            Encoding::Utf32Le | Encoding::Utf32Be => self.next_utf32(encoding),
+        }
+    }
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use super::*;
+    use crate::{JsonNode, JsonWriter, ParseOptions, WriteOptions};
+
+    /// Decode all chars of bytes, with specified encoding or detected encoding.
+    fn decode(bytes: &[u8], encoding: Option<Encoding>, lossy: bool) -> Result<String> {
+        let mut decoder = CharDecoder::new(bytes, encoding, lossy);
+        let mut s = String::new();
+        while let Some(c) = decoder.next_char()? {
+            s.push(c);
+        }
+
+        Ok(s)
+    }
+
+    /// Test detecting encodings and decoding them.
+    #[test]
+    fn test_decode() -> Result<()> {
+        let json = "{\"a\": \"é😀\"}";
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+            Encoding::Utf32Le,
+            Encoding::Utf32Be,
+        ] {
+            let mut bytes = Vec::new();
+            encoding.write_str(&mut bytes, json)?;
+            assert_eq!(decode(&bytes, None, false)?, json, "{}", encoding);
+            assert_eq!(decode(&bytes, Some(encoding), false)?, json, "{}", encoding);
+
+            let mut with_bom = Vec::new();
+            encoding.write_str(&mut with_bom, "\u{feff}")?;
+            with_bom.extend_from_slice(&bytes);
+            assert_eq!(decode(&with_bom, None, false)?, json, "{}", encoding);
+        }
+
+        assert_eq!(decode(b"1", None, false)?, "1");
+        assert_eq!(decode(b"", None, false)?, "");
+        Ok(())
+    }
+
+    /// Test invalid byte sequences, decoded lossily or reported with byte offset.
+    #[test]
+    fn test_invalid() -> Result<()> {
+        let cases: [(&[u8], Option<Encoding>, &str, usize); 6] = [
+            (b"[\"a\xff\"]", None, "[\"a\u{fffd}\"]", 3),
+            (b"[\"\xe2\x82\"]", None, "[\"\u{fffd}\"]", 2),
+            (b"[\xed\xa0\x80]", None, "[\u{fffd}]", 1),
+            (
+                b"[\x00\x00\xd8]\x00",
+                Some(Encoding::Utf16Le),
+                "[\u{fffd}]",
+                2,
+            ),
+            (b"[\x00\x00", Some(Encoding::Utf16Le), "[\u{fffd}", 2),
+            (b"\x00\x00\x00[\x00\x11\x00\x00", None, "[\u{fffd}", 4),
+        ];
+        for (bytes, encoding, lossy, offset) in cases {
+            assert_eq!(decode(bytes, encoding, true)?, lossy);
+
+            let err = decode(bytes, encoding, false).unwrap_err();
+            let decode_error = err.downcast_ref::<DecodeError>().unwrap();
+            assert_eq!(decode_error.offset, offset, "{:?}", bytes);
+        }
+
+        Ok(())
+    }
+
+    /// Test parsing JSON of encodings other than UTF-8, and writing it back.
+    #[test]
+    fn test_parse_and_write() -> Result<()> {
+        let json = "{\"name\": \"Zoë\"}";
+        let strict = ParseOptions::strict();
+        for (encoding, byte_order_mark) in [
+            (Encoding::Utf8, true),
+            (Encoding::Utf16Le, true),
+            (Encoding::Utf16Be, false),
+        ] {
+            let options = WriteOptions {
+                encoding,
+                byte_order_mark,
+                ..WriteOptions::compact()
+            };
+            let mut writer = JsonWriter::new(Vec::new(), options);
+            writer.write_node(&JsonNode::parse_single_node(json.as_bytes())?)?;
+            let bytes = writer.into_inner();
+            assert_eq!(
+                bytes.starts_with(&[0xef]) || bytes.starts_with(&[0xff]),
+                byte_order_mark
+            );
+
+            let json_node = JsonNode::parse_single_node_with_options(&bytes[..], &strict)?;
+            assert_eq!(json_node.to_string(), json);
+        }
+
+        let bytes = b"{\"a\": \"\xff\"}";
+        let err = JsonNode::parse_single_node(&bytes[..]).unwrap_err();
+        assert_eq!(err.downcast_ref::<DecodeError>().map(|e| e.offset), Some(7));
+        let options = ParseOptions {
+            allow_invalid_bytes: true,
+            ..ParseOptions::strict()
+        };
+        let json_node = JsonNode::parse_single_node_with_options(&bytes[..], &options)?;
+        assert_eq!(json_node.to_string(), "{\"a\": \"\u{fffd}\"}");
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+//! ANSI syntax highlighting of JSON output.
+
+use std::{
+    env,
+    fmt::{self, Write},
+    io::{self, IsTerminal},
+};
+
+/// ANSI escape sequence resetting all styles.
+const RESET: &str = "\x1b[0m";
+
+/// Kinds of JSON output highlighted by a theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Style {
+    Key,
+    String,
+    Number,
+    Boolean,
+    Null,
+    Punctuation,
+}
+
+/// Theme of ANSI syntax highlighting, each field is an ANSI escape sequence starting a style.<br>
+/// An empty escape sequence leaves the corresponding output uncolored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Style of object property names.
+    pub key: String,
+
+    /// Style of string values.
+    pub string: String,
+
+    /// Style of number values.
+    pub number: String,
+
+    /// Style of true and false.
+    pub boolean: String,
+
+    /// Style of null.
+    pub null: String,
+
+    /// Style of brackets, commas and colons.
+    pub punctuation: String,
+}
+
+impl Default for Theme {
+    /// Default theme, using the 8 basic ANSI colors.
+    fn default() -> Self {
+        Theme {
+            key: String::from("\x1b[34m"),
+            string: String::from("\x1b[32m"),
+            number: String::from("\x1b[36m"),
+            boolean: String::from("\x1b[33m"),
+            null: String::from("\x1b[35m"),
+            punctuation: String::new(),
+        }
+    }
+}
+
+impl Theme {
+    /// Get the default theme if stdout is a terminal supporting colors, otherwise None for plain output.<br>
+    /// Colors are disabled when environment variable NO_COLOR is set or TERM is dumb.
+    pub fn detect() -> Option<Theme> {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let dumb = env::var_os("TERM").is_some_and(|v| v == "dumb");
+        if no_color || dumb || !io::stdout().is_terminal() {
+            return None;
+        }
+
+        Some(Theme::default())
+    }
+
+    /// Get ANSI escape sequence of a style.
+    fn code(&self, style: Style) -> &str {
+        match style {
+            Style::Key => &self.key,
+            Style::String => &self.string,
+            Style::Number => &self.number,
+            Style::Boolean => &self.boolean,
+            Style::Null => &self.null,
+            Style::Punctuation => &self.punctuation,
+        }
+    }
+}
+
+/// Write output of a formatting function in a style of an optional theme, plain if no theme is given.
+pub(crate) fn write_styled<F, G>(
+    f: &mut F,
+    theme: Option<&Theme>,
+    style: Style,
+    g: G,
+) -> fmt::Result
+where
+    F: Write,
+    G: FnOnce(&mut F) -> fmt::Result,
+{
+    let code = theme.map_or("", |t| t.code(style));
+    if code.is_empty() {
+        return g(f);
+    }
+
+    f.write_str(code)?;
+    g(f)?;
+    f.write_str(RESET)
+}
+
+#[cfg(test)]
+mod highlight_tests {
+    use super::*;
+    use crate::{JsonNode, JsonTag, WriteOptions};
+    use anyhow::Result;
+
+    /// Get a theme marking each style by a distinguishable fake escape sequence.
+    fn marker_theme() -> Theme {
+        Theme {
+            key: String::from("<k>"),
+            string: String::from("<s>"),
+            number: String::from("<n>"),
+            boolean: String::from("<b>"),
+            null: String::from("<0>"),
+            punctuation: String::new(),
+        }
+    }
+
+    /// Test highlighting of JSON nodes, and plain output without a theme.
+    #[test]
+    fn test_highlight_node() -> Result<()> {
+        let json = r#"{"a": [1, true, null], "b": "x"}"#;
+        let json_node = JsonNode::parse_single_node(json.as_bytes())?;
+        let options = WriteOptions {
+            theme: Some(marker_theme()),
+            ..WriteOptions::default()
+        };
+        assert_eq!(
+            json_node.to_string_with_options(&options),
+            "{<k>\"a\"\x1b[0m: [<n>1\x1b[0m, <b>true\x1b[0m, <0>null\x1b[0m], <k>\"b\"\x1b[0m: <s>\"x\"\x1b[0m}"
+        );
+        assert_eq!(
+            json_node.to_string_with_options(&WriteOptions::default()),
+            json
+        );
+
+        let options = WriteOptions {
+            theme: Some(Theme::default()),
+            ..WriteOptions::pretty_with_width(30)
+        };
+        let output = json_node.to_string_with_options(&options);
+        assert!(output.starts_with("{\n    \x1b[34m\"a\"\x1b[0m: [\x1b[36m1\x1b[0m, "));
+
+        Ok(())
+    }
+
+    /// Test highlighting of malformed JSON tags without building JSON nodes.
+    #[test]
+    fn test_highlight_tags() -> Result<()> {
+        let json = r#"{"a": [1, oops}, 'b': false"#;
+        let json_tags = JsonTag::parse(json.as_bytes())?;
+        let options = WriteOptions {
+            theme: Some(marker_theme()),
+            ..WriteOptions::default()
+        };
+        assert_eq!(
+            JsonTag::to_string_with_options(&json_tags, &options),
+            "{<k>\"a\"\x1b[0m: [<n>1\x1b[0m, oops}, <k>'b'\x1b[0m: <b>false\x1b[0m"
+        );
+
+        Ok(())
+    }
+}
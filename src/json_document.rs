@@ -0,0 +1,687 @@
+//! Format-preserving JSON document, whose values are edited by JSONPath while the rest of the original text is kept untouched.
+
+use anyhow::{bail, Result};
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fmt::{self, Formatter},
+    io::Read,
+    ops::Range,
+};
+
+use crate::json_node::*;
+use crate::json_number::*;
+use crate::json_path::*;
+use crate::json_string::*;
+use crate::json_tag::*;
+use crate::json_writer::*;
+use crate::parse_options::*;
+
+/// Span of a JSON node in document tags, by indexes of its first and last tags, along with spans of its entries.
+#[derive(Debug)]
+pub(crate) struct NodeSpan {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) entries: Vec<EntrySpan>,
+}
+
+/// Span of an array element or an object property in document tags.
+#[derive(Debug)]
+pub(crate) struct EntrySpan {
+    /// Index of the first tag, which is the property name for an object property.
+    pub(crate) start: usize,
+    pub(crate) value: NodeSpan,
+}
+
+impl NodeSpan {
+    /// Find span of the JSON node at specified location inside this one.
+    fn find(&self, location: &[usize]) -> Result<&NodeSpan> {
+        let mut span = self;
+        for i in location {
+            span = match span.entries.get(*i) {
+                Some(entry) => &entry.value,
+                None => bail!("document structure does not match node at {:?}", location),
+            };
+        }
+
+        Ok(span)
+    }
+}
+
+/// JSON document, which keeps its original text including whitespace and comments.<br>
+/// Edits by JSONPath only rewrite the affected values, everything else is written back as it was.
+#[derive(Debug, Clone)]
+pub struct JsonDocument {
+    tags: Vec<JsonTag>,
+    options: ParseOptions,
+}
+
+impl JsonDocument {
+    /// Parse a JSON document from a instance that implements Read trait, with default options.
+    pub fn parse<R>(reader: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        JsonDocument::parse_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Parse a JSON document from a instance that implements Read trait, with specified options.<br>
+    /// The document must contain exactly 1 JSON node.
+    pub fn parse_with_options<R>(reader: R, options: &ParseOptions) -> Result<Self>
+    where
+        R: Read,
+    {
+        let tags = JsonTag::parse_lossless_with_options(reader, options)?;
+        let document = JsonDocument {
+            tags,
+            options: options.clone(),
+        };
+        document.to_node()?;
+        document.root_span()?;
+
+        Ok(document)
+    }
+
+    /// Get JSON tags of the document, including whitespace and comments.
+    pub fn tags(&self) -> &[JsonTag] {
+        &self.tags
+    }
+
+    /// Parse the document into a JSON node, to read values from.
+    pub fn to_node(&self) -> Result<JsonNode> {
+        let mut nodes = JsonNode::parse_tags_with_options(&self.tags, &self.options)?;
+        if 1 != nodes.len() {
+            bail!("expecting 1 node, but found {}", nodes.len());
+        }
+
+        Ok(nodes.remove(0))
+    }
+
+    /// Set null to the JSON nodes selected by JSONPath.
+    pub fn set_null(&mut self, json_path: &str) -> Result<()> {
+        self.set_raw(json_path, &JsonNode::PlainNull)
+    }
+
+    /// Set a f64 number to the JSON nodes selected by JSONPath.
+    pub fn set_number(&mut self, json_path: &str, value: f64) -> Result<()> {
+        self.set_json_number(json_path, JsonNumber::from(value))
+    }
+
+    /// Set an i64 number to the JSON nodes selected by JSONPath.
+    pub fn set_i64(&mut self, json_path: &str, value: i64) -> Result<()> {
+        self.set_json_number(json_path, JsonNumber::from(value))
+    }
+
+    /// Set an u64 number to the JSON nodes selected by JSONPath.
+    pub fn set_u64(&mut self, json_path: &str, value: u64) -> Result<()> {
+        self.set_json_number(json_path, JsonNumber::from(value))
+    }
+
+    /// Set a JSON number to the JSON nodes selected by JSONPath.
+    pub fn set_json_number(&mut self, json_path: &str, value: JsonNumber) -> Result<()> {
+        self.set_raw(json_path, &JsonNode::PlainNumber(value))
+    }
+
+    /// Set a bool to the JSON nodes selected by JSONPath.
+    pub fn set_bool(&mut self, json_path: &str, value: bool) -> Result<()> {
+        self.set_raw(json_path, &JsonNode::PlainBoolean(value))
+    }
+
+    /// Set a string to the JSON nodes selected by JSONPath.
+    pub fn set_str(&mut self, json_path: &str, value: &str) -> Result<()> {
+        self.set_raw(json_path, &JsonNode::PlainString(String::from(value)))
+    }
+
+    /// Set a raw JSON node to the JSON nodes selected by JSONPath, only the text of selected nodes is rewritten.
+    pub fn set_raw(&mut self, json_path: &str, value: &JsonNode) -> Result<()> {
+        let (root, locations) = self.locate(json_path)?;
+        let mut ranges = Vec::new();
+        for location in &locations {
+            let span = root.find(location)?;
+            ranges.push(span.start..span.end + 1);
+        }
+
+        let mut edits = Vec::new();
+        for range in ranges {
+            let line_indent = self.line_indent(range.start);
+            let value_tags = self.render(value, &line_indent)?;
+            edits.push((range, value_tags));
+        }
+        self.apply(edits);
+
+        Ok(())
+    }
+
+    /// Remove the array elements or object properties selected by JSONPath, along with their separating commas.
+    pub fn remove(&mut self, json_path: &str) -> Result<()> {
+        let (root, locations) = self.locate(json_path)?;
+        let mut removed_by_parent: BTreeMap<&[usize], Vec<usize>> = BTreeMap::new();
+        for location in &locations {
+            match location.split_last() {
+                None => bail!("root node could not be removed"),
+                Some((index, parent)) => removed_by_parent.entry(parent).or_default().push(*index),
+            }
+        }
+
+        let mut edits = Vec::new();
+        for (parent, mut indexes) in removed_by_parent {
+            indexes.sort_unstable();
+            indexes.dedup();
+
+            let parent = root.find(parent)?;
+            for range in self.removal_ranges(parent, &indexes) {
+                edits.push((range, Vec::new()));
+            }
+        }
+        self.apply(edits);
+
+        Ok(())
+    }
+
+    /// Insert a property to the objects selected by JSONPath, after their last properties.<br>
+    /// The property name must not exist yet, use set_raw() to change an existing property.
+    pub fn insert(&mut self, json_path: &str, name: &str, value: &JsonNode) -> Result<()> {
+        let (root, locations) = self.locate(json_path)?;
+        let mut edits = Vec::new();
+        for location in &locations {
+            let span = root.find(location)?;
+            if self.tags[span.start] != JsonTag::LeftCurly {
+                bail!(
+                    "property could only be inserted to object, {} is not object",
+                    json_path
+                );
+            }
+
+            let exists = span.entries.iter().any(|e| match &self.tags[e.start] {
+                JsonTag::Literal(literal, kind) => {
+                    JsonNode::parse_prop_name(literal, *kind, &self.options)
+                        .ok()
+                        .as_deref()
+                        == Some(name)
+                }
+                _ => false,
+            });
+            if exists {
+                bail!("property {} already exists", name);
+            }
+
+            let mut entry_tags = Vec::new();
+            let mut quoted_name = String::new();
+            write_escaped(&mut quoted_name, name, &EscapeOptions::default())?;
+            entry_tags.push(JsonTag::Literal(quoted_name, LiteralKind::String('"')));
+            entry_tags.extend(self.colon_template(span));
+            let separator = self.separator_template(span);
+            let value_tags = self.render(value, &self.entry_indent(span, &separator))?;
+            entry_tags.extend(value_tags);
+
+            edits.push(self.insertion(span, span.entries.len(), entry_tags, separator));
+        }
+        self.apply(edits);
+
+        Ok(())
+    }
+
+    /// Insert an element to the arrays selected by JSONPath, at specified index.
+    pub fn insert_element(
+        &mut self,
+        json_path: &str,
+        index: usize,
+        value: &JsonNode,
+    ) -> Result<()> {
+        let (root, locations) = self.locate(json_path)?;
+        let mut edits = Vec::new();
+        for location in &locations {
+            let span = root.find(location)?;
+            if self.tags[span.start] != JsonTag::LeftSquare {
+                bail!(
+                    "element could only be inserted to array, {} is not array",
+                    json_path
+                );
+            }
+            if index > span.entries.len() {
+                bail!(
+                    "index {} out of range, array has {} elements",
+                    index,
+                    span.entries.len()
+                );
+            }
+
+            let separator = self.separator_template(span);
+            let entry_tags = self.render(value, &self.entry_indent(span, &separator))?;
+            edits.push(self.insertion(span, index, entry_tags, separator));
+        }
+        self.apply(edits);
+
+        Ok(())
+    }
+
+    /// Evaluate JSONPath to spans of the document and locations of selected JSON nodes.
+    fn locate(&self, json_path: &str) -> Result<(NodeSpan, Vec<NodeLocation>)> {
+        let json_path = JsonPath::parse_with_limits(json_path, &self.options.limits)?;
+        let mut root = self.to_node()?;
+        let locations = json_path.evaluate_locations(&mut root)?;
+
+        Ok((self.root_span()?, locations))
+    }
+
+    /// Get span of the root JSON node.
+    pub(crate) fn root_span(&self) -> Result<NodeSpan> {
+        let start = match self.next_significant(0) {
+            Some(start) => start,
+            None => bail!("expecting 1 node, but found 0"),
+        };
+
+        let (span, next) = self.span_at(start)?;
+        if let Some(i) = self.next_significant(next) {
+            bail!("unexpected tag: {}", JsonTag::to_string(&self.tags[i..]));
+        }
+
+        Ok(span)
+    }
+
+    /// Get span of the JSON node starting at specified tag, along with index of the tag after it.
+    fn span_at(&self, start: usize) -> Result<(NodeSpan, usize)> {
+        let close = match &self.tags[start] {
+            JsonTag::Literal(_, _) => {
+                let span = NodeSpan {
+                    start,
+                    end: start,
+                    entries: Vec::new(),
+                };
+                return Ok((span, start + 1));
+            }
+            JsonTag::LeftSquare => JsonTag::RightSquare,
+            JsonTag::LeftCurly => JsonTag::RightCurly,
+            _ => bail!(
+                "unexpected tag: {}",
+                JsonTag::to_string(&self.tags[start..])
+            ),
+        };
+
+        let mut entries = Vec::new();
+        let mut i = start + 1;
+        let end = loop {
+            i = self.expect_significant(i, start)?;
+            if self.tags[i] == close {
+                break i;
+            }
+            if self.tags[i] == JsonTag::Comma {
+                i += 1;
+                continue;
+            }
+
+            let entry_start = i;
+            if close == JsonTag::RightCurly {
+                if !matches!(self.tags[i], JsonTag::Literal(_, _)) {
+                    bail!("unexpected tag: {}", JsonTag::to_string(&self.tags[i..]));
+                }
+
+                i = self.expect_significant(i + 1, start)?;
+                if self.tags[i] == JsonTag::Colon {
+                    i = self.expect_significant(i + 1, start)?;
+                }
+            }
+
+            let (value, next) = self.span_at(i)?;
+            entries.push(EntrySpan {
+                start: entry_start,
+                value,
+            });
+            i = next;
+        };
+
+        let span = NodeSpan {
+            start,
+            end,
+            entries,
+        };
+        Ok((span, end + 1))
+    }
+
+    /// Get index of the first tag at or after specified index, which is neither whitespace nor comment.
+    fn next_significant(&self, from: usize) -> Option<usize> {
+        (from..self.tags.len()).find(|i| !self.tags[*i].is_trivia())
+    }
+
+    /// Get index of the next significant tag inside a container starting at specified tag, which must exist.
+    fn expect_significant(&self, from: usize, container_start: usize) -> Result<usize> {
+        match self.next_significant(from) {
+            Some(i) => Ok(i),
+            None => bail!(
+                "unclosed bracket: {}",
+                JsonTag::to_string(&self.tags[container_start..])
+            ),
+        }
+    }
+
+    /// Get ranges of tags to remove, for removing entries of specified indexes(sorted) from a container.
+    fn removal_ranges(&self, parent: &NodeSpan, indexes: &[usize]) -> Vec<Range<usize>> {
+        let entries = &parent.entries;
+        if indexes.len() >= entries.len() {
+            let inner = parent.start + 1..parent.end;
+            return vec![inner];
+        }
+
+        let mut ranges = Vec::new();
+        let mut run_start = 0;
+        while run_start < indexes.len() {
+            let mut run_end = run_start;
+            while run_end + 1 < indexes.len() && indexes[run_end + 1] == indexes[run_end] + 1 {
+                run_end += 1;
+            }
+
+            let (first, last) = (indexes[run_start], indexes[run_end]);
+            if last + 1 < entries.len() {
+                // remove the run along with the comma and trivia after it on the same line
+                let mut end = entries[last].value.end + 1;
+                if let Some(comma) = self.next_comma(entries[last].value.end) {
+                    end = comma + 1;
+                    while let Some(tag) = self.tags.get(end) {
+                        match tag {
+                            JsonTag::Whitespace(ws) if ws.contains('\n') => {
+                                end += 1;
+                                break;
+                            }
+                            JsonTag::Whitespace(_) => end += 1,
+                            JsonTag::Comment(c) if c.starts_with("//") => end += 1,
+                            _ => break,
+                        }
+                    }
+                }
+                ranges.push(entries[first].start..end);
+            } else {
+                // remove the run along with the comma before it
+                let prev_end = entries[first - 1].value.end;
+                let mut start = entries[first].start;
+                let comma = self.next_comma(prev_end);
+                if matches!(self.tags[start - 1], JsonTag::Whitespace(_))
+                    && comma.map_or(start - 1 > prev_end, |c| start - 1 > c)
+                {
+                    start -= 1;
+                }
+
+                ranges.push(start..entries[last].value.end + 1);
+                if let Some(comma) = comma {
+                    ranges.push(comma..comma + 1);
+                }
+            }
+
+            run_start = run_end + 1;
+        }
+
+        ranges
+    }
+
+    /// Get index of the comma right after the tag at specified index, skipping trivia.
+    fn next_comma(&self, after: usize) -> Option<usize> {
+        self.next_significant(after + 1)
+            .filter(|i| self.tags[*i] == JsonTag::Comma)
+    }
+
+    /// Get tags between a property name and its value to copy for a new property, or ": " if there is none.
+    fn colon_template(&self, span: &NodeSpan) -> Vec<JsonTag> {
+        match span.entries.first() {
+            Some(entry) => self.tags[entry.start + 1..entry.value.start]
+                .iter()
+                .filter(|t| !matches!(t, JsonTag::Comment(_)))
+                .cloned()
+                .collect(),
+            None => vec![JsonTag::Colon, JsonTag::Whitespace(String::from(" "))],
+        }
+    }
+
+    /// Get tags separating entries of a container to copy for a new entry, a comma followed by whitespace.
+    fn separator_template(&self, span: &NodeSpan) -> Vec<JsonTag> {
+        let ws_before = |entry: &EntrySpan| match &self.tags[entry.start - 1] {
+            JsonTag::Whitespace(ws) => Some(ws.clone()),
+            _ => None,
+        };
+
+        let ws = match span.entries.as_slice() {
+            [] => None,
+            [only] => ws_before(only).filter(|ws| ws.contains('\n')),
+            [_, second, ..] => ws_before(second),
+        };
+        let ws = match ws {
+            Some(ws) => ws,
+            None if span.entries.len() >= 2 => String::new(),
+            None => String::from(" "),
+        };
+
+        let mut separator = vec![JsonTag::Comma];
+        if !ws.is_empty() {
+            separator.push(JsonTag::Whitespace(ws));
+        }
+        separator
+    }
+
+    /// Get the edit inserting an entry into a container at specified index, separated by specified separator.
+    fn insertion(
+        &self,
+        span: &NodeSpan,
+        index: usize,
+        entry_tags: Vec<JsonTag>,
+        separator: Vec<JsonTag>,
+    ) -> (Range<usize>, Vec<JsonTag>) {
+        if span.entries.is_empty() {
+            // replace whitespace inside of empty container
+            let inner = span.start + 1..span.end;
+            if self.tags[inner.clone()]
+                .iter()
+                .all(|t| matches!(t, JsonTag::Whitespace(_)))
+            {
+                return (inner, entry_tags);
+            }
+
+            return (span.start + 1..span.start + 1, entry_tags);
+        }
+
+        if index == span.entries.len() {
+            let at = span.entries[index - 1].value.end + 1;
+            let mut tags = separator;
+            tags.extend(entry_tags);
+            (at..at, tags)
+        } else {
+            let at = span.entries[index].start;
+            let mut tags = entry_tags;
+            tags.extend(separator);
+            (at..at, tags)
+        }
+    }
+
+    /// Get indentation of a new entry of a container, which follows specified separator.
+    fn entry_indent(&self, span: &NodeSpan, separator: &[JsonTag]) -> String {
+        match separator.last() {
+            Some(JsonTag::Whitespace(ws)) if ws.contains('\n') => {
+                ws.rsplit('\n').next().unwrap_or("").to_string()
+            }
+            _ => self.line_indent(span.start),
+        }
+    }
+
+    /// Get indentation of the line where the tag at specified index is.
+    fn line_indent(&self, at: usize) -> String {
+        let mut line = String::new();
+        for tag in self.tags[..at].iter().rev() {
+            let text = JsonTag::to_raw_string(std::slice::from_ref(tag));
+            match text.rfind('\n') {
+                Some(i) => {
+                    line.insert_str(0, &text[i + 1..]);
+                    break;
+                }
+                None => line.insert_str(0, &text),
+            }
+        }
+
+        line.chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect()
+    }
+
+    /// Render a JSON node into tags to be written at a line with specified indentation.<br>
+    /// Containers are written pretty, following indentation and line ending of the document, if it is multi-line.
+    fn render(&self, value: &JsonNode, line_indent: &str) -> Result<Vec<JsonTag>> {
+        let mut line_breaks = self.tags.iter().filter_map(|t| match t {
+            JsonTag::Whitespace(ws) if ws.contains('\n') => Some(ws),
+            _ => None,
+        });
+
+        let text = match (value, line_breaks.next()) {
+            (JsonNode::Array(_) | JsonNode::Object(_), Some(first)) => {
+                let line_ending = if first.contains("\r\n") {
+                    LineEnding::CrLf
+                } else {
+                    LineEnding::Lf
+                };
+                let indent = self
+                    .tags
+                    .iter()
+                    .find_map(|t| match t {
+                        JsonTag::Whitespace(ws) => {
+                            let last_line = ws.rsplit('\n').next().unwrap_or("");
+                            match last_line.chars().next() {
+                                _ if !ws.contains('\n') => None,
+                                Some('\t') => Some(Indent::Tabs),
+                                Some(' ') => Some(Indent::Spaces(last_line.len())),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or(Indent::Spaces(4));
+
+                let options = WriteOptions {
+                    indent,
+                    line_ending,
+                    ..WriteOptions::pretty()
+                };
+                let line_ending = line_ending.as_str();
+                value
+                    .to_string_with_options(&options)
+                    .replace(line_ending, &format!("{}{}", line_ending, line_indent))
+            }
+            _ => value.to_string(),
+        };
+
+        JsonTag::parse_lossless_with_options(text.as_bytes(), &self.options)
+    }
+
+    /// Apply edits replacing ranges of tags, the ranges must not overlap.
+    fn apply(&mut self, mut edits: Vec<(Range<usize>, Vec<JsonTag>)>) {
+        edits.sort_by_key(|(range, _)| Reverse((range.start, range.end)));
+        for (range, tags) in edits {
+            self.tags.splice(range, tags);
+        }
+    }
+}
+
+impl fmt::Display for JsonDocument {
+    /// Implement Display trait for JsonDocument, writing the document text with all edits applied.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&JsonTag::to_raw_string(&self.tags))
+    }
+}
+
+#[cfg(test)]
+mod json_document_tests {
+    use super::*;
+
+    const JSON: &str = "{\n  // package name\n  \"name\": 'demo',\n  \"version\": \"1.0.0\", // bump on release\n  \"tags\": [\"a\", \"b\"],\n  \"deps\": {}\n}\n";
+
+    /// Test setting values keeps the rest of the document untouched.
+    #[test]
+    fn test_set() -> Result<()> {
+        let mut document = JsonDocument::parse(JSON.as_bytes())?;
+        assert_eq!(document.to_string(), JSON);
+
+        document.set_str("$.version", "1.0.1")?;
+        document.set_raw(
+            "$.deps",
+            &JsonNode::parse_single_node(r#"{"serde": "1", "anyhow": "1"}"#.as_bytes())?,
+        )?;
+        document.set_bool("$.tags[1]", true)?;
+        assert_eq!(
+            document.to_string(),
+            "{\n  // package name\n  \"name\": 'demo',\n  \"version\": \"1.0.1\", // bump on release\n  \"tags\": [\"a\", true],\n  \"deps\": {\n    \"serde\": \"1\",\n    \"anyhow\": \"1\"\n  }\n}\n"
+        );
+        assert_eq!(
+            document.to_node()?.get_str("$.deps.serde")?,
+            Some(String::from("1"))
+        );
+
+        Ok(())
+    }
+
+    /// Test removing properties and elements along with their commas.
+    #[test]
+    fn test_remove() -> Result<()> {
+        let mut document = JsonDocument::parse(JSON.as_bytes())?;
+        document.remove("$.version")?;
+        document.remove("$.tags[0]")?;
+        document.remove("$.deps")?;
+        assert_eq!(
+            document.to_string(),
+            "{\n  // package name\n  \"name\": 'demo',\n  \"tags\": [\"b\"]\n}\n"
+        );
+
+        let mut document = JsonDocument::parse("[1, 2, 3, 4]".as_bytes())?;
+        document.remove("$[1:]")?;
+        assert_eq!(document.to_string(), "[1]");
+        document.remove("$[*]")?;
+        assert_eq!(document.to_string(), "[]");
+        assert!(document.remove("$").is_err());
+
+        Ok(())
+    }
+
+    /// Test inserting properties and elements following the layout of their siblings.
+    #[test]
+    fn test_insert() -> Result<()> {
+        let mut document = JsonDocument::parse(JSON.as_bytes())?;
+        document.insert("$", "private", &JsonNode::PlainBoolean(true))?;
+        document.insert(
+            "$.deps",
+            "anyhow",
+            &JsonNode::PlainString(String::from("1")),
+        )?;
+        document.insert_element("$.tags", 0, &JsonNode::PlainString(String::from("z")))?;
+        document.insert_element("$.tags", 3, &JsonNode::PlainNull)?;
+        assert_eq!(
+            document.to_string(),
+            "{\n  // package name\n  \"name\": 'demo',\n  \"version\": \"1.0.0\", // bump on release\n  \"tags\": [\"z\", \"a\", \"b\", null],\n  \"deps\": {\"anyhow\": \"1\"},\n  \"private\": true\n}\n"
+        );
+        assert!(document
+            .insert("$", "private", &JsonNode::PlainNull)
+            .is_err());
+        assert!(document
+            .insert_element("$.tags", 9, &JsonNode::PlainNull)
+            .is_err());
+
+        Ok(())
+    }
+
+    /// Test malformed input returns error rather than panicking, with cases found by fuzzing.
+    #[test]
+    fn test_malformed_document() -> Result<()> {
+        for json in [
+            "}{",
+            "{:}",
+            "{\"a\" 1 2}",
+            "{\"a\": [1}, 2]",
+            "[\"\\uD83D\", '\\u', \"\\\"]",
+            "[0x, -., 1e, .e1, tru, True, NaN, -NaN]",
+            "[\u{feff}1, \u{2028}, \"é😀\0\"]",
+            "/* [1",
+            "// x\r[",
+            "{\"a\": {\"b\": [,]]",
+        ] {
+            if let Ok(mut document) = JsonDocument::parse(json.as_bytes()) {
+                let _ = document.remove("$[0]");
+                let _ = document.set_null("$.a");
+                let _ = document.to_string();
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,618 @@
+//! SAX-style streaming reader and writer of JSON events: start and end of objects and arrays,
+//! property names, and plain values, validated as they go.
+
+use anyhow::{bail, Result};
+use std::io::{Read, Write};
+
+use crate::highlight::*;
+use crate::json_node::*;
+use crate::json_tag::*;
+use crate::json_writer::*;
+use crate::limits::*;
+use crate::parse_options::*;
+
+/// JSON event, read from or written to a stream of JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonEvent {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// Name of an object property, followed by the events of its value.
+    Key(String),
+    /// Plain value(null, bool, number, or string). The event writer also accepts objects and arrays.
+    Value(JsonNode),
+}
+
+/// Object or array being read by the event reader.
+struct ReadFrame {
+    is_object: bool,
+    /// Count of entries read so far.
+    count: usize,
+    after_comma: bool,
+    /// Whether a property name has been read, and its value not yet.
+    after_key: bool,
+    /// JSONPath fragment of the current entry, such as ['name'] or \[0\].
+    path_frag: String,
+}
+
+/// Streaming reader of JSON events, reads JSON tags one at a time and yields one event at a time,
+/// validating the structure as specified by options.<br>
+/// The input must contain exactly 1 JSON value. Limits on input length and tag count apply to the tags
+/// read for each event instead of the whole input, other limits apply as in parsing. Reading stops at the first error.
+pub struct JsonEventReader<R: Read> {
+    json_tag_reader: JsonTagReader<R>,
+    /// JSON tag read ahead but not consumed yet.
+    peeked: Option<JsonTag>,
+    /// Count of bytes read before the current event.
+    event_start: usize,
+    /// Count of JSON tags read for the current event.
+    event_tags: usize,
+    options: ParseOptions,
+    frames: Vec<ReadFrame>,
+    depth: usize,
+    is_root_read: bool,
+    is_done: bool,
+}
+
+impl<R: Read> JsonEventReader<R> {
+    /// Create a JSON event reader, with default options.
+    pub fn new(reader: R) -> Self {
+        JsonEventReader::with_options(reader, &ParseOptions::default())
+    }
+
+    /// Create a JSON event reader, with specified options.
+    pub fn with_options(reader: R, options: &ParseOptions) -> Self {
+        let tag_options = ParseOptions {
+            limits: Limits {
+                max_input_len: None,
+                max_tags: None,
+                ..options.limits.clone()
+            },
+            ..options.clone()
+        };
+
+        JsonEventReader {
+            json_tag_reader: JsonTagReader::with_options(reader, &tag_options),
+            peeked: None,
+            event_start: 0,
+            event_tags: 0,
+            options: options.clone(),
+            frames: Vec::new(),
+            depth: 0,
+            is_root_read: false,
+            is_done: false,
+        }
+    }
+
+    /// Nesting depth of the value of the last event, 0 for the top-level value.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Normalized JSONPath of the value of the last event, such as $\['data'\]\[0\].<br>
+    /// For a Key event, it is the path of the property.
+    pub fn path(&self) -> String {
+        let mut path = String::from("$");
+        for frame in &self.frames {
+            path.push_str(&frame.path_frag);
+        }
+
+        path
+    }
+
+    /// Read the next JSON tag, either the one read ahead or a new one, checking limits on the current event.
+    fn read_tag(&mut self) -> Result<Option<JsonTag>> {
+        if let Some(json_tag) = self.peeked.take() {
+            return Ok(Some(json_tag));
+        }
+
+        let json_tag = self.json_tag_reader.next().transpose()?;
+        let limits = &self.options.limits;
+        let bytes_read = self.json_tag_reader.bytes_read();
+        limits.check_input_len(bytes_read.saturating_sub(self.event_start))?;
+        if json_tag.is_some() {
+            self.event_tags += 1;
+            limits.check_tags(self.event_tags)?;
+        }
+
+        Ok(json_tag)
+    }
+
+    /// Read a value starting with specified tag, the top frame has been updated for it.
+    fn read_value(&mut self, json_tag: JsonTag) -> Result<JsonEvent> {
+        self.depth = self.frames.len();
+        let json_event = match json_tag {
+            JsonTag::LeftCurly | JsonTag::LeftSquare => {
+                let is_object = json_tag == JsonTag::LeftCurly;
+                self.frames.push(ReadFrame {
+                    is_object,
+                    count: 0,
+                    after_comma: false,
+                    after_key: false,
+                    path_frag: String::new(),
+                });
+                return Ok(if is_object {
+                    JsonEvent::StartObject
+                } else {
+                    JsonEvent::StartArray
+                });
+            }
+            JsonTag::Literal(literal, kind) => {
+                self.options
+                    .limits
+                    .check_literal_len(literal.chars().count())?;
+                JsonEvent::Value(JsonNode::parse_plain(&literal, kind, &self.options)?)
+            }
+            _ => bail!("unexpected tag: {}", JsonTag::to_string(&[json_tag])),
+        };
+
+        self.end_value();
+        Ok(json_event)
+    }
+
+    /// Mark the current value as read, either an entry of the top frame or the top-level value.
+    fn end_value(&mut self) {
+        match self.frames.last_mut() {
+            Some(frame) => {
+                frame.count += 1;
+                frame.after_comma = false;
+                frame.after_key = false;
+            }
+            None => self.is_root_read = true,
+        }
+    }
+
+    /// Read the end of the top frame, checking trailing comma.
+    fn read_end(&mut self) -> Result<JsonEvent> {
+        let frame = self.frames.pop();
+        let is_object = frame.as_ref().is_some_and(|f| f.is_object);
+        if frame.is_some_and(|f| f.after_comma)
+            && !self.options.allow_extra_commas
+            && !self.options.allow_trailing_commas
+        {
+            bail!("unexpected trailing comma");
+        }
+
+        self.depth = self.frames.len();
+        self.end_value();
+        Ok(if is_object {
+            JsonEvent::EndObject
+        } else {
+            JsonEvent::EndArray
+        })
+    }
+
+    /// Read a comma between entries of the top frame.
+    fn read_comma(&mut self) -> Result<()> {
+        let allow_extra_commas = self.options.allow_extra_commas;
+        let frame = match self.frames.last_mut() {
+            Some(frame) if !frame.after_key => frame,
+            _ => bail!("unexpected comma"),
+        };
+        if (frame.count == 0 || frame.after_comma) && !allow_extra_commas {
+            bail!("unexpected comma");
+        }
+
+        frame.after_comma = true;
+        Ok(())
+    }
+
+    /// Check the comma before a new entry of the top frame.
+    fn check_comma_before(&self, json_tag: &JsonTag) -> Result<()> {
+        if let Some(frame) = self.frames.last() {
+            if frame.count > 0 && !frame.after_comma && !self.options.allow_missing_commas {
+                bail!(
+                    "expecting comma: {}",
+                    JsonTag::to_string(std::slice::from_ref(json_tag))
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the next JSON event, or None at the end of input.
+    fn read_next(&mut self) -> Result<Option<JsonEvent>> {
+        self.event_start = self.json_tag_reader.bytes_read();
+        self.event_tags = 0;
+        loop {
+            let json_tag = match self.read_tag()? {
+                Some(json_tag) => json_tag,
+                None if !self.frames.is_empty() => bail!("unexpected end of json"),
+                None if !self.is_root_read => bail!("expecting 1 node, but found 0"),
+                None => return Ok(None),
+            };
+
+            let is_value = matches!(
+                json_tag,
+                JsonTag::Literal(_, _) | JsonTag::LeftCurly | JsonTag::LeftSquare
+            );
+            let (is_object, after_key) = match self.frames.last() {
+                None if !is_value && self.options.allow_stray_tags => continue,
+                None if self.is_root_read => bail!("expecting 1 node, but found more"),
+                None => return self.read_value(json_tag).map(Some),
+                Some(frame) => (frame.is_object, frame.after_key),
+            };
+            match json_tag {
+                JsonTag::Comma => self.read_comma()?,
+                JsonTag::RightCurly if is_object && !after_key => return self.read_end().map(Some),
+                JsonTag::RightSquare if !is_object => return self.read_end().map(Some),
+
+                // property name of object
+                JsonTag::Literal(literal, kind) if is_object && !after_key => {
+                    self.check_comma_before(&JsonTag::Literal(literal.clone(), kind))?;
+                    let name = JsonNode::parse_prop_name(&literal, kind, &self.options)?;
+                    let count = self.frames.last().map_or(0, |f| f.count);
+                    self.options.limits.check_object_props(count + 1)?;
+                    if let Some(frame) = self.frames.last_mut() {
+                        frame.after_key = true;
+                        frame.path_frag = format!("['{}']", name);
+                    }
+
+                    match self.read_tag()? {
+                        Some(JsonTag::Colon) => (),
+                        Some(json_tag) if self.options.allow_missing_colons => {
+                            self.peeked = Some(json_tag)
+                        }
+                        Some(json_tag) => {
+                            bail!("expecting colon: {}", JsonTag::to_string(&[json_tag]))
+                        }
+                        None => bail!("unexpected end of json"),
+                    }
+
+                    self.depth = self.frames.len();
+                    return Ok(Some(JsonEvent::Key(name)));
+                }
+
+                // value of object property
+                _ if after_key => return self.read_value(json_tag).map(Some),
+
+                // element of array
+                JsonTag::Literal(_, _) | JsonTag::LeftCurly | JsonTag::LeftSquare if !is_object => {
+                    self.check_comma_before(&json_tag)?;
+                    if let Some(frame) = self.frames.last_mut() {
+                        frame.path_frag = format!("[{}]", frame.count);
+                    }
+
+                    return self.read_value(json_tag).map(Some);
+                }
+
+                JsonTag::Colon | JsonTag::RightCurly | JsonTag::RightSquare
+                    if self.options.allow_stray_tags => {}
+                _ => bail!("unexpected tag: {}", JsonTag::to_string(&[json_tag])),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for JsonEventReader<R> {
+    type Item = Result<JsonEvent>;
+
+    /// Read the next JSON event, or an error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        let json_event = self.read_next();
+        self.is_done = !matches!(json_event, Ok(Some(_)));
+        json_event.transpose()
+    }
+}
+
+/// Object or array being written by the event writer.
+struct WriteFrame {
+    is_object: bool,
+    /// Count of entries written so far.
+    count: usize,
+    /// Whether a property name has been written, and its value not yet.
+    after_key: bool,
+}
+
+/// Streaming writer of JSON events, writes each event as it comes, formatted as specified by write options,
+/// and bails on an event out of place, such as a Key event in an array.<br>
+/// Objects and arrays started by events are always broken into lines in pretty output, and their properties
+/// are written in the order of events, so max_width and sort_keys only apply to nodes of Value events.
+pub struct JsonEventWriter<W: Write> {
+    writer: W,
+    options: WriteOptions,
+    frames: Vec<WriteFrame>,
+    /// Whether anything has been written, byte order mark is written before the first output.
+    is_started: bool,
+    is_root_written: bool,
+}
+
+impl<W: Write> JsonEventWriter<W> {
+    /// Create a JSON event writer from an instance which implements trait std::io::Write and write options.
+    pub fn new(writer: W, options: WriteOptions) -> Self {
+        JsonEventWriter {
+            writer,
+            options,
+            frames: Vec::new(),
+            is_started: false,
+            is_root_written: false,
+        }
+    }
+
+    /// Whether a complete top-level JSON value has been written.
+    pub fn is_complete(&self) -> bool {
+        self.is_root_written && self.frames.is_empty()
+    }
+
+    /// Write a JSON event.
+    pub fn write_event(&mut self, json_event: &JsonEvent) -> Result<()> {
+        let level = self.frames.len();
+        let (is_object, after_key, count) = match self.frames.last() {
+            None if self.is_root_written => bail!("expecting 1 node, but found more"),
+            None => (false, true, 0),
+            Some(frame) => (frame.is_object, frame.after_key, frame.count),
+        };
+
+        let options = &self.options;
+        if !self.is_started && options.byte_order_mark {
+            options.encoding.write_str(&mut self.writer, "\u{feff}")?;
+        }
+        self.is_started = true;
+
+        match json_event {
+            JsonEvent::Key(name) => {
+                if !is_object || after_key {
+                    bail!("unexpected key event: {}", name);
+                }
+
+                write_fmt_to(&mut self.writer, options.encoding, |f| {
+                    if count > 0 {
+                        options.write_comma(f)?;
+                    }
+                    options.write_line_break(f, level)?;
+                    options.write_styled(f, Style::Key, |f| options.write_prop_name(f, name))?;
+                    options.write_colon(f)
+                })?;
+                if let Some(frame) = self.frames.last_mut() {
+                    frame.after_key = true;
+                }
+            }
+
+            JsonEvent::EndObject | JsonEvent::EndArray => {
+                let is_end_object = *json_event == JsonEvent::EndObject;
+                if level == 0 || is_object != is_end_object || after_key {
+                    bail!("unexpected end event: {:?}", json_event);
+                }
+
+                write_fmt_to(&mut self.writer, options.encoding, |f| {
+                    if count > 0 {
+                        options.write_trailing_comma(f)?;
+                        options.write_line_break(f, level - 1)?;
+                    }
+                    options.write_bracket(f, if is_end_object { '}' } else { ']' })
+                })?;
+                self.frames.pop();
+                self.end_value()?;
+            }
+
+            JsonEvent::StartObject | JsonEvent::StartArray | JsonEvent::Value(_) => {
+                if is_object && !after_key {
+                    bail!("expecting key event, but found: {:?}", json_event);
+                }
+
+                write_fmt_to(&mut self.writer, options.encoding, |f| {
+                    if !is_object && level > 0 {
+                        if count > 0 {
+                            options.write_comma(f)?;
+                        }
+                        options.write_line_break(f, level)?;
+                    }
+                    match json_event {
+                        JsonEvent::StartObject => options.write_bracket(f, '{'),
+                        JsonEvent::StartArray => options.write_bracket(f, '['),
+                        JsonEvent::Value(json_node) => json_node.fmt_indent(f, level, options),
+                        _ => Ok(()),
+                    }
+                })?;
+                match json_event {
+                    JsonEvent::Value(_) => self.end_value()?,
+                    _ => self.frames.push(WriteFrame {
+                        is_object: *json_event == JsonEvent::StartObject,
+                        count: 0,
+                        after_key: false,
+                    }),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mark the current value as written, followed by a line ending if it is the top-level value and configured.
+    fn end_value(&mut self) -> Result<()> {
+        match self.frames.last_mut() {
+            Some(frame) => {
+                frame.count += 1;
+                frame.after_key = false;
+            }
+            None => {
+                self.is_root_written = true;
+                if self.options.trailing_newline {
+                    let line_ending = self.options.line_ending.as_str();
+                    self.options
+                        .encoding
+                        .write_str(&mut self.writer, line_ending)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flush internal writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Get internal writer back.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod json_event_tests {
+    use super::*;
+
+    /// Test reading JSON events, along with their depth and path.
+    #[test]
+    fn test_read_events() -> Result<()> {
+        let json = r#"{"a": [1, {"b": null}], "c": "x"}"#;
+        let mut reader = JsonEventReader::new(json.as_bytes());
+        let mut events = Vec::new();
+        while let Some(json_event) = reader.next().transpose()? {
+            events.push((json_event, reader.depth(), reader.path()));
+        }
+
+        let value =
+            |json: &str| JsonEvent::Value(JsonNode::parse_single_node(json.as_bytes()).unwrap());
+        let expected = vec![
+            (JsonEvent::StartObject, 0, "$"),
+            (JsonEvent::Key(String::from("a")), 1, "$['a']"),
+            (JsonEvent::StartArray, 1, "$['a']"),
+            (value("1"), 2, "$['a'][0]"),
+            (JsonEvent::StartObject, 2, "$['a'][1]"),
+            (JsonEvent::Key(String::from("b")), 3, "$['a'][1]['b']"),
+            (value("null"), 3, "$['a'][1]['b']"),
+            (JsonEvent::EndObject, 2, "$['a'][1]"),
+            (JsonEvent::EndArray, 1, "$['a']"),
+            (JsonEvent::Key(String::from("c")), 1, "$['c']"),
+            (value("\"x\""), 1, "$['c']"),
+            (JsonEvent::EndObject, 0, "$"),
+        ];
+        let expected: Vec<(JsonEvent, usize, String)> = expected
+            .into_iter()
+            .map(|(e, d, p)| (e, d, String::from(p)))
+            .collect();
+        assert_eq!(events, expected);
+        Ok(())
+    }
+
+    /// Test validating structure while reading JSON events.
+    #[test]
+    fn test_read_invalid() -> Result<()> {
+        let strict = ParseOptions::strict();
+        for json in [
+            "",
+            "[1, 2",
+            "[1 2]",
+            "[1,]",
+            "{\"a\" 1}",
+            "{\"a\": }",
+            "{\"a\": 1,}",
+            "{1: 2}",
+            "[1] 2",
+            "{]",
+            "[}",
+            "{\"a\": 1 \"b\": 2}",
+        ] {
+            let result =
+                JsonEventReader::with_options(json.as_bytes(), &strict).collect::<Result<Vec<_>>>();
+            assert!(result.is_err(), "{}", json);
+        }
+
+        let events =
+            JsonEventReader::new("{a 1, b: [2 3,]}".as_bytes()).collect::<Result<Vec<_>>>()?;
+        assert_eq!(events.len(), 9);
+        Ok(())
+    }
+
+    /// Test limits on input length and tag count applying to each event instead of the whole input.
+    #[test]
+    fn test_read_limits_per_event() -> Result<()> {
+        let options = ParseOptions {
+            limits: Limits {
+                max_input_len: Some(16),
+                max_tags: Some(4),
+                ..Limits::default()
+            },
+            ..ParseOptions::default()
+        };
+        let json = format!("[{}]", vec!["{\"a\": 1}"; 100].join(", "));
+        let events =
+            JsonEventReader::with_options(json.as_bytes(), &options).collect::<Result<Vec<_>>>()?;
+        assert_eq!(events.len(), 402);
+
+        let json = r#"[1, "a string longer than the limit"]"#;
+        let err = JsonEventReader::with_options(json.as_bytes(), &options)
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::InputTooLarge(16))
+        );
+
+        let json = "[1,,,,,2]";
+        let err = JsonEventReader::with_options(json.as_bytes(), &options)
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::TooManyTags(4))
+        );
+        Ok(())
+    }
+
+    /// Test writing JSON events, re-emitting the events read with modifications.
+    #[test]
+    fn test_write_events() -> Result<()> {
+        let json = r#"{"a": [1, {"b": null}, []], "secret": {"x": 1}, "c": "x"}"#;
+        for options in [
+            WriteOptions::default(),
+            WriteOptions::compact(),
+            WriteOptions::pretty(),
+        ] {
+            let mut writer = JsonEventWriter::new(Vec::new(), options.clone());
+            for json_event in JsonEventReader::new(json.as_bytes()) {
+                writer.write_event(&json_event?)?;
+            }
+            assert!(writer.is_complete());
+
+            let expected =
+                JsonNode::parse_single_node(json.as_bytes())?.to_string_with_options(&options);
+            assert_eq!(String::from_utf8(writer.into_inner())?, expected);
+        }
+
+        // drop the secret property and its value
+        let mut reader = JsonEventReader::new(json.as_bytes());
+        let mut writer = JsonEventWriter::new(Vec::new(), WriteOptions::compact());
+        let mut skip_depth = None;
+        while let Some(json_event) = reader.next().transpose()? {
+            if let Some(depth) = skip_depth {
+                // the value ends with a plain value or the end of object or array at the same depth
+                let is_start = matches!(json_event, JsonEvent::StartObject | JsonEvent::StartArray);
+                if reader.depth() == depth && !is_start {
+                    skip_depth = None;
+                }
+                continue;
+            }
+            if json_event == JsonEvent::Key(String::from("secret")) {
+                skip_depth = Some(reader.depth());
+                continue;
+            }
+
+            writer.write_event(&json_event)?;
+        }
+        let output = String::from_utf8(writer.into_inner())?;
+        assert_eq!(output, r#"{"a":[1,{"b":null},[]],"c":"x"}"#);
+
+        let mut writer = JsonEventWriter::new(Vec::new(), WriteOptions::default());
+        writer.write_event(&JsonEvent::StartArray)?;
+        assert!(writer
+            .write_event(&JsonEvent::Key(String::from("a")))
+            .is_err());
+        assert!(writer.write_event(&JsonEvent::EndObject).is_err());
+        writer.write_event(&JsonEvent::EndArray)?;
+        assert!(writer
+            .write_event(&JsonEvent::Value(JsonNode::PlainNull))
+            .is_err());
+        Ok(())
+    }
+}
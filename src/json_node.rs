@@ -4,10 +4,15 @@ use anyhow::{bail, Result};
 use std::{
     fmt::{self, Formatter},
     io::Read,
-    str::FromStr,
 };
 
+use crate::highlight::*;
+use crate::json_number::*;
+use crate::json_string::*;
 use crate::json_tag::*;
+use crate::json_writer::*;
+use crate::limits::*;
+use crate::parse_options::*;
 
 /// JSON object property
 #[derive(Debug, PartialEq, Clone)]
@@ -28,45 +33,117 @@ impl JsonObjProp {
 pub enum JsonNode {
     PlainNull,
     PlainString(String),
-    PlainNumber(f64),
+    PlainNumber(JsonNumber),
     PlainBoolean(bool),
     Array(Vec<JsonNode>),
     Object(Vec<JsonObjProp>),
 }
 
 impl JsonNode {
-    /// Parse a single JSON node from a instance that implements Reader trait.
+    /// Parse a single JSON node from a instance that implements Reader trait, with default limits.
     pub fn parse_single_node<R>(reader: R) -> Result<JsonNode>
     where
         R: Read,
     {
-        let mut nodes = JsonNode::parse(reader)?;
+        JsonNode::parse_single_node_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Parse a single JSON node from a instance that implements Reader trait, with specified limits.
+    pub fn parse_single_node_with_limits<R>(reader: R, limits: &Limits) -> Result<JsonNode>
+    where
+        R: Read,
+    {
+        JsonNode::parse_single_node_with_options(reader, &ParseOptions::with_limits(limits))
+    }
+
+    /// Parse a single JSON node from a instance that implements Reader trait, with specified options.
+    pub fn parse_single_node_with_options<R>(reader: R, options: &ParseOptions) -> Result<JsonNode>
+    where
+        R: Read,
+    {
+        let mut nodes = JsonNode::parse_with_options(reader, options)?;
         if 1 != nodes.len() {
-            bail!("more than 1 node found");
+            bail!("expecting 1 node, but found {}", nodes.len());
         }
 
         let n = nodes.remove(0);
         Ok(n)
     }
 
-    /// Parse JSON nodes from a instance that implements Reader trait.
+    /// Parse JSON nodes from a instance that implements Reader trait, with default limits.
     pub fn parse<R>(reader: R) -> Result<Vec<JsonNode>>
     where
         R: Read,
     {
-        let tags = JsonTag::parse(reader)?;
-        let nodes = JsonNode::parse_tags(&tags)?;
+        JsonNode::parse_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Parse JSON nodes from a instance that implements Reader trait, with specified limits.
+    pub fn parse_with_limits<R>(reader: R, limits: &Limits) -> Result<Vec<JsonNode>>
+    where
+        R: Read,
+    {
+        JsonNode::parse_with_options(reader, &ParseOptions::with_limits(limits))
+    }
+
+    /// Parse JSON nodes from a instance that implements Reader trait, with specified options.
+    pub fn parse_with_options<R>(reader: R, options: &ParseOptions) -> Result<Vec<JsonNode>>
+    where
+        R: Read,
+    {
+        let tags = JsonTag::parse_with_options(reader, options)?;
+        let nodes = JsonNode::parse_tags_with_options(&tags, options)?;
         Ok(nodes)
     }
 
-    /// Parse JSON nodes from a JSON tag slice.
+    /// Parse JSON nodes from a JSON tag slice, with default limits.
     pub fn parse_tags(json_tags: &[JsonTag]) -> Result<Vec<JsonNode>> {
+        JsonNode::parse_tags_with_options(json_tags, &ParseOptions::default())
+    }
+
+    /// Parse JSON nodes from a JSON tag slice, with specified limits.
+    pub fn parse_tags_with_limits(json_tags: &[JsonTag], limits: &Limits) -> Result<Vec<JsonNode>> {
+        JsonNode::parse_tags_with_options(json_tags, &ParseOptions::with_limits(limits))
+    }
+
+    /// Parse JSON nodes from a JSON tag slice, with specified options.
+    pub fn parse_tags_with_options(
+        json_tags: &[JsonTag],
+        options: &ParseOptions,
+    ) -> Result<Vec<JsonNode>> {
+        options.limits.check_tags(json_tags.len())?;
+        if !options.allow_comments {
+            if let Some(JsonTag::Comment(comment)) =
+                json_tags.iter().find(|t| matches!(t, JsonTag::Comment(_)))
+            {
+                bail!("unexpected comment: {}", comment);
+            }
+        }
+        if json_tags.iter().any(JsonTag::is_trivia) {
+            let json_tags: Vec<JsonTag> = json_tags
+                .iter()
+                .filter(|t| !t.is_trivia())
+                .cloned()
+                .collect();
+            return JsonNode::parse_tags_at(&json_tags, options, 0);
+        }
+
+        JsonNode::parse_tags_at(json_tags, options, 0)
+    }
+
+    /// Parse JSON nodes from a JSON tag slice, which is nested at specified depth.
+    fn parse_tags_at(
+        json_tags: &[JsonTag],
+        options: &ParseOptions,
+        depth: usize,
+    ) -> Result<Vec<JsonNode>> {
         let mut i = 0;
         let mut json_nodes = Vec::new();
         while i < json_tags.len() {
             match &json_tags[i] {
-                JsonTag::Literal(literal) => {
-                    let plain_node = JsonNode::parse_plain(literal)?;
+                JsonTag::Literal(literal, kind) => {
+                    options.limits.check_literal_len(literal.chars().count())?;
+                    let plain_node = JsonNode::parse_plain(literal, *kind, options)?;
                     json_nodes.push(plain_node);
 
                     i += 1;
@@ -74,6 +151,7 @@ impl JsonNode {
                 }
 
                 JsonTag::LeftSquare => {
+                    options.limits.check_depth(depth + 1)?;
                     let right_square_i = JsonNode::find_match_tag(
                         json_tags,
                         i,
@@ -81,7 +159,8 @@ impl JsonNode {
                         JsonTag::RightSquare,
                     )?;
 
-                    let array_node = JsonNode::parse_array(&json_tags[i..=right_square_i])?;
+                    let array_node =
+                        JsonNode::parse_array(&json_tags[i..=right_square_i], options, depth + 1)?;
                     json_nodes.push(array_node);
 
                     i = right_square_i + 1;
@@ -89,6 +168,7 @@ impl JsonNode {
                 }
 
                 JsonTag::LeftCurly => {
+                    options.limits.check_depth(depth + 1)?;
                     let right_curly_i = JsonNode::find_match_tag(
                         json_tags,
                         i,
@@ -96,7 +176,8 @@ impl JsonNode {
                         JsonTag::RightCurly,
                     )?;
 
-                    let object_node = JsonNode::parse_object(&json_tags[i..=right_curly_i])?;
+                    let object_node =
+                        JsonNode::parse_object(&json_tags[i..=right_curly_i], options, depth + 1)?;
                     json_nodes.push(object_node);
 
                     i = right_curly_i + 1;
@@ -104,6 +185,10 @@ impl JsonNode {
                 }
 
                 _ => {
+                    if !options.allow_stray_tags {
+                        bail!("unexpected tag: {}", JsonTag::to_string(&json_tags[i..]));
+                    }
+
                     i += 1;
                     continue;
                 }
@@ -114,12 +199,19 @@ impl JsonNode {
     }
 
     /// Parse a single JSON node from a JSON tag slice, starts at specified index.
-    fn parse_next(json_tags: &[JsonTag], start: &mut usize) -> Result<Option<JsonNode>> {
+    fn parse_next(
+        json_tags: &[JsonTag],
+        start: &mut usize,
+        options: &ParseOptions,
+        depth: usize,
+    ) -> Result<Option<JsonNode>> {
         let i = *start;
         let node = match &json_tags[i] {
-            JsonTag::Literal(_) => {
+            JsonTag::Literal(_, _) => {
                 *start += 1;
-                JsonNode::parse_tags(&json_tags[i..=i])?.into_iter().next()
+                JsonNode::parse_tags_at(&json_tags[i..=i], options, depth)?
+                    .into_iter()
+                    .next()
             }
             JsonTag::LeftSquare => {
                 let right_square_i = JsonNode::find_match_tag(
@@ -130,7 +222,7 @@ impl JsonNode {
                 )?;
 
                 *start = right_square_i + 1;
-                JsonNode::parse_tags(&json_tags[i..=right_square_i])?
+                JsonNode::parse_tags_at(&json_tags[i..=right_square_i], options, depth)?
                     .into_iter()
                     .next()
             }
@@ -143,7 +235,7 @@ impl JsonNode {
                 )?;
 
                 *start = right_curly_i + 1;
-                JsonNode::parse_tags(&json_tags[i..=right_curly_i])?
+                JsonNode::parse_tags_at(&json_tags[i..=right_curly_i], options, depth)?
                     .into_iter()
                     .next()
             }
@@ -186,37 +278,120 @@ impl JsonNode {
         Ok(i)
     }
 
-    /// Parse a plain data type JSON node(null, bool, number, or string) from a literal string.
-    fn parse_plain(literal: &str) -> Result<JsonNode> {
-        let plain_node = match literal {
-            str if str.chars().all(|c| c.is_numeric() || c == '.')
-                && str.chars().filter(|c| *c == '.').count() <= 1 =>
-            {
-                JsonNode::PlainNumber(f64::from_str(str)?)
+    /// Parse a plain data type JSON node(null, bool, number, or string) from a literal string of specified kind.
+    pub(crate) fn parse_plain(
+        literal: &str,
+        kind: LiteralKind,
+        options: &ParseOptions,
+    ) -> Result<JsonNode> {
+        let plain_node = match kind {
+            _ if !kind.is_allowed(literal, options) => bail!("unexpected literal: {}", literal),
+            LiteralKind::Number | LiteralKind::MalformedNumber => {
+                match JsonNumber::parse(literal, options)? {
+                    Some(number) => JsonNode::PlainNumber(number),
+                    None => bail!("unexpected literal: {}", literal),
+                }
+            }
+            LiteralKind::Boolean => JsonNode::PlainBoolean(literal.eq_ignore_ascii_case("true")),
+            LiteralKind::Null => JsonNode::PlainNull,
+            LiteralKind::String(_) => {
+                JsonNode::PlainString(JsonNode::parse_quoted(literal, options)?)
             }
+            LiteralKind::BareWord => JsonNode::PlainString(String::from(literal)),
+        };
 
-            "true" | "True" | "TRUE" => JsonNode::PlainBoolean(true),
-            "false" | "False" | "FALSE" => JsonNode::PlainBoolean(false),
+        Ok(plain_node)
+    }
 
-            "null" | "Null" | "NULL" => JsonNode::PlainNull,
+    /// Parse a quoted string literal, the quotes are trimmed and the escapes are decoded.
+    /// If invalid strings not allowed, the string must be closed by the same quote,
+    /// and must not contain unescaped control chars or invalid escapes.
+    pub(crate) fn parse_quoted(literal: &str, options: &ParseOptions) -> Result<String> {
+        let (prefix, literal) = if options.allow_python_literals {
+            literal.split_at(string_prefix_len(literal))
+        } else {
+            ("", literal)
+        };
+        let quote = literal.chars().next().unwrap_or('"');
+        let is_closed = literal.len() > 1 && literal.ends_with(quote);
+        if !options.allow_invalid_strings {
+            if !is_closed {
+                bail!("unterminated string: {}", literal);
+            }
 
-            _ => {
-                if ((literal.starts_with('\'') && literal.ends_with('\''))
-                    || (literal.starts_with('"') && literal.ends_with('"')))
-                    && literal.len() > 1
-                {
-                    JsonNode::PlainString(String::from(&literal[1..literal.len() - 1]))
-                } else {
-                    JsonNode::PlainString(String::from(literal))
+            let mut is_escape = false;
+            let mut is_continuation = false;
+            for c in literal[1..literal.len() - 1].chars() {
+                match c {
+                    c if c < '\u{20}' => {
+                        // JSON5 strings accept control chars, and line breaks only if escaped
+                        let is_line_break = c == '\r' || c == '\n';
+                        let allowed = (options.allow_json5_strings || options.allow_python_strings)
+                            && (!is_line_break || is_escape || is_continuation);
+                        if !allowed {
+                            bail!("unescaped control char in string: {}", literal);
+                        }
+                    }
+                    c if c == quote && !is_escape => {
+                        bail!("unexpected quote in string: {}", literal)
+                    }
+                    _ => (),
                 }
+
+                is_continuation = c == '\r' && is_escape;
+                is_escape = c == '\\' && !is_escape;
             }
-        };
+        }
 
-        Ok(plain_node)
+        if is_closed {
+            let inner = &literal[1..literal.len() - 1];
+            if prefix.contains(['r', 'R']) {
+                return Ok(String::from(inner));
+            }
+
+            let syntax = if options.allow_python_strings {
+                EscapeSyntax::Python
+            } else if options.allow_json5_strings {
+                EscapeSyntax::Json5
+            } else {
+                EscapeSyntax::Json
+            };
+            unescape(inner, quote, options.allow_invalid_strings, syntax)
+        } else {
+            Ok(String::from(literal))
+        }
+    }
+
+    /// Parse an object property name from a literal string of specified kind,
+    /// the quotes are trimmed and the escapes are decoded.
+    pub(crate) fn parse_prop_name(
+        literal: &str,
+        kind: LiteralKind,
+        options: &ParseOptions,
+    ) -> Result<String> {
+        match kind {
+            _ if !kind.is_allowed(literal, options) => (),
+            LiteralKind::String(_) => return JsonNode::parse_quoted(literal, options),
+            LiteralKind::BareWord => (),
+            _ if options.allow_non_string_keys => {
+                return Ok(JsonNode::parse_plain(literal, kind, options)?.to_string());
+            }
+            _ => (),
+        }
+        let is_identifier_key = options.allow_identifier_keys && is_identifier(literal);
+        if !options.allow_bare_words && !is_identifier_key {
+            bail!("object property name must be quoted: {}", literal);
+        }
+
+        Ok(String::from(literal))
     }
 
     /// Parse a array data type from a JSON tag slice.
-    fn parse_array(json_tags: &[JsonTag]) -> Result<JsonNode> {
+    fn parse_array(
+        json_tags: &[JsonTag],
+        options: &ParseOptions,
+        depth: usize,
+    ) -> Result<JsonNode> {
         let inner_tags = if json_tags.first() == Some(&JsonTag::LeftSquare)
             && json_tags.last() == Some(&JsonTag::RightSquare)
         {
@@ -226,21 +401,45 @@ impl JsonNode {
         };
 
         let mut i = 0;
+        let mut after_comma = false;
         let mut inner_nodes = Vec::new();
         while i < inner_tags.len() {
-            let node = JsonNode::parse_next(inner_tags, &mut i)?;
-            if node.is_none() {
+            if let JsonTag::Comma = inner_tags[i] {
+                if (inner_nodes.is_empty() || after_comma) && !options.allow_extra_commas {
+                    bail!("unexpected comma: {}", JsonTag::to_string(&inner_tags[i..]));
+                }
+
+                after_comma = true;
+                i += 1;
                 continue;
             }
+            if !inner_nodes.is_empty() && !after_comma && !options.allow_missing_commas {
+                bail!("expecting comma: {}", JsonTag::to_string(&inner_tags[i..]));
+            }
 
-            inner_nodes.push(node.unwrap());
+            let node_start = i;
+            let node = match JsonNode::parse_next(inner_tags, &mut i, options, depth)? {
+                Some(node) => node,
+                None => {
+                    if !options.allow_stray_tags {
+                        bail!(
+                            "unexpected tag: {}",
+                            JsonTag::to_string(&inner_tags[node_start..])
+                        );
+                    }
 
-            // skip comma symbol
-            if i < inner_tags.len() {
-                if let JsonTag::Comma = inner_tags[i] {
-                    i += 1;
+                    continue;
                 }
-            }
+            };
+
+            inner_nodes.push(node);
+            after_comma = false;
+        }
+        if after_comma && !options.allow_extra_commas && !options.allow_trailing_commas {
+            bail!(
+                "unexpected trailing comma: {}",
+                JsonTag::to_string(json_tags)
+            );
         }
 
         let array_node = JsonNode::Array(inner_nodes);
@@ -248,7 +447,11 @@ impl JsonNode {
     }
 
     /// Parse a object data type from a JSON tag slice.
-    fn parse_object(json_tags: &[JsonTag]) -> Result<JsonNode> {
+    fn parse_object(
+        json_tags: &[JsonTag],
+        options: &ParseOptions,
+        depth: usize,
+    ) -> Result<JsonNode> {
         let inner_tags = if json_tags.first() == Some(&JsonTag::LeftCurly)
             && json_tags.last() == Some(&JsonTag::RightCurly)
         {
@@ -258,16 +461,24 @@ impl JsonNode {
         };
 
         let mut i = 0;
+        let mut after_comma = false;
         let mut prop_list = Vec::new();
         while i < inner_tags.len() {
-            let prop_name = if let JsonTag::Literal(str) = &inner_tags[i] {
-                if (str.starts_with('\'') && str.ends_with('\''))
-                    || (str.starts_with('"') && str.ends_with('"'))
-                {
-                    &str[1..str.len() - 1]
-                } else {
-                    str
+            if let JsonTag::Comma = inner_tags[i] {
+                if (prop_list.is_empty() || after_comma) && !options.allow_extra_commas {
+                    bail!("unexpected comma: {}", JsonTag::to_string(&inner_tags[i..]));
                 }
+
+                after_comma = true;
+                i += 1;
+                continue;
+            }
+            if !prop_list.is_empty() && !after_comma && !options.allow_missing_commas {
+                bail!("expecting comma: {}", JsonTag::to_string(&inner_tags[i..]));
+            }
+
+            let prop_name = if let JsonTag::Literal(str, kind) = &inner_tags[i] {
+                JsonNode::parse_prop_name(str, *kind, options)?
             } else {
                 bail!(
                     "object property name must be string: {}",
@@ -277,113 +488,243 @@ impl JsonNode {
 
             // skip colon symbol
             let mut start = i + 1;
-            if let JsonTag::Colon = inner_tags[start] {
+            if let Some(JsonTag::Colon) = inner_tags.get(start) {
                 start += 1;
+            } else if !options.allow_missing_colons {
+                bail!(
+                    "expecting colon after property name: {}",
+                    JsonTag::to_string(&inner_tags[i..])
+                );
             }
 
             let mut value_node = None;
             while start < inner_tags.len() {
-                value_node = JsonNode::parse_next(inner_tags, &mut start)?;
+                let value_start = start;
+                value_node = JsonNode::parse_next(inner_tags, &mut start, options, depth)?;
                 if value_node.is_none() {
+                    if !options.allow_stray_tags {
+                        bail!(
+                            "unexpected tag: {}",
+                            JsonTag::to_string(&inner_tags[value_start..])
+                        );
+                    }
+
                     continue;
                 }
 
-                i = start;
                 break;
             }
-            if value_node.is_none() {
-                bail!(
+            let value_node = match value_node {
+                Some(value_node) => value_node,
+                None => bail!(
                     "object property value not found: {}",
                     JsonTag::to_string(&inner_tags[i..start])
-                );
-            }
+                ),
+            };
 
-            let obj_prop = JsonObjProp::new(String::from(prop_name), value_node.unwrap());
+            let obj_prop = JsonObjProp::new(prop_name, value_node);
             prop_list.push(obj_prop);
+            options.limits.check_object_props(prop_list.len())?;
 
-            // skip comma symbol
-            if i < inner_tags.len() {
-                if let JsonTag::Comma = inner_tags[i] {
-                    i += 1;
-                }
-            }
+            i = start;
+            after_comma = false;
+        }
+        if after_comma && !options.allow_extra_commas && !options.allow_trailing_commas {
+            bail!(
+                "unexpected trailing comma: {}",
+                JsonTag::to_string(json_tags)
+            );
         }
 
         Ok(JsonNode::Object(prop_list))
     }
 
-    /// Compose a formatted JSON string representation of a JSON node.
-    fn fmt_indent(
+    /// Compose a formatted JSON string representation of a JSON node, nested at specified level.
+    pub(crate) fn fmt_indent<F: fmt::Write>(
         &self,
-        f: &mut Formatter<'_>,
-        indent_width: usize,
-        no_plain_indent: bool,
+        f: &mut F,
+        level: usize,
+        options: &WriteOptions,
+    ) -> fmt::Result {
+        self.fmt_at(f, level, options.indent_columns(level), 0, options)
+    }
+
+    /// Compose a formatted JSON string representation of a JSON node, nested at specified level,
+    /// starting at specified column and followed by specified count of chars on the same line.
+    fn fmt_at<F: fmt::Write>(
+        &self,
+        f: &mut F,
+        level: usize,
+        column: usize,
+        suffix: usize,
+        options: &WriteOptions,
     ) -> fmt::Result {
-        let pretty = f.alternate();
-        let ending = if pretty { "\n" } else { "" };
-        let comma_separator = if pretty { "," } else { ", " };
-        let indent_width = if pretty { indent_width } else { 0 };
-        let next_indent_width = if pretty { indent_width + 4 } else { 0 };
-        let plain_indent_width = if no_plain_indent { 0 } else { indent_width };
         match self {
-            JsonNode::PlainNull => write!(f, "{:indent$}null", "", indent = plain_indent_width)?,
+            JsonNode::PlainNull => options.write_styled(f, Style::Null, |f| f.write_str("null"))?,
             JsonNode::PlainBoolean(b) => {
-                write!(f, "{:indent$}{}", "", b, indent = plain_indent_width)?
+                options.write_styled(f, Style::Boolean, |f| write!(f, "{}", b))?
+            }
+            JsonNode::PlainNumber(n) if options.json5 => {
+                options.write_styled(f, Style::Number, |f| f.write_str(n.as_str()))?
             }
             JsonNode::PlainNumber(n) => {
-                write!(f, "{:indent$}{}", "", n, indent = plain_indent_width)?
+                let number = n.to_json_string();
+                let style = if number == "null" {
+                    Style::Null
+                } else {
+                    Style::Number
+                };
+                options.write_styled(f, style, |f| f.write_str(&number))?
             }
             JsonNode::PlainString(s) => {
-                write!(f, r#"{:indent$}"{}""#, "", s, indent = plain_indent_width)?
+                options.write_styled(f, Style::String, |f| options.write_str_value(f, s))?
             }
             JsonNode::Object(prop_list) => {
-                write!(f, "{{{}", ending)?;
-
-                for i in 0..prop_list.len() {
-                    let prop = &prop_list[i];
-
-                    write!(
-                        f,
-                        r#"{:indent$}"{}""#,
-                        "",
-                        prop.name,
-                        indent = next_indent_width
-                    )?;
-                    f.write_str(": ")?;
+                if prop_list.is_empty() {
+                    options.write_bracket(f, '{')?;
+                    return options.write_bracket(f, '}');
+                }
+                if self.fits_inline(column + suffix, options) {
+                    return self.fmt_indent(f, 0, &options.single_line());
+                }
 
-                    prop.value.fmt_indent(f, next_indent_width, true)?;
+                let mut prop_list: Vec<&JsonObjProp> = prop_list.iter().collect();
+                if options.sort_keys {
+                    prop_list.sort_by(|a, b| a.name.cmp(&b.name));
+                }
 
-                    if i != prop_list.len() - 1 {
-                        write!(f, "{}{}", comma_separator, ending)?;
+                options.write_bracket(f, '{')?;
+                for (i, prop) in prop_list.iter().enumerate() {
+                    if i != 0 {
+                        options.write_comma(f)?;
                     }
-                }
 
-                write!(f, "{}{:indent$}}}", ending, "", indent = indent_width)?;
+                    let mut name = String::new();
+                    options.write_prop_name(&mut name, &prop.name)?;
+                    let column = options.indent_columns(level + 1)
+                        + name.chars().count()
+                        + options.colon_columns();
+                    let suffix = usize::from(i + 1 != prop_list.len());
+
+                    options.write_line_break(f, level + 1)?;
+                    options.write_styled(f, Style::Key, |f| f.write_str(&name))?;
+                    options.write_colon(f)?;
+                    prop.value.fmt_at(f, level + 1, column, suffix, options)?;
+                }
+                options.write_trailing_comma(f)?;
+                options.write_line_break(f, level)?;
+                options.write_bracket(f, '}')?;
             }
             JsonNode::Array(arr) => {
-                write!(f, "[{}", ending)?;
-
-                for i in 0..arr.len() {
-                    let elem = &arr[i];
-                    elem.fmt_indent(f, next_indent_width, false)?;
+                if arr.is_empty() {
+                    options.write_bracket(f, '[')?;
+                    return options.write_bracket(f, ']');
+                }
+                if self.fits_inline(column + suffix, options) {
+                    return self.fmt_indent(f, 0, &options.single_line());
+                }
 
-                    if i != arr.len() - 1 {
-                        write!(f, "{}{}", comma_separator, ending)?;
+                options.write_bracket(f, '[')?;
+                for (i, elem) in arr.iter().enumerate() {
+                    if i != 0 {
+                        options.write_comma(f)?;
                     }
-                }
 
-                write!(f, "{}{:indent$}]", ending, "", indent = indent_width)?;
+                    let column = options.indent_columns(level + 1);
+                    let suffix = usize::from(i + 1 != arr.len());
+
+                    options.write_line_break(f, level + 1)?;
+                    elem.fmt_at(f, level + 1, column, suffix, options)?;
+                }
+                options.write_trailing_comma(f)?;
+                options.write_line_break(f, level)?;
+                options.write_bracket(f, ']')?;
             }
         }
 
         Ok(())
     }
+
+    /// Whether a single-line JSON string representation of a JSON node in pretty output
+    /// fits within max line width along with specified count of columns taken on the same line.
+    fn fits_inline(&self, taken: usize, options: &WriteOptions) -> bool {
+        if !options.pretty {
+            return false;
+        }
+        let capacity = match options.max_width.and_then(|w| w.checked_sub(taken)) {
+            Some(capacity) => capacity,
+            None => return false,
+        };
+
+        let measure_options = WriteOptions {
+            theme: None,
+            ..options.single_line()
+        };
+        let mut bounded = BoundedWriter::new(capacity);
+        self.fmt_indent(&mut bounded, 0, &measure_options).is_ok()
+    }
+
+    /// Compose a JSON string representation of a JSON node with specified write options.
+    pub fn to_string_with_options(&self, options: &WriteOptions) -> String {
+        let mut result = String::new();
+        // writing to a String never fails
+        let _ = self.fmt_indent(&mut result, 0, options);
+        if options.trailing_newline {
+            result.push_str(options.line_ending.as_str());
+        }
+
+        result
+    }
+
+    /// Get a displayable JSON node, whose strings are escaped as specified by escape options.
+    pub fn escaped<'a>(&'a self, escape: &'a EscapeOptions) -> EscapedJsonNode<'a> {
+        EscapedJsonNode { node: self, escape }
+    }
 }
 
 impl fmt::Display for JsonNode {
-    /// Implement Display trait for JsonNode
+    /// Implement Display trait for JsonNode, alternate formatter option writes pretty output.
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        self.fmt_indent(f, 0, false)
+        let options = if f.alternate() {
+            WriteOptions::pretty()
+        } else {
+            WriteOptions::default()
+        };
+        self.fmt_indent(f, 0, &options)
+    }
+}
+
+/// JSON node displayed with specified escape options.
+pub struct EscapedJsonNode<'a> {
+    node: &'a JsonNode,
+    escape: &'a EscapeOptions,
+}
+
+impl fmt::Display for EscapedJsonNode<'_> {
+    /// Implement Display trait for EscapedJsonNode
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let options = WriteOptions {
+            escape: self.escape.clone(),
+            ..if f.alternate() {
+                WriteOptions::pretty()
+            } else {
+                WriteOptions::default()
+            }
+        };
+        self.node.fmt_indent(f, 0, &options)
+    }
+}
+
+impl JsonTag {
+    /// Decoded value of the literal as a plain JSON node(null, bool, number, or string), None if not a literal.<br>
+    /// It fails if the literal is invalid under specified parse options, such as a bare word in strict mode.
+    pub fn literal_value(&self, options: &ParseOptions) -> Result<Option<JsonNode>> {
+        match self {
+            JsonTag::Literal(literal, kind) => {
+                Ok(Some(JsonNode::parse_plain(literal, *kind, options)?))
+            }
+            _ => Ok(None),
+        }
     }
 }
 
@@ -391,6 +732,10 @@ impl fmt::Display for JsonNode {
 mod json_node_tests {
     use super::JsonNode;
     use super::JsonObjProp;
+    use crate::json_number::JsonNumber;
+    use crate::json_tag::JsonTag;
+    use crate::json_writer::WriteOptions;
+    use crate::parse_options::ParseOptions;
     use anyhow::Result;
     use std::fmt::Write;
 
@@ -402,13 +747,16 @@ mod json_node_tests {
         assert_eq!(
             json_node,
             JsonNode::Object(vec![
-                JsonObjProp::new(String::from(r#"simple"#), JsonNode::PlainNumber(123f64)),
+                JsonObjProp::new(
+                    String::from(r#"simple"#),
+                    JsonNode::PlainNumber(JsonNumber::from(123f64))
+                ),
                 JsonObjProp::new(
                     String::from(r#"array"#),
                     JsonNode::Array(vec![
                         JsonNode::PlainString(String::from(r#"a"#)),
                         JsonNode::PlainString(String::from(r#"b"#)),
-                        JsonNode::PlainString(String::from(r#"c\""#)),
+                        JsonNode::PlainString(String::from(r#"c""#)),
                     ]),
                 ),
                 JsonObjProp::new(
@@ -462,4 +810,224 @@ mod json_node_tests {
 
         Ok(())
     }
+
+    /// Test number parsing following RFC 8259 number grammar.
+    #[test]
+    fn test_number_grammar() -> Result<()> {
+        let json = r#"[-5, 1e10, 2.5E-3, -0.1, 0, 012, 1., .5, +1, 0x1F, ١٢٣]"#;
+        let json_node = JsonNode::parse_single_node(json.as_bytes())?;
+        assert_eq!(
+            json_node,
+            JsonNode::Array(vec![
+                JsonNode::PlainNumber(JsonNumber::from(-5f64)),
+                JsonNode::PlainNumber(JsonNumber::from(1e10f64)),
+                JsonNode::PlainNumber(JsonNumber::from(2.5e-3f64)),
+                JsonNode::PlainNumber(JsonNumber::from(-0.1f64)),
+                JsonNode::PlainNumber(JsonNumber::from(0f64)),
+                JsonNode::PlainString(String::from("012")),
+                JsonNode::PlainString(String::from("1.")),
+                JsonNode::PlainString(String::from(".5")),
+                JsonNode::PlainString(String::from("+1")),
+                JsonNode::PlainString(String::from("0x1F")),
+                JsonNode::PlainString(String::from("١٢٣")),
+            ])
+        );
+
+        Ok(())
+    }
+
+    /// Test number parsing with lenient number extras allowed.
+    #[test]
+    fn test_number_extras() -> Result<()> {
+        let options = ParseOptions {
+            allow_leading_plus: true,
+            allow_leading_decimal_point: true,
+            allow_hex_numbers: true,
+            ..ParseOptions::strict()
+        };
+        let json = r#"[+1, .5, -.25e1, 0x1F, -0XfF]"#;
+        let json_node = JsonNode::parse_single_node_with_options(json.as_bytes(), &options)?;
+        assert_eq!(
+            json_node,
+            JsonNode::Array(vec![
+                JsonNode::PlainNumber(JsonNumber::from(1f64)),
+                JsonNode::PlainNumber(JsonNumber::from(0.5f64)),
+                JsonNode::PlainNumber(JsonNumber::from(-2.5f64)),
+                JsonNode::PlainNumber(JsonNumber::from(31f64)),
+                JsonNode::PlainNumber(JsonNumber::from(-255f64)),
+            ])
+        );
+
+        for json in [r#"[+-1]"#, r#"[.]"#, r#"[0x]"#, r#"[0x1G]"#, r#"[1.]"#] {
+            assert!(JsonNode::parse_single_node_with_options(json.as_bytes(), &options).is_err());
+        }
+
+        Ok(())
+    }
+
+    /// Test parsing JSON5 input.
+    #[test]
+    fn test_json5() -> Result<()> {
+        let json =
+            "{unquoted: 'single \"q\"', trailing: [1, 2,], hex: 0xFF, lead: .5, trail: 5.,\n\
+            pos: +1, inf: -Infinity, multi: 'a\\\n\
+            b\\x41\\v', // comment\n\
+            }";
+        let mut json_node =
+            JsonNode::parse_single_node_with_options(json.as_bytes(), &ParseOptions::json5())?;
+        assert_eq!(
+            json_node.get_str("$.unquoted")?,
+            Some(String::from("single \"q\""))
+        );
+        assert_eq!(json_node.get_number("$.trailing[1]")?, Some(2f64));
+        assert_eq!(json_node.get_raw("$.trailing[2]")?, None);
+        assert_eq!(json_node.get_number("$.hex")?, Some(255f64));
+        assert_eq!(json_node.get_number("$.lead")?, Some(0.5f64));
+        assert_eq!(json_node.get_number("$.trail")?, Some(5f64));
+        assert_eq!(json_node.get_number("$.pos")?, Some(1f64));
+        assert_eq!(json_node.get_number("$.inf")?, Some(f64::NEG_INFINITY));
+        assert_eq!(
+            json_node.get_str("$.multi")?,
+            Some(String::from("abA\u{b}"))
+        );
+
+        for json in [r#"[1,,2]"#, "['a\nb']", r#"{"a" 1}"#] {
+            assert!(JsonNode::parse_single_node_with_options(
+                json.as_bytes(),
+                &ParseOptions::json5()
+            )
+            .is_err());
+        }
+
+        Ok(())
+    }
+
+    /// Test parsing Python repr() output and JavaScript object literals.
+    #[test]
+    fn test_python_and_javascript() -> Result<()> {
+        let python = r#"{'name': "it's", 'tags': ('a', 'b'), 'one': (1,), 'empty': (), 'ok': True,
+            'none': None, 1: b'\x00raw', 2.5: r'\d+', None: [inf, -nan], 'nested': [(1, [2]), {}],}"#;
+        let json_node =
+            JsonNode::parse_single_node_with_options(python.as_bytes(), &ParseOptions::python())?;
+        assert_eq!(
+            json_node.to_string(),
+            r#"{"name": "it's", "tags": ["a", "b"], "one": [1], "empty": [], "ok": true, "none": null, "1": "\u0000raw", "2.5": "\\d+", "null": [null, null], "nested": [[1, [2]], {}]}"#
+        );
+        let strict = ParseOptions::strict();
+        JsonNode::parse_single_node_with_options(json_node.to_string().as_bytes(), &strict)?;
+
+        // output of ascii() and repr(), with astral chars, and unknown escapes keeping their backslash
+        let python = r#"[{'a': '\U0001f600\xe9', 'b': 'x\\d', 'c': '\U000e0001\x00\t', 'd': '\d\101'}, ('\U000e0001',)]"#;
+        let json_node =
+            JsonNode::parse_single_node_with_options(python.as_bytes(), &ParseOptions::python())?;
+        assert_eq!(
+            json_node.to_string(),
+            "[{\"a\": \"😀é\", \"b\": \"x\\\\d\", \"c\": \"\u{e0001}\\u0000\\t\", \"d\": \"\\\\dA\"}, [\"\u{e0001}\"]]"
+        );
+
+        // parenthesized values without comma are not tuples
+        for (python, json) in [
+            ("(1)", "1"),
+            ("[1, (2)]", "[1, 2]"),
+            ("((1,))", "[1]"),
+            ("(((1), 2), ({'a': (3)}))", r#"[[1, 2], {"a": 3}]"#),
+            ("[(), ( ), ('x')]", r#"[[], [], "x"]"#),
+        ] {
+            let json_node = JsonNode::parse_single_node_with_options(
+                python.as_bytes(),
+                &ParseOptions::python(),
+            )?;
+            assert_eq!(json_node.to_string(), json);
+        }
+
+        let javascript = "{name: 'x', 'a': undefined, 1: [0x10, Infinity,], // note
+}";
+        let json_node = JsonNode::parse_single_node_with_options(
+            javascript.as_bytes(),
+            &ParseOptions::javascript(),
+        )?;
+        assert_eq!(
+            json_node.to_string(),
+            r#"{"name": "x", "a": null, "1": [16, null]}"#
+        );
+        JsonNode::parse_single_node_with_options(json_node.to_string().as_bytes(), &strict)?;
+        let options = WriteOptions {
+            json5: true,
+            ..WriteOptions::default()
+        };
+        assert_eq!(
+            json_node.to_string_with_options(&options),
+            "{name: 'x', a: null, '1': [0x10, Infinity]}"
+        );
+
+        for json in ["[None]", "[undefined]", "(1, 2)", "[b'x']", "{1: 2}"] {
+            assert!(JsonNode::parse_single_node_with_options(
+                json.as_bytes(),
+                &ParseOptions::json5()
+            )
+            .is_err());
+        }
+        for json in ["[1)", "(1]", "[(1, 2]]", "((1, 2), [3)]", "(1", "((1)"] {
+            assert!(JsonNode::parse_single_node_with_options(
+                json.as_bytes(),
+                &ParseOptions::python()
+            )
+            .is_err());
+        }
+        let json_node = JsonNode::parse_single_node(b"[None, (1)]".as_slice())?;
+        assert_eq!(json_node.to_string(), r#"["None", "(1)"]"#);
+
+        Ok(())
+    }
+
+    /// Test malformed input returns error rather than panicking, with cases found by fuzzing.
+    #[test]
+    fn test_malformed_no_panic() -> Result<()> {
+        let strict = ParseOptions::strict();
+        for json in [
+            r#"{"a"}"#,
+            r#"{"a":}"#,
+            r#"{"a" :"#,
+            r#"{,}"#,
+            r#"[1,"#,
+            r#"]"#,
+            r#"{1: 2}"#,
+        ] {
+            assert!(
+                JsonNode::parse_with_options(json.as_bytes(), &strict).is_err(),
+                "{}",
+                json
+            );
+        }
+
+        for json in [
+            "}{",
+            "{:}",
+            "{\"a\" 1 2}",
+            "{\"a\": [1}, 2]",
+            "[\"\\uD83D\", '\\u', \"\\\"]",
+            "[0x, -., 1e, .e1, tru, True, NaN, -NaN]",
+            "[\u{feff}1, \u{2028}, \"é😀\0\"]",
+            "/* [1",
+            "// x\r[",
+            "{\"a\": {\"b\": [,]]",
+        ] {
+            for options in [
+                ParseOptions::default(),
+                strict.clone(),
+                ParseOptions::json5(),
+            ] {
+                if let Ok(mut nodes) = JsonNode::parse_with_options(json.as_bytes(), &options) {
+                    for node in &mut nodes {
+                        let _ = format!("{:#}", node);
+                        let _ = node.to_string_with_options(&WriteOptions::json5());
+                        let _ = node.get_raw("$[0].a[-1:]");
+                    }
+                }
+                let _ = JsonTag::parse_lossless_with_options(json.as_bytes(), &options);
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,412 @@
+//! JSON number which keeps 64-bit integers exactly and remembers its original text.
+
+use anyhow::{bail, Result};
+use std::{
+    fmt::{self, Formatter},
+    str::FromStr,
+};
+
+use crate::parse_options::*;
+
+/// Value of JSON number, integers are kept exactly if they fit in i64 or u64.
+#[derive(Debug, Clone, Copy)]
+enum NumberValue {
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+}
+
+/// JSON number.<br>
+/// Integers fitting in i64 or u64 are kept exactly, other numbers are approximated by f64,
+/// while the original text(lexeme) is always kept and used for serialization if it is valid in the output.
+#[derive(Debug, Clone)]
+pub struct JsonNumber {
+    value: NumberValue,
+    lexeme: String,
+}
+
+impl JsonNumber {
+    /// Parse a number from a literal string, following RFC 8259 number grammar.
+    /// Leading plus sign, leading or trailing decimal point, hex numbers, Infinity and NaN,
+    /// and Python inf and nan are accepted only if allowed by options.
+    /// If the literal is not a number, return None.
+    pub(crate) fn parse(literal: &str, options: &ParseOptions) -> Result<Option<JsonNumber>> {
+        let (negative, unsigned) = match literal.as_bytes().first() {
+            Some(b'-') => (true, &literal[1..]),
+            Some(b'+') if options.allow_leading_plus => (false, &literal[1..]),
+            _ => (false, literal),
+        };
+
+        let value = if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+            if !options.allow_hex_numbers
+                || unsigned.len() < 3
+                || !unsigned[2..].bytes().all(|b| b.is_ascii_hexdigit())
+            {
+                return Ok(None);
+            }
+
+            let magnitude = match u64::from_str_radix(&unsigned[2..], 16) {
+                Ok(n) => n,
+                Err(_) => bail!("hex number out of range: {}", literal),
+            };
+            if !negative {
+                NumberValue::UInt(magnitude)
+            } else if magnitude <= i64::MAX as u64 + 1 {
+                NumberValue::Int((magnitude as i64).wrapping_neg())
+            } else {
+                bail!("hex number out of range: {}", literal)
+            }
+        } else if options.allow_python_literals && (unsigned == "inf" || unsigned == "nan") {
+            // kept as Infinity or NaN, which JSON5 output writes as is
+            let magnitude = if unsigned == "nan" {
+                f64::NAN
+            } else {
+                f64::INFINITY
+            };
+            return Ok(Some(JsonNumber::from(if negative {
+                -magnitude
+            } else {
+                magnitude
+            })));
+        } else if options.allow_infinity_and_nan && (unsigned == "Infinity" || unsigned == "NaN") {
+            let magnitude = if unsigned == "NaN" {
+                f64::NAN
+            } else {
+                f64::INFINITY
+            };
+            NumberValue::Float(if negative { -magnitude } else { magnitude })
+        } else {
+            if unsigned.starts_with('.') && !unsigned[1..].starts_with(|c: char| c.is_ascii_digit())
+            {
+                return Ok(None);
+            }
+
+            let mut normalized = String::from(unsigned);
+            if normalized.starts_with('.') && options.allow_leading_decimal_point {
+                normalized.insert(0, '0');
+            }
+            if options.allow_trailing_decimal_point {
+                if normalized.ends_with('.') {
+                    normalized.push('0');
+                }
+                normalized = normalized.replacen(".e", ".0e", 1).replacen(".E", ".0E", 1);
+            }
+            if !is_json_number(&normalized) || normalized.starts_with('-') {
+                return Ok(None);
+            }
+
+            if negative {
+                normalized.insert(0, '-');
+            }
+            JsonNumber::parse_value(&normalized)?
+        };
+
+        let number = JsonNumber {
+            value,
+            lexeme: String::from(literal),
+        };
+        Ok(Some(number))
+    }
+
+    /// Parse number value from a string conforming to RFC 8259 number grammar.
+    fn parse_value(number_str: &str) -> Result<NumberValue> {
+        let is_integer = !number_str.contains(['.', 'e', 'E']);
+        if is_integer {
+            if let Ok(i) = i64::from_str(number_str) {
+                return Ok(NumberValue::Int(i));
+            }
+            if let Ok(u) = u64::from_str(number_str) {
+                return Ok(NumberValue::UInt(u));
+            }
+        }
+
+        Ok(NumberValue::Float(f64::from_str(number_str)?))
+    }
+
+    /// Get the number as i64, if it is an integer fitting in i64.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.value {
+            NumberValue::Int(i) => Some(i),
+            NumberValue::UInt(u) => i64::try_from(u).ok(),
+            NumberValue::Float(_) => None,
+        }
+    }
+
+    /// Get the number as u64, if it is an integer fitting in u64.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.value {
+            NumberValue::Int(i) => u64::try_from(i).ok(),
+            NumberValue::UInt(u) => Some(u),
+            NumberValue::Float(_) => None,
+        }
+    }
+
+    /// Get the number as f64, which may lose precision.
+    pub fn as_f64(&self) -> f64 {
+        match self.value {
+            NumberValue::Int(i) => i as f64,
+            NumberValue::UInt(u) => u as f64,
+            NumberValue::Float(f) => f,
+        }
+    }
+
+    /// Get the original text of the number.
+    pub fn as_str(&self) -> &str {
+        &self.lexeme
+    }
+
+    /// Whether the number is an integer fitting in i64 or u64.
+    pub fn is_integer(&self) -> bool {
+        !matches!(self.value, NumberValue::Float(_))
+    }
+
+    /// Get the text of the number conforming to RFC 8259 number grammar.<br>
+    /// The original text is kept if it conforms, otherwise the number is written in canonical form,
+    /// e.g. 0x10 as 16, .5 as 0.5, +1 as 1 and 5. as 5.0.
+    /// Infinity and NaN, which RFC 8259 cannot represent, are written as null.
+    pub fn to_json_string(&self) -> String {
+        if is_json_number(&self.lexeme) {
+            return self.lexeme.clone();
+        }
+
+        match self.value {
+            NumberValue::Int(i) => i.to_string(),
+            NumberValue::UInt(u) => u.to_string(),
+            NumberValue::Float(f) if !f.is_finite() => String::from("null"),
+            NumberValue::Float(f) => {
+                // fix up the original text rather than printing f64, so that no precision is lost
+                let unsigned = self.lexeme.trim_start_matches(['+', '-']);
+                let mut canonical = String::from(unsigned);
+                if canonical.starts_with('.') {
+                    canonical.insert(0, '0');
+                }
+                if canonical.ends_with('.') {
+                    canonical.push('0');
+                }
+                canonical = canonical.replacen(".e", ".0e", 1).replacen(".E", ".0E", 1);
+                if self.lexeme.starts_with('-') {
+                    canonical.insert(0, '-');
+                }
+
+                if is_json_number(&canonical) {
+                    canonical
+                } else {
+                    f.to_string()
+                }
+            }
+        }
+    }
+}
+
+impl From<i64> for JsonNumber {
+    /// Create JSON number from i64.
+    fn from(i: i64) -> Self {
+        JsonNumber {
+            value: NumberValue::Int(i),
+            lexeme: i.to_string(),
+        }
+    }
+}
+
+impl From<u64> for JsonNumber {
+    /// Create JSON number from u64.
+    fn from(u: u64) -> Self {
+        JsonNumber {
+            value: NumberValue::UInt(u),
+            lexeme: u.to_string(),
+        }
+    }
+}
+
+impl From<f64> for JsonNumber {
+    /// Create JSON number from f64.
+    /// NaN and infinities, which JSON cannot represent, are kept as NaN, Infinity and -Infinity,
+    /// which are written as is in JSON5 output, and as null otherwise.
+    fn from(f: f64) -> Self {
+        let lexeme = if f.is_nan() {
+            String::from("NaN")
+        } else if f.is_infinite() {
+            String::from(if f > 0f64 { "Infinity" } else { "-Infinity" })
+        } else {
+            f.to_string()
+        };
+
+        JsonNumber {
+            value: NumberValue::Float(f),
+            lexeme,
+        }
+    }
+}
+
+impl FromStr for JsonNumber {
+    type Err = anyhow::Error;
+
+    /// Parse JSON number from a string conforming to RFC 8259 number grammar.
+    fn from_str(s: &str) -> Result<Self> {
+        match JsonNumber::parse(s, &ParseOptions::strict())? {
+            Some(number) => Ok(number),
+            None => bail!("invalid number: {}", s),
+        }
+    }
+}
+
+impl PartialEq for JsonNumber {
+    /// Numbers are equal if their values are equal, regardless of their original text.
+    fn eq(&self, other: &Self) -> bool {
+        match (self.value, other.value) {
+            (NumberValue::Int(a), NumberValue::Int(b)) => a == b,
+            (NumberValue::UInt(a), NumberValue::UInt(b)) => a == b,
+            (NumberValue::Int(a), NumberValue::UInt(b))
+            | (NumberValue::UInt(b), NumberValue::Int(a)) => a as i128 == b as i128,
+            _ => self.as_f64() == other.as_f64(),
+        }
+    }
+}
+
+impl fmt::Display for JsonNumber {
+    /// Implement Display trait for JsonNumber, written as RFC 8259 number.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_json_string())
+    }
+}
+
+/// Check whether a literal string conforms to RFC 8259 number grammar.
+pub(crate) fn is_json_number(literal: &str) -> bool {
+    let bytes = literal.as_bytes();
+    let mut i = 0;
+    let count_digits = |i: &mut usize| {
+        let start = *i;
+        while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+
+    if bytes.get(i) == Some(&b'-') {
+        i += 1;
+    }
+    match bytes.get(i) {
+        Some(b'0') => i += 1,
+        Some(b'1'..=b'9') => {
+            count_digits(&mut i);
+        }
+        _ => return false,
+    }
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        if 0 == count_digits(&mut i) {
+            return false;
+        }
+    }
+    if let Some(b'e' | b'E') = bytes.get(i) {
+        i += 1;
+        if let Some(b'+' | b'-') = bytes.get(i) {
+            i += 1;
+        }
+        if 0 == count_digits(&mut i) {
+            return false;
+        }
+    }
+
+    i == bytes.len()
+}
+
+#[cfg(test)]
+mod json_number_tests {
+    use super::*;
+    use crate::{JsonNode, WriteOptions};
+
+    /// Test integers beyond 2^53 are kept exactly.
+    #[test]
+    fn test_big_integers() -> Result<()> {
+        let json = r#"[9007199254740993, -9223372036854775808, 18446744073709551615, 123456789012345678901234567890]"#;
+        let json_node = JsonNode::parse_single_node(json.as_bytes())?;
+        if let JsonNode::Array(arr) = &json_node {
+            let numbers: Vec<&JsonNumber> = arr
+                .iter()
+                .map(|n| match n {
+                    JsonNode::PlainNumber(n) => n,
+                    _ => panic!("expecting number, but found: {:?}", n),
+                })
+                .collect();
+            assert_eq!(numbers[0].as_i64(), Some(9007199254740993));
+            assert_eq!(numbers[1].as_i64(), Some(i64::MIN));
+            assert_eq!(numbers[1].as_u64(), None);
+            assert_eq!(numbers[2].as_u64(), Some(u64::MAX));
+            assert_eq!(numbers[2].as_i64(), None);
+            assert_eq!(numbers[3].as_i64(), None);
+            assert!(!numbers[3].is_integer());
+            assert_eq!(numbers[3].as_str(), "123456789012345678901234567890");
+        } else {
+            panic!("expecting array, but found: {:?}", json_node);
+        }
+        assert_eq!(json_node.to_string(), json);
+
+        Ok(())
+    }
+
+    /// Test the original text is written back unless the value is changed.
+    #[test]
+    fn test_lexeme_round_trip() -> Result<()> {
+        let json = r#"{"price": 1.50, "rate": 2.5E-3, "id": 1e2}"#;
+        let mut json_node = JsonNode::parse_single_node(json.as_bytes())?;
+        assert_eq!(json_node.to_string(), json);
+        assert_eq!(
+            json_node.get_number_str("$.price")?,
+            Some(String::from("1.50"))
+        );
+        assert_eq!(json_node.get_f64("$.rate")?, Some(2.5e-3));
+        assert!(json_node.get_i64("$.id").is_err());
+
+        json_node.set_number("$.price", 1.25)?;
+        json_node.set_u64("$.id", u64::MAX)?;
+        assert_eq!(
+            json_node.to_string(),
+            r#"{"price": 1.25, "rate": 2.5E-3, "id": 18446744073709551615}"#
+        );
+        assert_eq!(json_node.get_u64("$.id")?, Some(u64::MAX));
+
+        // overflowing to infinity, yet valid RFC 8259 text
+        let json = r#"[1e400, -1E+400]"#;
+        json_node = JsonNode::parse_single_node(json.as_bytes())?;
+        assert_eq!(json_node.get_f64("$[0]")?, Some(f64::INFINITY));
+        assert_eq!(json_node.to_string(), json);
+
+        Ok(())
+    }
+
+    /// Test numbers not conforming to RFC 8259 are written in canonical form unless writing JSON5.
+    #[test]
+    fn test_json5_numbers_to_json() -> Result<()> {
+        let json = r#"[0x10, .5, +1, 5., -0XfF, +.5e3, 1.e2, 2.50]"#;
+        let json_node =
+            JsonNode::parse_single_node_with_options(json.as_bytes(), &ParseOptions::json5())?;
+
+        let output = json_node.to_string();
+        assert_eq!(output, r#"[16, 0.5, 1, 5.0, -255, 0.5e3, 1.0e2, 2.50]"#);
+        let reparsed =
+            JsonNode::parse_single_node_with_options(output.as_bytes(), &ParseOptions::strict())?;
+        assert_eq!(reparsed, json_node);
+
+        let options = WriteOptions {
+            json5: true,
+            ..WriteOptions::default()
+        };
+        let output = json_node.to_string_with_options(&options);
+        assert_eq!(output, json);
+
+        Ok(())
+    }
+
+    /// Test numbers are compared by value.
+    #[test]
+    fn test_number_eq() -> Result<()> {
+        assert_eq!(JsonNumber::from_str("1.50")?, JsonNumber::from(1.5));
+        assert_eq!(JsonNumber::from_str("3")?, JsonNumber::from(3u64));
+        assert_eq!(JsonNumber::from(3i64), JsonNumber::from(3u64));
+        assert_ne!(JsonNumber::from(-1i64), JsonNumber::from(u64::MAX));
+        assert!(JsonNumber::from_str("01").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+//! Best-effort parsing of truncated JSON, such as a document still being streamed.
+
+use anyhow::{bail, Result};
+use std::io::Read;
+
+use crate::json_node::*;
+use crate::json_number::*;
+use crate::json_repair::*;
+use crate::json_string::*;
+use crate::json_tag::*;
+use crate::limits::*;
+use crate::parse_options::*;
+
+/// Where a truncated JSON input ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartialEnd {
+    /// The input is a complete JSON value.
+    Complete,
+    /// No value started yet.
+    Empty,
+    /// Inside a string, which is closed in the partial node.
+    InString,
+    /// Inside or right after a number, which may continue. The valid part of it is kept.
+    InNumber,
+    /// Inside true, false or null, which is left out.
+    InLiteral,
+    /// Inside or right after a property name, the property is left out.
+    InPropertyName,
+    /// After the colon of a property, the property is left out.
+    AfterColon,
+    /// After an opening bracket or comma, waiting for the next entry.
+    BeforeEntry,
+    /// After a complete entry of an array or object, which is not closed yet.
+    AfterEntry,
+}
+
+/// The largest valid prefix of truncated JSON, with open arrays and objects closed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialNode {
+    /// JSON node parsed from the prefix, None if no value started yet.
+    pub node: Option<JsonNode>,
+
+    /// Where the input ended.
+    pub end: PartialEnd,
+
+    /// JSONPath of the entry in progress where the input ended, such as $['items'][2],
+    /// or of the innermost open array or object if no entry is in progress.
+    pub path: String,
+}
+
+impl PartialNode {
+    /// Whether the input is a complete JSON value.
+    pub fn is_complete(&self) -> bool {
+        self.end == PartialEnd::Complete
+    }
+}
+
+/// An array or object not closed yet.
+#[derive(Debug, Clone)]
+struct Frame {
+    is_object: bool,
+    expect: Expect,
+    /// Property name or element index of the current entry.
+    key: Option<String>,
+    index: usize,
+    /// Tag index of the comma before the current entry, and of the current entry.
+    comma: Option<usize>,
+    entry_start: usize,
+}
+
+/// How a literal at the end of input is cut.
+enum CutLiteral {
+    /// The literal is complete.
+    Complete,
+    /// The literal is replaced by its valid part.
+    Replaced(PartialEnd, JsonTag),
+    /// The literal has no valid part.
+    Dropped(PartialEnd),
+}
+
+/// Trim an escape cut at the end of string content, such as \ or \u12,
+/// or a high surrogate escape whose low surrogate has not arrived yet.
+fn trim_partial_escape(inner: &str) -> &str {
+    let p = match inner.rfind('\\') {
+        Some(p) => p,
+        None => return inner,
+    };
+    if inner[..p].chars().rev().take_while(|c| *c == '\\').count() % 2 == 1 {
+        return inner;
+    }
+
+    let escape = &inner[p + 1..];
+    let is_cut = match escape.chars().next() {
+        None => true,
+        Some('u') if escape.len() < 5 => true,
+        Some('u') if escape.len() == 5 => {
+            matches!(u16::from_str_radix(&escape[1..], 16), Ok(0xD800..=0xDBFF))
+        }
+        Some('x') => escape.len() < 3,
+        _ => false,
+    };
+    if is_cut {
+        &inner[..p]
+    } else {
+        inner
+    }
+}
+
+/// Cut a literal at the end of input to its valid part.
+fn cut_literal(literal: &str, kind: LiteralKind, options: &ParseOptions) -> CutLiteral {
+    let first = literal.chars().next().unwrap_or(' ');
+    if let LiteralKind::String(quote) = kind {
+        if first != quote || is_closed(literal, quote) {
+            return CutLiteral::Complete;
+        }
+
+        let inner = trim_partial_escape(&literal[1..]);
+        let closed = format!("{}{}{}", quote, inner, quote);
+        return CutLiteral::Replaced(PartialEnd::InString, JsonTag::Literal(closed, kind));
+    }
+
+    if first.is_ascii_digit() || matches!(first, '-' | '+' | '.') {
+        let mut number = String::from(literal);
+        while !number.is_empty() {
+            if let Ok(Some(_)) = JsonNumber::parse(&number, options) {
+                let number = JsonTag::Literal(number, LiteralKind::Number);
+                return CutLiteral::Replaced(PartialEnd::InNumber, number);
+            }
+
+            number.pop();
+        }
+
+        return CutLiteral::Dropped(PartialEnd::InNumber);
+    }
+
+    let is_keyword_prefix = ["true", "false", "null"]
+        .iter()
+        .any(|keyword| keyword.len() > literal.len() && keyword.starts_with(literal));
+    if is_keyword_prefix {
+        return CutLiteral::Dropped(PartialEnd::InLiteral);
+    }
+
+    CutLiteral::Complete
+}
+
+impl JsonNode {
+    /// Parse the largest valid prefix of truncated JSON from a instance that implements Read trait,
+    /// with default limits.
+    pub fn parse_partial<R>(reader: R) -> Result<PartialNode>
+    where
+        R: Read,
+    {
+        JsonNode::parse_partial_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Parse the largest valid prefix of truncated JSON from a instance that implements Read trait,
+    /// with specified limits.
+    pub fn parse_partial_with_limits<R>(reader: R, limits: &Limits) -> Result<PartialNode>
+    where
+        R: Read,
+    {
+        JsonNode::parse_partial_with_options(reader, &ParseOptions::with_limits(limits))
+    }
+
+    /// Parse the largest valid prefix of truncated JSON from a instance that implements Read trait,
+    /// with specified options.<br>
+    /// Open arrays and objects are closed, a string cut is closed, a number cut keeps its valid part,
+    /// while a property or literal cut is left out.
+    pub fn parse_partial_with_options<R>(reader: R, options: &ParseOptions) -> Result<PartialNode>
+    where
+        R: Read,
+    {
+        let mut json_tags = JsonTag::parse_lossless_with_options(reader, options)?;
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut is_done = false;
+        let mut last = None;
+        for (i, json_tag) in json_tags.iter().enumerate() {
+            if json_tag.is_trivia() {
+                continue;
+            }
+            if is_done {
+                bail!(
+                    "expecting 1 node, but found more: {}",
+                    JsonTag::to_string(&json_tags[i..])
+                );
+            }
+
+            last = Some(i);
+            let is_key = matches!(
+                frames.last(),
+                Some(Frame {
+                    is_object: true,
+                    expect: Expect::First | Expect::Key | Expect::Next,
+                    ..
+                })
+            );
+            match json_tag {
+                JsonTag::Literal(literal, kind) if is_key => {
+                    if let Some(frame) = frames.last_mut() {
+                        if frame.expect == Expect::Next {
+                            frame.comma = None;
+                        }
+                        frame.key = Some(
+                            JsonNode::parse_prop_name(literal, *kind, options)
+                                .unwrap_or_else(|_| literal.clone()),
+                        );
+                        frame.entry_start = i;
+                        frame.expect = Expect::Colon;
+                    }
+                }
+                JsonTag::Literal(_, _) | JsonTag::LeftCurly | JsonTag::LeftSquare => {
+                    match frames.last_mut() {
+                        Some(frame) if !frame.is_object => {
+                            if frame.expect == Expect::Next {
+                                frame.index += 1;
+                                frame.comma = None;
+                            }
+                            frame.entry_start = i;
+                            frame.expect = Expect::Next;
+                        }
+                        Some(frame) => frame.expect = Expect::Next,
+                        None => is_done = matches!(json_tag, JsonTag::Literal(_, _)),
+                    }
+
+                    if *json_tag != JsonTag::LeftCurly && *json_tag != JsonTag::LeftSquare {
+                        continue;
+                    }
+                    options.limits.check_depth(frames.len() + 1)?;
+                    frames.push(Frame {
+                        is_object: *json_tag == JsonTag::LeftCurly,
+                        expect: Expect::First,
+                        key: None,
+                        index: 0,
+                        comma: None,
+                        entry_start: i + 1,
+                    });
+                }
+                JsonTag::RightCurly | JsonTag::RightSquare => match frames.pop() {
+                    Some(_) => is_done = frames.is_empty(),
+                    None if !options.allow_stray_tags => {
+                        bail!("unexpected tag: {}", JsonTag::to_string(&json_tags[i..]))
+                    }
+                    None => (),
+                },
+                JsonTag::Comma => {
+                    if let Some(frame) = frames.last_mut() {
+                        if frame.expect == Expect::Next && !frame.is_object {
+                            frame.index += 1;
+                        }
+                        frame.expect = if frame.is_object {
+                            Expect::Key
+                        } else {
+                            Expect::Value
+                        };
+                        frame.comma = Some(i);
+                        frame.entry_start = i + 1;
+                    }
+                }
+                JsonTag::Colon => {
+                    if let Some(frame) = frames.last_mut() {
+                        if frame.expect == Expect::Colon {
+                            frame.expect = Expect::Value;
+                        }
+                    }
+                }
+                JsonTag::Whitespace(_) | JsonTag::Comment(_) => (),
+            }
+        }
+
+        // cut the entry in progress at the end of input
+        let ends_in_literal = matches!(last, Some(i) if i + 1 == json_tags.len())
+            && matches!(json_tags.last(), Some(JsonTag::Literal(_, _)));
+        let cut_last = match (last, ends_in_literal) {
+            (Some(i), true) => match &json_tags[i] {
+                JsonTag::Literal(literal, kind) => cut_literal(literal, *kind, options),
+                _ => CutLiteral::Complete,
+            },
+            _ => CutLiteral::Complete,
+        };
+        let innermost = frames.last().cloned();
+        let (end, cut) = match &innermost {
+            None if last.is_none() => (PartialEnd::Empty, None),
+            None => match cut_last {
+                CutLiteral::Complete => (PartialEnd::Complete, None),
+                CutLiteral::Replaced(end, json_tag) => {
+                    json_tags.pop();
+                    json_tags.push(json_tag);
+                    (end, None)
+                }
+                CutLiteral::Dropped(end) => (end, Some(0)),
+            },
+            Some(frame) => {
+                let entry_cut = Some(frame.comma.unwrap_or(frame.entry_start));
+                match frame.expect {
+                    Expect::First => (PartialEnd::BeforeEntry, None),
+                    Expect::Key => (PartialEnd::BeforeEntry, frame.comma),
+                    Expect::Value if !frame.is_object => (PartialEnd::BeforeEntry, frame.comma),
+                    Expect::Colon => (PartialEnd::InPropertyName, entry_cut),
+                    Expect::Value => (PartialEnd::AfterColon, entry_cut),
+                    _ => match cut_last {
+                        CutLiteral::Complete => (PartialEnd::AfterEntry, None),
+                        CutLiteral::Replaced(end, json_tag) => {
+                            json_tags.pop();
+                            json_tags.push(json_tag);
+                            (end, None)
+                        }
+                        CutLiteral::Dropped(end) => (end, entry_cut),
+                    },
+                }
+            }
+        };
+        if let Some(cut) = cut {
+            json_tags.truncate(cut);
+        }
+
+        let mut path = String::from("$");
+        let in_entry = matches!(
+            end,
+            PartialEnd::InString
+                | PartialEnd::InNumber
+                | PartialEnd::InLiteral
+                | PartialEnd::AfterColon
+        );
+        for (depth, frame) in frames.iter().enumerate() {
+            if depth + 1 == frames.len() && !in_entry {
+                break;
+            }
+            match (&frame.key, frame.is_object) {
+                (Some(key), true) => path.push_str(&format!("['{}']", key)),
+                (_, false) => path.push_str(&format!("[{}]", frame.index)),
+                _ => (),
+            }
+        }
+
+        for frame in frames.iter().rev() {
+            json_tags.push(if frame.is_object {
+                JsonTag::RightCurly
+            } else {
+                JsonTag::RightSquare
+            });
+        }
+        let node = JsonNode::parse_tags_with_options(&json_tags, options)?
+            .into_iter()
+            .next();
+
+        Ok(PartialNode { node, end, path })
+    }
+}
+
+#[cfg(test)]
+mod json_partial_tests {
+    use super::*;
+
+    /// Test where truncated input ends and what is kept.
+    #[test]
+    fn test_parse_partial() -> Result<()> {
+        let cases = [
+            ("", None, PartialEnd::Empty, "$"),
+            (r#"[1, 2"#, Some("[1, 2]"), PartialEnd::InNumber, "$[1]"),
+            (r#"[1, 2 "#, Some("[1, 2]"), PartialEnd::AfterEntry, "$"),
+            (r#"[1, -"#, Some("[1]"), PartialEnd::InNumber, "$[1]"),
+            (
+                r#"[1, 2.5e"#,
+                Some("[1, 2.5]"),
+                PartialEnd::InNumber,
+                "$[1]",
+            ),
+            (r#"[1, tr"#, Some("[1]"), PartialEnd::InLiteral, "$[1]"),
+            (r#"[1, "#, Some("[1]"), PartialEnd::BeforeEntry, "$"),
+            (
+                r#"{"a": "he\u00"#,
+                Some(r#"{"a": "he"}"#),
+                PartialEnd::InString,
+                "$['a']",
+            ),
+            (
+                r#"{"a": ["x\"#,
+                Some(r#"{"a": ["x"]}"#),
+                PartialEnd::InString,
+                "$['a'][0]",
+            ),
+            (
+                r#"{"a": 1, "b"#,
+                Some(r#"{"a": 1}"#),
+                PartialEnd::InPropertyName,
+                "$",
+            ),
+            (
+                r#"{"a": 1, "b": "#,
+                Some(r#"{"a": 1}"#),
+                PartialEnd::AfterColon,
+                "$['b']",
+            ),
+            (
+                r#"{"a": {"#,
+                Some(r#"{"a": {}}"#),
+                PartialEnd::BeforeEntry,
+                "$['a']",
+            ),
+            (
+                r#"{"a": true}"#,
+                Some(r#"{"a": true}"#),
+                PartialEnd::Complete,
+                "$",
+            ),
+        ];
+        for (json, expected, end, path) in cases {
+            let partial = JsonNode::parse_partial(json.as_bytes())?;
+            assert_eq!(
+                partial.node.map(|n| n.to_string()).as_deref(),
+                expected,
+                "{}",
+                json
+            );
+            assert_eq!(partial.end, end, "{}", json);
+            assert_eq!(partial.path, path, "{}", json);
+        }
+
+        assert!(JsonNode::parse_partial(r#"{"a": 1} 2"#.as_bytes()).is_err());
+
+        Ok(())
+    }
+
+    /// Test parsing every prefix of a growing buffer.
+    #[test]
+    fn test_parse_growing() -> Result<()> {
+        let json = r#"{"id": 12, "text": "héllo \"x\" 😀", "items": [true, null, {"k": -1.5e3}], "e": {}}"#;
+        for (end, _) in json.char_indices().skip(1) {
+            let partial = JsonNode::parse_partial_with_options(
+                &json.as_bytes()[..end],
+                &ParseOptions::strict(),
+            )?;
+            assert!(partial.node.is_some(), "{}", &json[..end]);
+            assert!(!partial.is_complete());
+        }
+
+        let partial = JsonNode::parse_partial(json.as_bytes())?;
+        assert!(partial.is_complete());
+        assert_eq!(
+            partial.node,
+            Some(JsonNode::parse_single_node(json.as_bytes())?)
+        );
+
+        Ok(())
+    }
+
+    /// Test malformed input returns error rather than panicking, with cases found by fuzzing.
+    #[test]
+    fn test_malformed_partial() -> Result<()> {
+        for json in [
+            "}{",
+            "{:}",
+            "{\"a\" 1 2}",
+            "{\"a\": [1}, 2]",
+            "[\"\\uD83D\", '\\u', \"\\\"]",
+            "[0x, -., 1e, .e1, tru, True, NaN, -NaN]",
+            "[\u{feff}1, \u{2028}, \"é😀\0\"]",
+            "/* [1",
+            "// x\r[",
+            "{\"a\": {\"b\": [,]]",
+        ] {
+            for options in [
+                ParseOptions::default(),
+                ParseOptions::strict(),
+                ParseOptions::json5(),
+            ] {
+                let _ = JsonNode::parse_partial_with_options(json.as_bytes(), &options);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -3,7 +3,10 @@
 use anyhow::{bail, Result};
 use std::{io::Read, str::FromStr};
 
+use crate::encoding::*;
 use crate::json_node::*;
+use crate::json_number::*;
+use crate::limits::*;
 use crate::peekable_codepoints::*;
 // use crate::filter_expression::*;
 
@@ -101,7 +104,10 @@ impl ArrayElementSelector {
     /// Parse range-type array element selector from a string.
     fn parse_range(elem_selector_str: String) -> Result<Self> {
         let range_str = ArrayElementSelector::trim_brackets(elem_selector_str);
-        let colon_index = range_str.chars().position(|c| c == ':').unwrap();
+        let colon_index = match range_str.chars().position(|c| c == ':') {
+            Some(colon_index) => colon_index,
+            None => bail!("expecting colon in range element selector: {}", range_str),
+        };
         let range_left_str: String = range_str.chars().take(colon_index).collect();
         let range_right_str: String = range_str.chars().skip(colon_index + 1).collect();
 
@@ -344,7 +350,10 @@ impl JsonPathPart {
 
         let last_frag_type = PartFragType::identify_frag(peekable_cp)?;
         if last_frag_type == PartFragType::Filter {
-            todo!("implement json path filter");
+            bail!(
+                "json path filter not supported yet: {}...",
+                peekable_cp.peek(2)?
+            );
         }
 
         let part = JsonPathPart::new(&path_name, elem_selector /*, filter*/);
@@ -352,100 +361,107 @@ impl JsonPathPart {
     }
 }
 
-/// Get selected elements out of a mut slice reference as mut reference by indexes.
-fn get_mut_by_indexes<'a, T>(vec: &'a mut [T], indexes: &[usize]) -> Vec<&'a mut T> {
-    let mut results = Vec::new();
-
-    let mut i = 0;
-    let mut iter = vec.iter_mut();
-    loop {
-        let elem = iter.next();
-        if elem.is_none() {
-            break;
-        }
-
-        let elem = elem.unwrap();
-        if indexes.contains(&i) {
-            results.push(elem);
-        }
-
-        i += 1;
-    }
-
-    results
+/// Get selected elements out of a mut slice reference as mut reference by indexes, along with their indexes.
+fn get_mut_by_indexes<'a, T>(vec: &'a mut [T], indexes: &[usize]) -> Vec<(usize, &'a mut T)> {
+    vec.iter_mut()
+        .enumerate()
+        .filter(|(i, _)| indexes.contains(i))
+        .collect()
 }
 
-/// Get selected elements out of a mut slice reference as mut reference by index range.
-fn get_mut_by_index_range<T>(vec: &mut [T], start: usize, end: usize) -> Vec<&mut T> {
-    let mut results = Vec::new();
-
-    let mut i = 0;
-    let mut iter = vec.iter_mut();
-    loop {
-        let elem = iter.next();
-        if elem.is_none() {
-            break;
-        }
-        if i < start {
-            i += 1;
-            continue;
-        }
-        if i >= end {
-            break;
-        }
-
-        let elem = elem.unwrap();
-        results.push(elem);
+/// Get selected elements out of a mut slice reference as mut reference by index range, along with their indexes.
+fn get_mut_by_index_range<T>(vec: &mut [T], start: usize, end: usize) -> Vec<(usize, &mut T)> {
+    vec.iter_mut()
+        .enumerate()
+        .skip(start)
+        .take(end.saturating_sub(start))
+        .collect()
+}
 
-        i += 1;
-    }
+/// Location of a JSON node inside its root, as indexes of array elements or object properties from the root down.
+pub(crate) type NodeLocation = Vec<usize>;
 
-    results
+/// Get location of a child of the JSON node at specified location, by index of the child.
+fn located_at(location: &[usize], index: usize) -> NodeLocation {
+    let mut child_location = location.to_vec();
+    child_location.push(index);
+    child_location
 }
 
 /// JSONPath, composed of JSONPath parts.
 #[derive(Debug, PartialEq)]
 pub struct JsonPath {
     pub parts: Vec<JsonPathPart>,
+    limits: Limits,
 }
 
 impl JsonPath {
-    /// Create JsonPath from a list of JSONPath parts.
-    fn new(parts: Vec<JsonPathPart>) -> Self {
-        JsonPath { parts }
+    /// Create JsonPath from a list of JSONPath parts, evaluated with specified limits.
+    fn new(parts: Vec<JsonPathPart>, limits: &Limits) -> Self {
+        JsonPath {
+            parts,
+            limits: limits.clone(),
+        }
     }
 
-    /// Parse JsonPath from a string representation of it.
+    /// Parse JsonPath from a string representation of it, with default limits.
     pub fn parse(path_str: &str) -> Result<Self> {
+        JsonPath::parse_with_limits(path_str, &Limits::default())
+    }
+
+    /// Parse JsonPath from a string representation of it, with specified limits.
+    pub fn parse_with_limits(path_str: &str, limits: &Limits) -> Result<Self> {
+        limits.check_path_len(path_str.chars().count())?;
+
         let mut path_parts = Vec::new();
-        let mut peekable_cp = PeekableCodePoints::new(path_str.as_bytes());
+        let mut peekable_cp =
+            PeekableCodePoints::with_encoding(path_str.as_bytes(), Some(Encoding::Utf8), false);
         loop {
-            let part = JsonPathPart::parse_next(&mut peekable_cp)?;
-            if part.is_none() {
-                break;
-            }
+            let part = match JsonPathPart::parse_next(&mut peekable_cp)? {
+                Some(part) => part,
+                None => break,
+            };
 
-            path_parts.push(part.unwrap());
+            path_parts.push(part);
 
             if Some('.') == peekable_cp.peek_char(0)? {
                 peekable_cp.skip(1)?;
             }
         }
 
-        let json_path = JsonPath::new(path_parts);
+        let json_path = JsonPath::new(path_parts, limits);
         Ok(json_path)
     }
 
-    /// Evaluate JSONPath to a list of mutable JsonNode.
-    fn evaluate_json_path<'a>(&self, json_node: &'a mut JsonNode) -> Result<Vec<&'a mut JsonNode>> {
-        let mut current = vec![json_node];
+    /// Evaluate JSONPath to a list of mutable JsonNode,
+    /// failing if the selected nodes are nested deeper than the limits it is parsed with.
+    pub fn evaluate<'a>(&self, json_node: &'a mut JsonNode) -> Result<Vec<&'a mut JsonNode>> {
+        let located = self.evaluate_located(json_node)?;
+        Ok(located.into_iter().map(|(_, n)| n).collect())
+    }
+
+    /// Evaluate JSONPath to a list of locations of selected JSON nodes.
+    pub(crate) fn evaluate_locations(&self, json_node: &mut JsonNode) -> Result<Vec<NodeLocation>> {
+        let located = self.evaluate_located(json_node)?;
+        Ok(located.into_iter().map(|(l, _)| l).collect())
+    }
+
+    /// Evaluate JSONPath to a list of mutable JsonNode, along with their locations.
+    fn evaluate_located<'a>(
+        &self,
+        json_node: &'a mut JsonNode,
+    ) -> Result<Vec<(NodeLocation, &'a mut JsonNode)>> {
+        let mut current = vec![(NodeLocation::new(), json_node)];
+        // all selected nodes share the same depth, as each part descends one level for each of them
+        let mut depth = 0;
         for path_part in &self.parts {
             match path_part.path_name.as_str() {
                 "$" => (),
                 "@" => (),
                 pn => {
+                    depth += 1;
                     let mut next = Vec::new();
-                    for c in current {
+                    for (location, c) in current {
                         // only handle object notation
                         if let JsonNode::Object(pl) = c {
                             let prop_index = pl.iter().position(|x| x.name == pn);
@@ -453,7 +469,7 @@ impl JsonPath {
                             // ignore if not found
                             if let Some(prop_index) = prop_index {
                                 let n = &mut pl[prop_index].value;
-                                next.push(n);
+                                next.push((located_at(&location, prop_index), n));
                             }
                         }
                     }
@@ -465,30 +481,30 @@ impl JsonPath {
             match &path_part.elem_selector {
                 None => (),
                 Some(es) => {
+                    depth += 1;
                     let mut next = Vec::new();
-                    for c in current {
+                    for (location, c) in current {
+                        let mut selected = Vec::new();
                         match c {
                             JsonNode::Array(arr) => {
                                 let arr_len = arr.len();
                                 match es {
                                     ArrayElementSelector::Single(i) => {
                                         if *i < arr_len {
-                                            next.push(&mut arr[*i]);
+                                            selected.push((*i, &mut arr[*i]));
                                         }
                                     }
                                     ArrayElementSelector::Multiple(il) => {
-                                        let mut selected = get_mut_by_indexes(arr, il);
-                                        next.append(&mut selected);
+                                        selected = get_mut_by_indexes(arr, il);
                                     }
                                     ArrayElementSelector::Range(s, e) => {
                                         match s {
                                             None => {
                                                 match e {
-                                                    None => next.extend(arr.iter_mut()),
+                                                    None => selected = get_mut_by_index_range(arr, 0, arr_len),
                                                     Some(e) if *e < 0 => bail!("array element selector end index must not be negative: [:{}]", e),
                                                     Some(e) if *e >= 0 => {
-                                                        let mut selected = get_mut_by_index_range(arr, 0, *e as usize);
-                                                        next.append(&mut selected);
+                                                        selected = get_mut_by_index_range(arr, 0, *e as usize);
                                                     }
                                                     _ => bail!("range element selector unreachable code reached!"),
                                                 }
@@ -496,8 +512,8 @@ impl JsonPath {
                                             Some(s) if *s < 0 => {
                                                 match e {
                                                     None => {
-                                                        let mut selected = get_mut_by_index_range(arr, ((arr_len as i32) + *s) as usize, arr_len);
-                                                        next.append(&mut selected);
+                                                        let start = arr_len.saturating_sub(s.unsigned_abs() as usize);
+                                                        selected = get_mut_by_index_range(arr, start, arr_len);
                                                     }
                                                     Some(e) => bail!("array element selector start index must not be negative when end index specified: [{}:{}]", s, e),
                                                 }
@@ -505,13 +521,11 @@ impl JsonPath {
                                             Some(s) if *s >= 0 => {
                                                 match e {
                                                     None => {
-                                                        let mut selected = get_mut_by_index_range(arr, *s as usize, arr_len);
-                                                        next.append(&mut selected);
+                                                        selected = get_mut_by_index_range(arr, *s as usize, arr_len);
                                                     }
                                                     Some(e) if *e < 0 => bail!("array element selector end index must not be negative: [{}:{}]", s, e),
                                                     Some(e) if *e >= 0 => {
-                                                        let mut selected = get_mut_by_index_range(arr, *s as usize, *e as usize);
-                                                        next.append(&mut selected);
+                                                        selected = get_mut_by_index_range(arr, *s as usize, *e as usize);
                                                     }
                                                     _ => bail!("range element selector unreachable code reached!"),
                                                 }
@@ -520,18 +534,25 @@ impl JsonPath {
                                         }
                                     }
                                     ArrayElementSelector::All => {
-                                        let mut selected = get_mut_by_index_range(arr, 0, arr_len);
-                                        next.append(&mut selected);
+                                        selected = get_mut_by_index_range(arr, 0, arr_len);
                                     }
                                 }
                             }
                             _ => bail!("element selector must be applied on array notation, {} is not array", path_part.path_name)
                         }
+
+                        for (i, n) in selected {
+                            next.push((located_at(&location, i), n));
+                        }
                     }
 
                     current = next;
                 }
             }
+
+            if !current.is_empty() {
+                self.limits.check_depth(depth)?;
+            }
         }
 
         Ok(current)
@@ -543,7 +564,7 @@ impl JsonPath {
         json_node: &'a mut JsonNode,
     ) -> Result<Vec<&'a JsonNode>> {
         let mut result = Vec::new();
-        let nodes = self.evaluate_json_path(json_node)?;
+        let nodes = self.evaluate(json_node)?;
         for n in nodes {
             result.push(&*n);
         }
@@ -552,12 +573,12 @@ impl JsonPath {
     }
 
     /// Get a list of number value by a JsonPath. The nodes selected by JsonPath must be of number type.
-    pub(crate) fn json_path_get_number(&self, json_node: &mut JsonNode) -> Result<Vec<f64>> {
+    pub(crate) fn json_path_get_number(&self, json_node: &mut JsonNode) -> Result<Vec<JsonNumber>> {
         let mut numbers = Vec::new();
         let selected = self.json_path_get_raw(json_node)?;
         for n in selected {
             match n {
-                JsonNode::PlainNumber(num) => numbers.push(*num),
+                JsonNode::PlainNumber(num) => numbers.push(num.clone()),
                 _ => bail!("expecting number, but found: {:?}", n),
             }
         }
@@ -599,7 +620,11 @@ impl JsonPath {
     }
 
     /// Set the nodes selected by JsonPath to a value of specified number.
-    pub(crate) fn json_path_set_number(&self, json_node: &mut JsonNode, value: f64) -> Result<()> {
+    pub(crate) fn json_path_set_number(
+        &self,
+        json_node: &mut JsonNode,
+        value: JsonNumber,
+    ) -> Result<()> {
         self.json_path_set_raw(json_node, &JsonNode::PlainNumber(value))
     }
 
@@ -619,7 +644,7 @@ impl JsonPath {
         json_node: &mut JsonNode,
         value: &JsonNode,
     ) -> Result<()> {
-        let selected = self.evaluate_json_path(json_node)?;
+        let selected = self.evaluate(json_node)?;
         for n in selected {
             *n = value.clone();
         }
@@ -629,8 +654,13 @@ impl JsonPath {
 }
 
 impl JsonNode {
-    /// Get a number value of the node selected by specified JSONPath.
+    /// Get a number value of the node selected by specified JSONPath, as f64 which may lose precision.
     pub fn get_number(&mut self, json_path: &str) -> Result<Option<f64>> {
+        self.get_f64(json_path)
+    }
+
+    /// Get a JSON number of the node selected by specified JSONPath.
+    pub fn get_json_number(&mut self, json_path: &str) -> Result<Option<JsonNumber>> {
         let json_path = JsonPath::parse(json_path)?;
         let mut selected = json_path.json_path_get_number(self)?;
         if selected.is_empty() {
@@ -641,6 +671,40 @@ impl JsonNode {
         Ok(Some(selected))
     }
 
+    /// Get an i64 value of the node selected by specified JSONPath. The number must be an integer fitting in i64.
+    pub fn get_i64(&mut self, json_path: &str) -> Result<Option<i64>> {
+        match self.get_json_number(json_path)? {
+            None => Ok(None),
+            Some(n) => match n.as_i64() {
+                Some(i) => Ok(Some(i)),
+                None => bail!("expecting i64, but found: {}", n),
+            },
+        }
+    }
+
+    /// Get an u64 value of the node selected by specified JSONPath. The number must be an integer fitting in u64.
+    pub fn get_u64(&mut self, json_path: &str) -> Result<Option<u64>> {
+        match self.get_json_number(json_path)? {
+            None => Ok(None),
+            Some(n) => match n.as_u64() {
+                Some(u) => Ok(Some(u)),
+                None => bail!("expecting u64, but found: {}", n),
+            },
+        }
+    }
+
+    /// Get a f64 value of the node selected by specified JSONPath, which may lose precision.
+    pub fn get_f64(&mut self, json_path: &str) -> Result<Option<f64>> {
+        let number = self.get_json_number(json_path)?;
+        Ok(number.map(|n| n.as_f64()))
+    }
+
+    /// Get the original text of the number selected by specified JSONPath.
+    pub fn get_number_str(&mut self, json_path: &str) -> Result<Option<String>> {
+        let number = self.get_json_number(json_path)?;
+        Ok(number.map(|n| String::from(n.as_str())))
+    }
+
     /// Get a bool value of the node selected by specified JSONPath.
     pub fn get_bool(&mut self, json_path: &str) -> Result<Option<bool>> {
         let json_path = JsonPath::parse(json_path)?;
@@ -686,6 +750,21 @@ impl JsonNode {
 
     /// Set the value of nodes selected by specified JSONPath to specified number.
     pub fn set_number(&mut self, json_path: &str, value: f64) -> Result<()> {
+        self.set_json_number(json_path, JsonNumber::from(value))
+    }
+
+    /// Set the value of nodes selected by specified JSONPath to specified i64.
+    pub fn set_i64(&mut self, json_path: &str, value: i64) -> Result<()> {
+        self.set_json_number(json_path, JsonNumber::from(value))
+    }
+
+    /// Set the value of nodes selected by specified JSONPath to specified u64.
+    pub fn set_u64(&mut self, json_path: &str, value: u64) -> Result<()> {
+        self.set_json_number(json_path, JsonNumber::from(value))
+    }
+
+    /// Set the value of nodes selected by specified JSONPath to specified JSON number.
+    pub fn set_json_number(&mut self, json_path: &str, value: JsonNumber) -> Result<()> {
         let json_path = JsonPath::parse(json_path)?;
         json_path.json_path_set_number(self, value)?;
         Ok(())
@@ -724,21 +803,24 @@ mod json_path_tests {
         let json_path = JsonPath::parse(json_path_str)?;
         assert_eq!(
             json_path,
-            JsonPath::new(vec![
-                JsonPathPart::new(
-                    "$",
-                    Some(ArrayElementSelector::Range(Some(-1), None)) /*, None*/
-                ),
-                JsonPathPart::new(
-                    "store",
-                    Some(ArrayElementSelector::Range(None, Some(3))) /*, None*/
-                ),
-                JsonPathPart::new(
-                    "bicycle",
-                    Some(ArrayElementSelector::Multiple(vec![0, 13])) /*, None*/
-                ),
-                JsonPathPart::new("color", Some(ArrayElementSelector::All) /*, None*/),
-            ])
+            JsonPath::new(
+                vec![
+                    JsonPathPart::new(
+                        "$",
+                        Some(ArrayElementSelector::Range(Some(-1), None)) /*, None*/
+                    ),
+                    JsonPathPart::new(
+                        "store",
+                        Some(ArrayElementSelector::Range(None, Some(3))) /*, None*/
+                    ),
+                    JsonPathPart::new(
+                        "bicycle",
+                        Some(ArrayElementSelector::Multiple(vec![0, 13])) /*, None*/
+                    ),
+                    JsonPathPart::new("color", Some(ArrayElementSelector::All) /*, None*/),
+                ],
+                &Limits::default()
+            )
         );
 
         Ok(())
@@ -751,21 +833,24 @@ mod json_path_tests {
         let json_path = JsonPath::parse(json_path_str)?;
         assert_eq!(
             json_path,
-            JsonPath::new(vec![
-                JsonPathPart::new(
-                    "$",
-                    Some(ArrayElementSelector::Range(Some(-1), None)) /*, None*/
-                ),
-                JsonPathPart::new(
-                    "store",
-                    Some(ArrayElementSelector::Range(None, Some(3))) /*, None*/
-                ),
-                JsonPathPart::new(
-                    "bicycle",
-                    Some(ArrayElementSelector::Multiple(vec![0, 13])) /*, None*/
-                ),
-                JsonPathPart::new("color", Some(ArrayElementSelector::All) /*, None*/),
-            ])
+            JsonPath::new(
+                vec![
+                    JsonPathPart::new(
+                        "$",
+                        Some(ArrayElementSelector::Range(Some(-1), None)) /*, None*/
+                    ),
+                    JsonPathPart::new(
+                        "store",
+                        Some(ArrayElementSelector::Range(None, Some(3))) /*, None*/
+                    ),
+                    JsonPathPart::new(
+                        "bicycle",
+                        Some(ArrayElementSelector::Multiple(vec![0, 13])) /*, None*/
+                    ),
+                    JsonPathPart::new("color", Some(ArrayElementSelector::All) /*, None*/),
+                ],
+                &Limits::default()
+            )
         );
 
         Ok(())
@@ -815,7 +900,7 @@ mod json_path_tests {
             selected,
             vec![
                 &JsonNode::PlainBoolean(false),
-                &JsonNode::PlainNumber(3f64),
+                &JsonNode::PlainNumber(JsonNumber::from(3f64)),
                 &JsonNode::PlainString(String::from("yes")),
                 &JsonNode::PlainString(String::from("no")),
             ]
@@ -894,9 +979,18 @@ mod json_path_tests {
         json_path.json_path_set_raw(
             &mut json_node,
             &JsonNode::Object(vec![
-                JsonObjProp::new(String::from("hello"), JsonNode::PlainNumber(1f64)),
-                JsonObjProp::new(String::from("world"), JsonNode::PlainNumber(2f64)),
-                JsonObjProp::new(String::from("love"), JsonNode::PlainNumber(3f64)),
+                JsonObjProp::new(
+                    String::from("hello"),
+                    JsonNode::PlainNumber(JsonNumber::from(1f64)),
+                ),
+                JsonObjProp::new(
+                    String::from("world"),
+                    JsonNode::PlainNumber(JsonNumber::from(2f64)),
+                ),
+                JsonObjProp::new(
+                    String::from("love"),
+                    JsonNode::PlainNumber(JsonNumber::from(3f64)),
+                ),
             ]),
         )?;
 
@@ -904,12 +998,88 @@ mod json_path_tests {
         assert_eq!(
             selected,
             vec![&JsonNode::Object(vec![
-                JsonObjProp::new(String::from("hello"), JsonNode::PlainNumber(1f64)),
-                JsonObjProp::new(String::from("world"), JsonNode::PlainNumber(2f64)),
-                JsonObjProp::new(String::from("love"), JsonNode::PlainNumber(3f64)),
+                JsonObjProp::new(
+                    String::from("hello"),
+                    JsonNode::PlainNumber(JsonNumber::from(1f64))
+                ),
+                JsonObjProp::new(
+                    String::from("world"),
+                    JsonNode::PlainNumber(JsonNumber::from(2f64))
+                ),
+                JsonObjProp::new(
+                    String::from("love"),
+                    JsonNode::PlainNumber(JsonNumber::from(3f64))
+                ),
             ])]
         );
 
         Ok(())
     }
+
+    /// Test malformed JSONPath returns error rather than panicking, with cases found by fuzzing.
+    #[test]
+    fn test_malformed_json_path() -> Result<()> {
+        for path in [
+            "$.a[?]",
+            "$.a[?(@.b < 1)]",
+            "$[?a:]",
+            "$.a[:",
+            "$['a",
+            "$['a'x",
+            "$.a[-1-1]",
+        ] {
+            assert!(JsonPath::parse(path).is_err(), "{}", path);
+        }
+        for path in [
+            "", " ", "$.", ".(", "$.a[*-5]", "$.a[:]", "$.a[.]", "$..", "$.$:)0",
+        ] {
+            let _ = JsonPath::parse(path);
+        }
+
+        let mut json_node = JsonNode::parse_single_node(r#"[1, 2, 3]"#.as_bytes())?;
+        assert_eq!(
+            JsonPath::parse("$[-10:]")?
+                .json_path_get_raw(&mut json_node)?
+                .len(),
+            3
+        );
+        assert!(json_node.get_raw("$[?(@ > 1)]").is_err());
+
+        Ok(())
+    }
+
+    /// Test JSONPath evaluation honouring the limits specified by caller.
+    #[test]
+    fn test_json_path_limits() -> Result<()> {
+        let json = r#"{"array": [1, 2, 3], "object": {"a": {"b": [true]}}}"#;
+        let mut json_node = JsonNode::parse_single_node(json.as_bytes())?;
+
+        let limits = Limits {
+            max_depth: Some(2),
+            ..Limits::default()
+        };
+        let json_path = JsonPath::parse_with_limits("$.object.a", &limits)?;
+        assert_eq!(json_path.evaluate(&mut json_node)?.len(), 1);
+        let json_path = JsonPath::parse_with_limits("$.array[*]", &limits)?;
+        assert_eq!(json_path.evaluate(&mut json_node)?.len(), 3);
+        let json_path = JsonPath::parse_with_limits("$.object.a.b[0]", &limits)?;
+        let err = json_path.evaluate(&mut json_node).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::DepthExceeded(2))
+        );
+        assert_eq!(json_node.get_bool("$.object.a.b[0]")?, Some(true));
+
+        let limits = Limits {
+            max_path_len: Some(4),
+            ..Limits::default()
+        };
+        let err = JsonPath::parse_with_limits("$.object", &limits).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::PathTooLong(4))
+        );
+
+        Ok(())
+    }
 }
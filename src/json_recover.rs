@@ -0,0 +1,464 @@
+//! Parsing with recovery from errors, collecting every problem found and keeping a best-effort JSON node.
+
+use anyhow::Result;
+use std::io::Read;
+
+use crate::json_node::*;
+use crate::json_tag::*;
+use crate::json_validate::*;
+use crate::limits::*;
+use crate::parse_options::*;
+
+/// JSON node parsed with recovery from errors, together with the problems found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredNode {
+    /// Best-effort JSON node, with null as placeholder of each missing or invalid value,
+    /// None if no value found.
+    pub node: Option<JsonNode>,
+
+    /// Every problem found, ordered by position.
+    pub diagnostics: Vec<JsonDiagnostic>,
+
+    /// JSONPaths of the placeholders of missing or invalid values in the node, such as $['items'][2].
+    pub placeholders: Vec<String>,
+}
+
+impl RecoveredNode {
+    /// Whether no problem is found, so the node is exactly what a normal parse returns.
+    pub fn is_valid(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// State of parsing with recovery.
+struct Recoverer<'a> {
+    options: &'a ParseOptions,
+    json_tags: &'a [JsonTag],
+    positions: Vec<Position>,
+    /// Index of the current JSON tag.
+    i: usize,
+    /// Whether each array or object open is an object, innermost last.
+    open: Vec<bool>,
+    /// Fragments of the JSONPath of the current value, such as ['items'] and [2].
+    path: Vec<String>,
+    diagnostics: Vec<JsonDiagnostic>,
+    placeholders: Vec<String>,
+}
+
+impl JsonNode {
+    /// Parse a single JSON node with recovery from errors from a instance that implements Read trait,
+    /// with default limits.
+    pub fn parse_recovering<R>(reader: R) -> Result<RecoveredNode>
+    where
+        R: Read,
+    {
+        JsonNode::parse_recovering_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Parse a single JSON node with recovery from errors from a instance that implements Read trait,
+    /// with specified limits.
+    pub fn parse_recovering_with_limits<R>(reader: R, limits: &Limits) -> Result<RecoveredNode>
+    where
+        R: Read,
+    {
+        JsonNode::parse_recovering_with_options(reader, &ParseOptions::with_limits(limits))
+    }
+
+    /// Parse a single JSON node with recovery from errors from a instance that implements Read trait,
+    /// with specified options.<br>
+    /// After an error, parsing resynchronizes at the next comma or closing bracket,
+    /// and a missing or invalid value is replaced by null as placeholder.
+    /// Only I/O errors, decoding errors, limit errors and unterminated comments fail.
+    pub fn parse_recovering_with_options<R>(
+        reader: R,
+        options: &ParseOptions,
+    ) -> Result<RecoveredNode>
+    where
+        R: Read,
+    {
+        let json_tags = JsonTag::parse_lossless_with_options(reader, &tokenize_options(options))?;
+        JsonNode::parse_tags_recovering(&json_tags, options)
+    }
+
+    /// Parse a single JSON node with recovery from errors from a JSON tag slice, with specified options.<br>
+    /// Pass JSON tags parsed losslessly, so the positions of diagnostics are exact.
+    pub fn parse_tags_recovering(
+        json_tags: &[JsonTag],
+        options: &ParseOptions,
+    ) -> Result<RecoveredNode> {
+        options.limits.check_tags(json_tags.len())?;
+        let mut recoverer = Recoverer {
+            options,
+            json_tags,
+            positions: positions(json_tags),
+            i: 0,
+            open: Vec::new(),
+            path: Vec::new(),
+            diagnostics: Vec::new(),
+            placeholders: Vec::new(),
+        };
+
+        let mut node = None;
+        while let Some(json_tag) = recoverer.peek() {
+            match json_tag {
+                JsonTag::Literal(_, _) | JsonTag::LeftCurly | JsonTag::LeftSquare => {
+                    if node.is_none() {
+                        node = Some(recoverer.value()?);
+                        continue;
+                    }
+
+                    // values after the top-level value are checked, but left out
+                    recoverer.report(DiagnosticKind::ExtraValue, "end of input");
+                    let placeholder_count = recoverer.placeholders.len();
+                    recoverer.value()?;
+                    recoverer.placeholders.truncate(placeholder_count);
+                }
+                _ => {
+                    if !options.allow_stray_tags {
+                        let expected = if node.is_some() {
+                            "end of input"
+                        } else {
+                            "value"
+                        };
+                        recoverer.report(DiagnosticKind::UnexpectedTag, expected);
+                    }
+                    recoverer.i += 1;
+                }
+            }
+        }
+        if node.is_none() {
+            recoverer.report(DiagnosticKind::MissingValue, "value");
+        }
+
+        recoverer.diagnostics.sort_by_key(|d| d.offset);
+        Ok(RecoveredNode {
+            node,
+            diagnostics: recoverer.diagnostics,
+            placeholders: recoverer.placeholders,
+        })
+    }
+}
+
+impl<'a> Recoverer<'a> {
+    /// Get the current JSON tag, skipping whitespace and comments, None at the end of input.
+    fn peek(&mut self) -> Option<&'a JsonTag> {
+        while let Some(json_tag) = self.json_tags.get(self.i) {
+            match json_tag {
+                JsonTag::Comment(_) if !self.options.allow_comments => {
+                    self.report(DiagnosticKind::UnexpectedTag, "whitespace");
+                }
+                JsonTag::Whitespace(s)
+                    if !self.options.allow_non_json_whitespace
+                        && !s.chars().all(|c| matches!(c, ' ' | '\t' | '\n' | '\r')) =>
+                {
+                    self.report(DiagnosticKind::UnexpectedTag, "space, tab or line break");
+                }
+                JsonTag::Comment(_) | JsonTag::Whitespace(_) => (),
+                _ => return self.json_tags.get(self.i),
+            }
+
+            self.i += 1;
+        }
+
+        None
+    }
+
+    /// Describe the current JSON tag as it is found in the input.
+    fn found(&self) -> String {
+        match self.json_tags.get(self.i) {
+            None => String::from("end of input"),
+            Some(JsonTag::Whitespace(s)) => format!("{:?}", s),
+            Some(JsonTag::Literal(s, _) | JsonTag::Comment(s)) => s.clone(),
+            Some(JsonTag::LeftCurly) => String::from("'{'"),
+            Some(JsonTag::LeftSquare) => String::from("'['"),
+            Some(JsonTag::RightCurly) => String::from("'}'"),
+            Some(JsonTag::RightSquare) => String::from("']'"),
+            Some(JsonTag::Comma) => String::from("','"),
+            Some(JsonTag::Colon) => String::from("':'"),
+        }
+    }
+
+    /// Report a problem at the current JSON tag.
+    fn report(&mut self, kind: DiagnosticKind, expected: &str) {
+        let position = self.positions[self.i.min(self.json_tags.len())];
+        let found = self.found();
+        self.diagnostics
+            .push(JsonDiagnostic::new(position, kind, expected, &found));
+    }
+
+    /// Get null as the placeholder of the current value, recording its path.
+    fn placeholder(&mut self) -> JsonNode {
+        self.placeholders.push(format!("${}", self.path.concat()));
+        JsonNode::PlainNull
+    }
+
+    /// Skip JSON tags up to the next comma or closing bracket of the innermost array or object.
+    fn resync(&mut self) {
+        let mut depth = 0;
+        while let Some(json_tag) = self.json_tags.get(self.i) {
+            match json_tag {
+                JsonTag::LeftCurly | JsonTag::LeftSquare => depth += 1,
+                JsonTag::Comma | JsonTag::RightCurly | JsonTag::RightSquare if depth == 0 => return,
+                JsonTag::RightCurly | JsonTag::RightSquare => depth -= 1,
+                _ => (),
+            }
+
+            self.i += 1;
+        }
+    }
+
+    /// Parse the value at the current JSON tag, or get a placeholder if it is not a value.
+    fn value(&mut self) -> Result<JsonNode> {
+        let node = match self.peek() {
+            Some(JsonTag::Literal(literal, kind)) => {
+                match JsonNode::parse_plain(literal, *kind, self.options) {
+                    Ok(node) => {
+                        self.options
+                            .limits
+                            .check_literal_len(literal.chars().count())?;
+                        self.i += 1;
+                        node
+                    }
+                    Err(_) => {
+                        self.report(DiagnosticKind::InvalidLiteral, expected_value(*kind));
+                        self.i += 1;
+                        self.placeholder()
+                    }
+                }
+            }
+            Some(JsonTag::LeftCurly) => self.container(true)?,
+            Some(JsonTag::LeftSquare) => self.container(false)?,
+            _ => {
+                self.report(DiagnosticKind::MissingValue, "value");
+                self.placeholder()
+            }
+        };
+
+        Ok(node)
+    }
+
+    /// Parse the array or object at the current JSON tag.
+    fn container(&mut self, is_object: bool) -> Result<JsonNode> {
+        self.options.limits.check_depth(self.open.len() + 1)?;
+        self.open.push(is_object);
+        self.i += 1;
+
+        let mut elements = Vec::new();
+        let mut props = Vec::new();
+        let mut count = 0;
+        let mut comma = None;
+        loop {
+            let entry_expected = match (is_object, count, comma) {
+                (true, _, Some(_)) => "property name",
+                (false, _, Some(_)) => "value",
+                (true, 0, None) => "property name or '}'",
+                (false, 0, None) => "value or ']'",
+                (true, _, None) => "',' or '}'",
+                (false, _, None) => "',' or ']'",
+            };
+            match self.peek() {
+                None => {
+                    self.report(DiagnosticKind::UnclosedBracket, closing(is_object));
+                    break;
+                }
+                Some(JsonTag::RightCurly | JsonTag::RightSquare) => {
+                    let is_closing_object = self.json_tags[self.i] == JsonTag::RightCurly;
+                    if is_closing_object == is_object {
+                        if let Some(comma) = comma {
+                            if count > 0
+                                && !self.options.allow_trailing_commas
+                                && !self.options.allow_extra_commas
+                            {
+                                let position = self.positions[comma];
+                                let expected = closing(is_object);
+                                self.diagnostics.push(JsonDiagnostic::new(
+                                    position,
+                                    DiagnosticKind::ExtraComma,
+                                    expected,
+                                    "','",
+                                ));
+                            }
+                        }
+
+                        self.i += 1;
+                        break;
+                    }
+                    if self.open.contains(&is_closing_object) {
+                        // the bracket closes an outer array or object
+                        self.report(DiagnosticKind::MismatchedBracket, closing(is_object));
+                        break;
+                    }
+
+                    if !self.options.allow_stray_tags {
+                        self.report(DiagnosticKind::UnexpectedTag, entry_expected);
+                    }
+                    self.i += 1;
+                }
+                Some(JsonTag::Comma) => {
+                    if count == 0 || comma.is_some() {
+                        if !self.options.allow_extra_commas {
+                            self.report(DiagnosticKind::ExtraComma, entry_expected);
+                        }
+                    } else {
+                        comma = Some(self.i);
+                    }
+                    self.i += 1;
+                }
+                Some(JsonTag::Colon) if self.options.allow_stray_tags => self.i += 1,
+                Some(JsonTag::Colon) => {
+                    self.report(DiagnosticKind::UnexpectedTag, entry_expected);
+                    self.i += 1;
+                    self.resync();
+
+                    // the text skipped takes the place of an entry
+                    if count == 0 || comma.is_some() {
+                        count += 1;
+                    }
+                    comma = None;
+                }
+                Some(json_tag) => {
+                    if count > 0 && comma.is_none() && !self.options.allow_missing_commas {
+                        self.report(DiagnosticKind::MissingComma, entry_expected);
+                    }
+
+                    if !is_object {
+                        self.path.push(format!("[{}]", elements.len()));
+                        elements.push(self.value()?);
+                        self.path.pop();
+                    } else if let JsonTag::Literal(literal, kind) = json_tag {
+                        props.push(self.prop(literal, *kind)?);
+                    } else {
+                        self.report(DiagnosticKind::UnexpectedTag, "property name");
+                        self.resync();
+                    }
+
+                    count += 1;
+                    comma = None;
+                }
+            }
+        }
+
+        self.open.pop();
+        if is_object {
+            self.options.limits.check_object_props(props.len())?;
+            Ok(JsonNode::Object(props))
+        } else {
+            Ok(JsonNode::Array(elements))
+        }
+    }
+
+    /// Parse the object property whose name literal of specified kind is at the current JSON tag.
+    fn prop(&mut self, literal: &str, kind: LiteralKind) -> Result<JsonObjProp> {
+        let name = match JsonNode::parse_prop_name(literal, kind, self.options) {
+            Ok(name) => name,
+            Err(_) => {
+                self.report(DiagnosticKind::InvalidLiteral, expected_prop_name(kind));
+                String::from(literal)
+            }
+        };
+        self.i += 1;
+
+        self.path.push(format!("['{}']", name));
+        let value = match self.peek() {
+            Some(JsonTag::Colon) => {
+                self.i += 1;
+                self.value()?
+            }
+            Some(JsonTag::Literal(_, _) | JsonTag::LeftCurly | JsonTag::LeftSquare) => {
+                if !self.options.allow_missing_colons {
+                    self.report(DiagnosticKind::MissingColon, "':'");
+                }
+                self.value()?
+            }
+            _ => {
+                self.report(DiagnosticKind::MissingValue, "':'");
+                self.placeholder()
+            }
+        };
+        self.path.pop();
+
+        Ok(JsonObjProp::new(name, value))
+    }
+}
+
+#[cfg(test)]
+mod json_recover_tests {
+    use super::*;
+
+    /// Parse a JSON string strictly with recovery, and describe the diagnostics.
+    fn recover(json: &str) -> Result<(RecoveredNode, Vec<String>)> {
+        let recovered =
+            JsonNode::parse_recovering_with_options(json.as_bytes(), &ParseOptions::strict())?;
+        let diagnostics = recovered
+            .diagnostics
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        Ok((recovered, diagnostics))
+    }
+
+    /// Test every problem is collected, and a best-effort node is kept with placeholders.
+    #[test]
+    fn test_recover() -> Result<()> {
+        let json = "{\n  \"name\": 'x',\n  \"port\": 80 \"debug\": tru,\n  \"tags\": [\"a\",, \"b\": 1, \"c\"],\n  \"limits\": {\"max\": }\n}";
+        let (recovered, diagnostics) = recover(json)?;
+        assert_eq!(
+            diagnostics,
+            [
+                "InvalidLiteral at 2:11: expected value, found 'x'",
+                "MissingComma at 3:14: expected ',' or '}', found \"debug\"",
+                "InvalidLiteral at 3:23: expected value, found tru",
+                "ExtraComma at 4:16: expected value, found ','",
+                "UnexpectedTag at 4:21: expected ',' or ']', found ':'",
+                "MissingValue at 5:21: expected value, found '}'",
+            ]
+        );
+        assert_eq!(
+            recovered.node.map(|n| n.to_string()),
+            Some(String::from(
+                r#"{"name": null, "port": 80, "debug": null, "tags": ["a", "b", "c"], "limits": {"max": null}}"#
+            ))
+        );
+        assert_eq!(
+            recovered.placeholders,
+            ["$['name']", "$['debug']", "$['limits']['max']"]
+        );
+        Ok(())
+    }
+
+    /// Test brackets are recovered, and valid input is parsed as usual.
+    #[test]
+    fn test_recover_brackets() -> Result<()> {
+        let (recovered, diagnostics) = recover("[{\"a\": [1, 2}, {[3]: 4, \"b\" 5}] ]")?;
+        assert_eq!(
+            diagnostics,
+            [
+                "MismatchedBracket at 1:13: expected ']', found '}'",
+                "UnexpectedTag at 1:17: expected property name, found '['",
+                "MissingColon at 1:29: expected ':', found 5",
+                "UnexpectedTag at 1:33: expected end of input, found ']'",
+            ]
+        );
+        assert_eq!(
+            recovered.node.map(|n| n.to_string()),
+            Some(String::from(r#"[{"a": [1, 2]}, {"b": 5}]"#))
+        );
+
+        let (recovered, diagnostics) = recover("{\"a\": [1, {\"b\": 2")?;
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(
+            recovered.node.map(|n| n.to_string()),
+            Some(String::from(r#"{"a": [1, {"b": 2}]}"#))
+        );
+
+        let json = r#"{"a": [1, 2.5], "b": {"c": null}}"#;
+        let recovered = JsonNode::parse_recovering(json.as_bytes())?;
+        assert!(recovered.is_valid());
+        assert_eq!(
+            recovered.node,
+            Some(JsonNode::parse_single_node(json.as_bytes())?)
+        );
+        assert!(recover("")?.0.node.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,760 @@
+//! Repair of malformed "almost JSON", such as LLM or legacy-system output, into valid JSON with a report of fixes.
+
+use anyhow::{bail, Result};
+use std::{fmt, io::Read};
+
+use crate::json_node::*;
+use crate::json_number::*;
+use crate::json_string::*;
+use crate::json_tag::*;
+use crate::limits::*;
+use crate::parse_options::*;
+
+/// Kind of a fix applied when repairing malformed JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixKind {
+    /// Missing comma between entries inserted.
+    InsertedComma,
+    /// Leading, trailing or duplicated comma removed.
+    RemovedComma,
+    /// Missing colon between property name and value inserted.
+    InsertedColon,
+    /// Stray colon removed.
+    RemovedColon,
+    /// Missing property name inserted as empty string.
+    InsertedKey,
+    /// Missing property value inserted as null.
+    InsertedNull,
+    /// Unquoted property name quoted.
+    QuotedKey,
+    /// Unquoted value quoted as string.
+    QuotedValue,
+    /// String quoted by single quotes requoted by double quotes.
+    RequotedString,
+    /// Missing closing quote of string added.
+    ClosedString,
+    /// Unescaped control chars or invalid escapes in string escaped.
+    EscapedString,
+    /// String split across lines joined.
+    JoinedString,
+    /// Non-JSON literal such as True, None or NaN converted to true, false or null.
+    ConvertedLiteral,
+    /// Non-JSON number such as +1, .5 or 0x1F converted to JSON number.
+    ConvertedNumber,
+    /// Missing closing bracket inserted.
+    ClosedBracket,
+    /// Stray closing bracket removed.
+    RemovedBracket,
+    /// Comment removed.
+    RemovedComment,
+    /// Text before or after the JSON value removed.
+    RemovedText,
+}
+
+/// A fix applied when repairing malformed JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonFix {
+    /// Kind of the fix.
+    pub kind: FixKind,
+
+    /// Char offset in the input where the fix is applied.<br>
+    /// It is exact if JSON tags are parsed losslessly, otherwise whitespace is not counted.
+    pub offset: usize,
+
+    /// Original text replaced or removed by the fix, empty for insertions.
+    pub original: String,
+}
+
+impl fmt::Display for JsonFix {
+    /// Describe the fix, such as: QuotedKey at 1: a
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} at {}", self.kind, self.offset)?;
+        if !self.original.is_empty() {
+            write!(f, ": {}", self.original)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Valid JSON tags repaired from malformed JSON, together with the fixes applied.<br>
+/// Only the first JSON value is kept, text around it(such as prose of LLM output) is removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairedJson {
+    /// Repaired JSON tags, which is valid JSON.
+    pub tags: Vec<JsonTag>,
+
+    /// Fixes applied, ordered by where they are applied.
+    pub fixes: Vec<JsonFix>,
+}
+
+/// What is expected next inside an array or object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Expect {
+    /// First entry or closing bracket.
+    First,
+    /// Property name after comma.
+    Key,
+    /// Colon after property name.
+    Colon,
+    /// Value after colon, or array element after comma.
+    Value,
+    /// Comma or closing bracket after an entry.
+    Next,
+}
+
+/// An array or object being repaired.
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    is_object: bool,
+    expect: Expect,
+    /// Offset of the comma just written, which is removed if the container closes.
+    comma_offset: Option<usize>,
+}
+
+/// State of repairing, with JSON tags and fixes written so far.
+struct Repairer {
+    frames: Vec<Frame>,
+    tags: Vec<JsonTag>,
+    fixes: Vec<JsonFix>,
+}
+
+/// Count the chars of a JSON tag as it is written in the input.
+pub(crate) fn raw_len(json_tag: &JsonTag) -> usize {
+    match json_tag {
+        JsonTag::Literal(s, _) | JsonTag::Whitespace(s) | JsonTag::Comment(s) => s.chars().count(),
+        _ => 1,
+    }
+}
+
+/// Quote and escape a string as a JSON string literal.
+fn quoted(s: &str) -> JsonTag {
+    let mut literal = String::new();
+    write_escaped(&mut literal, s, &EscapeOptions::default()).unwrap_or_default();
+    JsonTag::Literal(literal, LiteralKind::String('"'))
+}
+
+/// Make a JSON tag of a keyword literal of the repaired output, either true, false or null.
+fn keyword(literal: &str) -> JsonTag {
+    let kind = match literal {
+        "null" => LiteralKind::Null,
+        _ => LiteralKind::Boolean,
+    };
+    JsonTag::Literal(String::from(literal), kind)
+}
+
+impl RepairedJson {
+    /// Repair malformed JSON read from a instance that implements Read trait, with default limits.
+    pub fn repair<R>(reader: R) -> Result<RepairedJson>
+    where
+        R: Read,
+    {
+        RepairedJson::repair_with_limits(reader, &Limits::default())
+    }
+
+    /// Repair malformed JSON read from a instance that implements Read trait, with specified limits.
+    pub fn repair_with_limits<R>(reader: R, limits: &Limits) -> Result<RepairedJson>
+    where
+        R: Read,
+    {
+        let options = ParseOptions::with_limits(limits);
+        let json_tags = JsonTag::parse_lossless_with_options(reader, &options)?;
+        Ok(RepairedJson::repair_tags_with_options(&json_tags, &options))
+    }
+
+    /// Repair malformed JSON tags.<br>
+    /// Pass JSON tags parsed losslessly, so strings split across lines are joined exactly
+    /// and the offsets of fixes are exact.
+    pub fn repair_tags(json_tags: &[JsonTag]) -> RepairedJson {
+        RepairedJson::repair_tags_with_options(json_tags, &ParseOptions::default())
+    }
+
+    /// Repair malformed JSON tags, re-parsing split strings with specified options.
+    fn repair_tags_with_options(json_tags: &[JsonTag], options: &ParseOptions) -> RepairedJson {
+        let mut input = json_tags.to_vec();
+        let mut repairer = Repairer {
+            frames: Vec::new(),
+            tags: Vec::new(),
+            fixes: Vec::new(),
+        };
+
+        // the JSON value starts at the first bracket, or the first literal if there is no bracket
+        let start = input
+            .iter()
+            .position(|t| matches!(t, JsonTag::LeftCurly | JsonTag::LeftSquare))
+            .or_else(|| {
+                input
+                    .iter()
+                    .position(|t| matches!(t, JsonTag::Literal(_, _)))
+            })
+            .unwrap_or(input.len());
+        repairer.remove_text(&input[..start], 0);
+
+        let mut i = start;
+        let mut offset = input[..start].iter().map(raw_len).sum();
+        while i < input.len() {
+            if let JsonTag::Literal(_, _) = input[i] {
+                RepairedJson::join_lines(&mut input, i, offset, options, &mut repairer.fixes);
+            }
+
+            match &input[i] {
+                JsonTag::Whitespace(_) => (),
+                JsonTag::Comment(comment) => {
+                    repairer.fix(FixKind::RemovedComment, offset, comment);
+                }
+                JsonTag::Literal(literal, _) => repairer.literal(literal, offset),
+                JsonTag::LeftCurly => repairer.open(true, offset),
+                JsonTag::LeftSquare => repairer.open(false, offset),
+                JsonTag::RightCurly => repairer.close(true, offset),
+                JsonTag::RightSquare => repairer.close(false, offset),
+                JsonTag::Comma => repairer.comma(offset),
+                JsonTag::Colon => repairer.colon(offset),
+            }
+
+            offset += raw_len(&input[i]);
+            i += 1;
+            if repairer.frames.is_empty() && !repairer.tags.is_empty() {
+                break;
+            }
+        }
+
+        repairer.remove_text(&input[i..], offset);
+        while !repairer.frames.is_empty() {
+            repairer.fix(FixKind::ClosedBracket, offset, "");
+            repairer.finish(offset);
+        }
+
+        repairer.fixes.sort_by_key(|fix| fix.offset);
+        RepairedJson {
+            tags: repairer.tags,
+            fixes: repairer.fixes,
+        }
+    }
+
+    /// If the literal at specified index is an unclosed string, which is split by the tokenizer at line breaks,
+    /// join it with the following tags up to the closing quote, and re-parse the rest of that literal.
+    fn join_lines(
+        input: &mut Vec<JsonTag>,
+        i: usize,
+        offset: usize,
+        options: &ParseOptions,
+        fixes: &mut Vec<JsonFix>,
+    ) {
+        let first = match &input[i] {
+            JsonTag::Literal(s, _) => s.clone(),
+            _ => return,
+        };
+        let quote = match first.chars().next() {
+            Some(q @ ('"' | '\'')) if !is_closed(&first, q) => q,
+            _ => return,
+        };
+        if !matches!(
+            input.get(i + 1),
+            Some(JsonTag::Whitespace(_) | JsonTag::Literal(_, _))
+        ) {
+            return;
+        }
+
+        let mut joined = first;
+        let mut j = i + 1;
+        let (closing, rest) = loop {
+            match input.get(j) {
+                None | Some(JsonTag::Comment(_)) => return,
+                Some(JsonTag::Literal(s, _)) => {
+                    if matches!(input[j - 1], JsonTag::Literal(_, _)) {
+                        // whitespace not kept, so a line break is assumed
+                        joined.push('\n');
+                    }
+                    match find_quote(s, quote) {
+                        Some(0) => return,
+                        Some(q) => {
+                            let end = q + quote.len_utf8();
+                            break (&s[..end], &s[end..]);
+                        }
+                        None => joined.push_str(s),
+                    }
+                }
+                Some(_) => joined.push_str(&JsonTag::to_raw_string(&input[j..=j])),
+            }
+
+            j += 1;
+        };
+
+        joined.push_str(closing);
+        let is_split = joined.contains(['\n', '\r']);
+        let mut replacement = vec![JsonTag::Literal(joined, LiteralKind::String(quote))];
+        if !rest.is_empty() {
+            match JsonTag::parse_lossless_with_options(rest.as_bytes(), options) {
+                Ok(rest_tags) => replacement.extend(rest_tags),
+                Err(_) => {
+                    let kind = LiteralKind::classify(rest, options);
+                    replacement.push(JsonTag::Literal(String::from(rest), kind));
+                }
+            }
+        }
+
+        input.splice(i..=j, replacement);
+        if is_split {
+            fixes.push(JsonFix {
+                kind: FixKind::JoinedString,
+                offset,
+                original: String::new(),
+            });
+        }
+    }
+
+    /// Parse the repaired JSON tags to a JSON node.
+    pub fn to_node(&self) -> Result<JsonNode> {
+        let mut nodes = JsonNode::parse_tags_with_options(&self.tags, &ParseOptions::strict())?;
+        if 1 != nodes.len() {
+            bail!("expecting 1 node, but found {}", nodes.len());
+        }
+
+        Ok(nodes.remove(0))
+    }
+}
+
+impl fmt::Display for RepairedJson {
+    /// Write the repaired JSON.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&JsonTag::to_string(&self.tags))
+    }
+}
+
+impl Repairer {
+    /// Record a fix.
+    fn fix(&mut self, kind: FixKind, offset: usize, original: &str) {
+        self.fixes.push(JsonFix {
+            kind,
+            offset,
+            original: String::from(original),
+        });
+    }
+
+    /// Record the text of JSON tags outside of the JSON value as removed, if it is not just whitespace.
+    fn remove_text(&mut self, json_tags: &[JsonTag], offset: usize) {
+        let text = JsonTag::to_raw_string(json_tags);
+        let trimmed = text.trim_start();
+        if !trimmed.is_empty() {
+            let leading = text.chars().count() - trimmed.chars().count();
+            self.fix(FixKind::RemovedText, offset + leading, trimmed.trim_end());
+        }
+    }
+
+    /// Write whatever is missing before a value, such as comma, property name or colon.
+    fn before_value(&mut self, offset: usize) {
+        let frame = match self.frames.last() {
+            Some(frame) => *frame,
+            None => return,
+        };
+
+        if frame.expect == Expect::Next {
+            self.fix(FixKind::InsertedComma, offset, "");
+            self.tags.push(JsonTag::Comma);
+        }
+        match (frame.is_object, frame.expect) {
+            (true, Expect::First | Expect::Key | Expect::Next) => {
+                self.fix(FixKind::InsertedKey, offset, "");
+                self.tags.push(quoted(""));
+                self.tags.push(JsonTag::Colon);
+            }
+            (true, Expect::Colon) => {
+                self.fix(FixKind::InsertedColon, offset, "");
+                self.tags.push(JsonTag::Colon);
+            }
+            _ => (),
+        }
+    }
+
+    /// Mark the value just written as an entry of the enclosing container.
+    fn after_value(&mut self) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.expect = Expect::Next;
+            frame.comma_offset = None;
+        }
+    }
+
+    /// Write a literal, as property name or value.
+    fn literal(&mut self, literal: &str, offset: usize) {
+        let frame = self.frames.last().copied();
+        match frame {
+            Some(Frame {
+                is_object: true,
+                expect: expect @ (Expect::First | Expect::Key | Expect::Next),
+                ..
+            }) => {
+                if expect == Expect::Next {
+                    self.fix(FixKind::InsertedComma, offset, "");
+                    self.tags.push(JsonTag::Comma);
+                }
+
+                let key = self.key(literal, offset);
+                self.tags.push(key);
+                if let Some(frame) = self.frames.last_mut() {
+                    frame.expect = Expect::Colon;
+                    frame.comma_offset = None;
+                }
+            }
+            _ => {
+                self.before_value(offset);
+                let value = self.value(literal, offset);
+                self.tags.push(value);
+                self.after_value();
+            }
+        }
+    }
+
+    /// Repair a property name literal.
+    fn key(&mut self, literal: &str, offset: usize) -> JsonTag {
+        if literal.starts_with(['"', '\'']) {
+            return self.string(literal, offset);
+        }
+
+        self.fix(FixKind::QuotedKey, offset, literal);
+        quoted(literal)
+    }
+
+    /// Repair a value literal.
+    fn value(&mut self, literal: &str, offset: usize) -> JsonTag {
+        if let Ok(Some(_)) = JsonNumber::parse(literal, &ParseOptions::strict()) {
+            return JsonTag::Literal(String::from(literal), LiteralKind::Number);
+        }
+
+        let converted = match literal {
+            "true" | "false" | "null" => return keyword(literal),
+            "True" | "TRUE" => "true",
+            "False" | "FALSE" => "false",
+            "None" | "Null" | "NULL" | "nil" | "undefined" => "null",
+            _ if literal.starts_with(['"', '\'']) => return self.string(literal, offset),
+            _ => match JsonNumber::parse(literal, &ParseOptions::json5()) {
+                Ok(Some(number)) => {
+                    let converted = match (number.as_i64(), number.as_u64(), number.as_f64()) {
+                        (Some(i), _, _) => i.to_string(),
+                        (None, Some(u), _) => u.to_string(),
+                        (None, None, f) if f.is_finite() => JsonNumber::from(f).to_string(),
+                        _ => {
+                            self.fix(FixKind::ConvertedLiteral, offset, literal);
+                            return keyword("null");
+                        }
+                    };
+
+                    self.fix(FixKind::ConvertedNumber, offset, literal);
+                    return JsonTag::Literal(converted, LiteralKind::Number);
+                }
+                _ => {
+                    self.fix(FixKind::QuotedValue, offset, literal);
+                    return quoted(literal);
+                }
+            },
+        };
+
+        self.fix(FixKind::ConvertedLiteral, offset, literal);
+        keyword(converted)
+    }
+
+    /// Repair a quoted string literal, requoting, closing and escaping it as needed.
+    fn string(&mut self, literal: &str, offset: usize) -> JsonTag {
+        let quote = literal.chars().next().unwrap_or('"');
+        if quote == '"' && JsonNode::parse_quoted(literal, &ParseOptions::strict()).is_ok() {
+            return JsonTag::Literal(String::from(literal), LiteralKind::String(quote));
+        }
+
+        let closed = is_closed(literal, quote);
+        let inner = if closed {
+            &literal[quote.len_utf8()..literal.len() - quote.len_utf8()]
+        } else {
+            &literal[quote.len_utf8()..]
+        };
+        let syntax = if quote == '\'' {
+            EscapeSyntax::Json5
+        } else {
+            EscapeSyntax::Json
+        };
+        let value = unescape(inner, quote, true, syntax).unwrap_or(String::from(inner));
+
+        if quote == '\'' {
+            self.fix(FixKind::RequotedString, offset, literal);
+        }
+        if !closed {
+            self.fix(FixKind::ClosedString, offset, literal);
+        } else if quote == '"' {
+            self.fix(FixKind::EscapedString, offset, literal);
+        }
+
+        quoted(&value)
+    }
+
+    /// Open an array or object.
+    fn open(&mut self, is_object: bool, offset: usize) {
+        self.before_value(offset);
+        self.tags.push(if is_object {
+            JsonTag::LeftCurly
+        } else {
+            JsonTag::LeftSquare
+        });
+        self.frames.push(Frame {
+            is_object,
+            expect: Expect::First,
+            comma_offset: None,
+        });
+    }
+
+    /// Close an array or object, closing unclosed containers nested in it.<br>
+    /// If there is no such container open, the bracket is removed.
+    fn close(&mut self, is_object: bool, offset: usize) {
+        if !self.frames.iter().any(|f| f.is_object == is_object) {
+            self.fix(
+                FixKind::RemovedBracket,
+                offset,
+                if is_object { "}" } else { "]" },
+            );
+            return;
+        }
+
+        while self.frames.last().map(|f| f.is_object) != Some(is_object) {
+            self.fix(FixKind::ClosedBracket, offset, "");
+            self.finish(offset);
+        }
+        self.finish(offset);
+    }
+
+    /// Write whatever is missing at the end of the innermost container, and close it.
+    fn finish(&mut self, offset: usize) {
+        let frame = match self.frames.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        if let Some(comma_offset) = frame.comma_offset {
+            self.tags.pop();
+            self.fix(FixKind::RemovedComma, comma_offset, ",");
+        }
+        if frame.is_object {
+            self.finish_prop(frame.expect, offset);
+        }
+
+        self.tags.push(if frame.is_object {
+            JsonTag::RightCurly
+        } else {
+            JsonTag::RightSquare
+        });
+        self.after_value();
+    }
+
+    /// Write null as the value of a property without value.
+    fn finish_prop(&mut self, expect: Expect, offset: usize) {
+        if expect == Expect::Colon {
+            self.fix(FixKind::InsertedColon, offset, "");
+            self.tags.push(JsonTag::Colon);
+        }
+        if matches!(expect, Expect::Colon | Expect::Value) {
+            self.fix(FixKind::InsertedNull, offset, "");
+            self.tags.push(keyword("null"));
+        }
+    }
+
+    /// Write a comma, or remove it if it is unexpected.
+    fn comma(&mut self, offset: usize) {
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => {
+                self.fix(FixKind::RemovedComma, offset, ",");
+                return;
+            }
+        };
+
+        match (frame.is_object, frame.expect) {
+            (_, Expect::First | Expect::Key) | (false, Expect::Value) => {
+                self.fix(FixKind::RemovedComma, offset, ",");
+                return;
+            }
+            (true, expect @ (Expect::Colon | Expect::Value)) => self.finish_prop(expect, offset),
+            _ => (),
+        }
+
+        self.tags.push(JsonTag::Comma);
+        if let Some(frame) = self.frames.last_mut() {
+            frame.expect = if frame.is_object {
+                Expect::Key
+            } else {
+                Expect::Value
+            };
+            frame.comma_offset = Some(offset);
+        }
+    }
+
+    /// Write a colon, or remove it if it is unexpected.
+    fn colon(&mut self, offset: usize) {
+        match self.frames.last_mut() {
+            Some(frame) if frame.expect == Expect::Colon => {
+                frame.expect = Expect::Value;
+                self.tags.push(JsonTag::Colon);
+            }
+            _ => self.fix(FixKind::RemovedColon, offset, ":"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod json_repair_tests {
+    use super::*;
+
+    /// Repair a string, and return the repaired JSON and kinds of fixes.
+    fn repair(json: &str) -> Result<(String, Vec<FixKind>)> {
+        let repaired = RepairedJson::repair(json.as_bytes())?;
+        repaired.to_node()?;
+        let kinds = repaired.fixes.iter().map(|fix| fix.kind).collect();
+        Ok((repaired.to_string(), kinds))
+    }
+
+    /// Test repairing common damage of almost JSON.
+    #[test]
+    fn test_repair() -> Result<()> {
+        assert_eq!(
+            repair(r#"{"a": 1 "b": [1, 2,], c: True, 'd': None,}"#)?,
+            (
+                String::from(r#"{"a": 1, "b": [1, 2], "c": true, "d": null}"#),
+                vec![
+                    FixKind::InsertedComma,
+                    FixKind::RemovedComma,
+                    FixKind::QuotedKey,
+                    FixKind::ConvertedLiteral,
+                    FixKind::RequotedString,
+                    FixKind::ConvertedLiteral,
+                    FixKind::RemovedComma,
+                ]
+            )
+        );
+        assert_eq!(
+            repair(r#"{"a" 1, "b": hello, "c": [+1, .5, 0x1F"#)?,
+            (
+                String::from(r#"{"a": 1, "b": "hello", "c": [1, 0.5, 31]}"#),
+                vec![
+                    FixKind::InsertedColon,
+                    FixKind::QuotedValue,
+                    FixKind::ConvertedNumber,
+                    FixKind::ConvertedNumber,
+                    FixKind::ConvertedNumber,
+                    FixKind::ClosedBracket,
+                    FixKind::ClosedBracket,
+                ]
+            )
+        );
+        assert_eq!(
+            repair("[1,, 2]]")?,
+            (
+                String::from("[1, 2]"),
+                vec![FixKind::RemovedComma, FixKind::RemovedText]
+            )
+        );
+        assert_eq!(
+            repair(r#"{"a": [1, 2}"#)?,
+            (
+                String::from(r#"{"a": [1, 2]}"#),
+                vec![FixKind::ClosedBracket]
+            )
+        );
+        assert_eq!(
+            repair(r#"{"a":, "b"}"#)?,
+            (
+                String::from(r#"{"a": null, "b": null}"#),
+                vec![
+                    FixKind::InsertedNull,
+                    FixKind::InsertedColon,
+                    FixKind::InsertedNull
+                ]
+            )
+        );
+
+        Ok(())
+    }
+
+    /// Test repairing strings and removing text around the JSON value.
+    #[test]
+    fn test_repair_strings() -> Result<()> {
+        let json =
+            "Result: {\"text\": \"line 1\n  line, 2\", /* note */ \"ok\": \"a\tb\\q\"} Done.";
+        let repaired = RepairedJson::repair(json.as_bytes())?;
+        assert_eq!(
+            repaired.to_string(),
+            r#"{"text": "line 1\n  line, 2", "ok": "a\tb\\q"}"#
+        );
+        assert_eq!(
+            repaired.fixes,
+            vec![
+                JsonFix {
+                    kind: FixKind::RemovedText,
+                    offset: 0,
+                    original: String::from("Result:"),
+                },
+                JsonFix {
+                    kind: FixKind::JoinedString,
+                    offset: 17,
+                    original: String::new(),
+                },
+                JsonFix {
+                    kind: FixKind::EscapedString,
+                    offset: 17,
+                    original: String::from("\"line 1\n  line, 2\""),
+                },
+                JsonFix {
+                    kind: FixKind::RemovedComment,
+                    offset: 37,
+                    original: String::from("/* note */"),
+                },
+                JsonFix {
+                    kind: FixKind::EscapedString,
+                    offset: 54,
+                    original: String::from("\"a\tb\\q\""),
+                },
+                JsonFix {
+                    kind: FixKind::RemovedText,
+                    offset: 63,
+                    original: String::from("Done."),
+                },
+            ]
+        );
+        assert_eq!(repaired.fixes[0].to_string(), "RemovedText at 0: Result:");
+
+        assert_eq!(
+            repair(r#"['it\'s', "open"#)?,
+            (
+                String::from(r#"["it's", "open"]"#),
+                vec![
+                    FixKind::RequotedString,
+                    FixKind::ClosedString,
+                    FixKind::ClosedBracket
+                ]
+            )
+        );
+
+        Ok(())
+    }
+
+    /// Test malformed input is either rejected or repaired to JSON parsed back, with cases found by fuzzing.
+    #[test]
+    fn test_malformed_repair() -> Result<()> {
+        for json in [
+            "}{",
+            "{:}",
+            "{\"a\" 1 2}",
+            "{\"a\": [1}, 2]",
+            "[\"\\uD83D\", '\\u', \"\\\"]",
+            "[0x, -., 1e, .e1, tru, True, NaN, -NaN]",
+            "[\u{feff}1, \u{2028}, \"é😀\0\"]",
+            "/* [1",
+            "// x\r[",
+            "{\"a\": {\"b\": [,]]",
+        ] {
+            if let Ok(repaired) = RepairedJson::repair(json.as_bytes()) {
+                assert!(
+                    repaired.tags.is_empty() || repaired.to_node().is_ok(),
+                    "{}",
+                    json
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,576 @@
+//! Streaming reader and writer of JSON sequences: JSON Lines(NDJSON), concatenated JSON,
+//! and RFC 7464 JSON text sequences(each record starting with the RS char U+001E).
+
+use anyhow::{bail, Result};
+use std::{
+    error::Error,
+    fmt,
+    io::{BufRead, Read, Write},
+};
+
+use crate::json_node::*;
+use crate::json_writer::*;
+use crate::limits::*;
+use crate::parse_options::*;
+
+/// Record separator of RFC 7464 JSON text sequences.
+const RS: u8 = 0x1e;
+
+/// Format of a JSON sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqFormat {
+    /// JSON Lines(NDJSON), one JSON value per line, blank lines skipped.
+    JsonLines,
+    /// JSON values one after another, optionally separated by whitespace, such as {"a":1}{"a":2} 3.
+    Concatenated,
+    /// RFC 7464 JSON text sequence, each JSON value preceded by RS(U+001E) and followed by line feed.
+    JsonTextSequence,
+}
+
+/// A JSON value read from a JSON sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonRecord {
+    /// Line number where the record starts, starting from 1.
+    pub line: usize,
+
+    /// JSON node of the record.
+    pub node: JsonNode,
+}
+
+/// Context of error of a single record, carrying the line number where the record starts.<br>
+/// It can be recovered from an anyhow::Error by downcast_ref::&lt;RecordError&gt;(),
+/// while the underlying error, such as LimitError, can still be recovered the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordError {
+    pub line: usize,
+}
+
+impl fmt::Display for RecordError {
+    /// Implement Display trait for RecordError
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid json record at line {}", self.line)
+    }
+}
+
+impl Error for RecordError {}
+
+/// Streaming reader of a JSON sequence, yields one JSON record at a time.<br>
+/// An invalid record yields an error and reading goes on with the next record,
+/// while an I/O error ends the sequence, and so does a record exceeding the input limit unless it is a JSON line.
+pub struct JsonSeqReader<R: BufRead> {
+    reader: R,
+    format: SeqFormat,
+    options: ParseOptions,
+    /// Line number of the next byte to read.
+    line: usize,
+    /// Whether a RS has been read, text before the first RS is not a record.
+    seen_rs: bool,
+    is_done: bool,
+}
+
+impl<R: BufRead> JsonSeqReader<R> {
+    /// Create a JSON sequence reader, with strict options since each record must be valid JSON.
+    pub fn new(reader: R, format: SeqFormat) -> Self {
+        JsonSeqReader::with_options(reader, format, &ParseOptions::strict())
+    }
+
+    /// Create a JSON sequence reader, with specified options.
+    pub fn with_options(reader: R, format: SeqFormat, options: &ParseOptions) -> Self {
+        JsonSeqReader {
+            reader,
+            format,
+            options: options.clone(),
+            line: 1,
+            seen_rs: false,
+            is_done: false,
+        }
+    }
+
+    /// Peek the next byte.
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    /// Read the next byte, counting lines.
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        let b = self.peek_byte()?;
+        if let Some(b) = b {
+            self.reader.consume(1);
+            if b == b'\n' {
+                self.line += 1;
+            }
+        }
+
+        Ok(b)
+    }
+
+    /// Read the next byte into record bytes, checking record size against the input limit.
+    fn push_byte(&mut self, record: &mut Vec<u8>) -> Result<Option<u8>> {
+        let b = self.next_byte()?;
+        if let Some(b) = b {
+            record.push(b);
+            self.options.limits.check_input_len(record.len())?;
+        }
+
+        Ok(b)
+    }
+
+    /// Read bytes until specified delimiter into record bytes, checking record size against the input limit,
+    /// which fails as soon as it is exceeded rather than after the whole record is read.
+    fn read_until_limited(&mut self, delimiter: u8, record: &mut Vec<u8>) -> Result<usize> {
+        let len = match self.options.limits.max_input_len {
+            Some(max) => (&mut self.reader)
+                .take(max as u64 + 1)
+                .read_until(delimiter, record)?,
+            None => self.reader.read_until(delimiter, record)?,
+        };
+        self.options.limits.check_input_len(record.len())?;
+
+        Ok(len)
+    }
+
+    /// Skip bytes until the next line feed without keeping them, return whether a line feed is found.
+    fn skip_line(&mut self) -> Result<bool> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(false);
+            }
+
+            match buf.iter().position(|b| *b == b'\n') {
+                Some(i) => {
+                    self.reader.consume(i + 1);
+                    self.line += 1;
+                    return Ok(true);
+                }
+                None => {
+                    let len = buf.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    /// Read bytes of the next record of JSON Lines, along with its line number.<br>
+    /// A line exceeding the input limit is skipped and fails as a single record.
+    fn read_line_record(&mut self) -> Result<Option<(usize, Vec<u8>)>> {
+        loop {
+            let line = self.line;
+            let mut record = Vec::new();
+            match self.read_until_limited(b'\n', &mut record) {
+                Ok(0) => return Ok(None),
+                Ok(_) => (),
+                Err(e) if e.downcast_ref::<LimitError>().is_some() => {
+                    if record.ends_with(b"\n") {
+                        self.line += 1;
+                    } else {
+                        self.skip_line()?;
+                    }
+                    return Err(e.context(RecordError { line }));
+                }
+                Err(e) => return Err(e),
+            }
+            if record.ends_with(b"\n") {
+                self.line += 1;
+            }
+
+            if !record.iter().all(u8::is_ascii_whitespace) {
+                return Ok(Some((line, record)));
+            }
+        }
+    }
+
+    /// Read bytes of the next record of RFC 7464 JSON text sequence, along with its line number.
+    fn read_rs_record(&mut self) -> Result<Option<(usize, Vec<u8>)>> {
+        loop {
+            let mut record = Vec::new();
+            let start_line = self.line;
+            if 0 == self.read_until_limited(RS, &mut record)? {
+                return Ok(None);
+            }
+            let follows_rs = self.seen_rs;
+            if record.last() == Some(&RS) {
+                self.seen_rs = true;
+                record.pop();
+            }
+            self.line += record.iter().filter(|b| **b == b'\n').count();
+
+            let leading = record
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+            if leading == record.len() {
+                continue;
+            }
+            let line = start_line + record[..leading].iter().filter(|b| **b == b'\n').count();
+            if !follows_rs {
+                bail!(RecordError { line });
+            }
+
+            return Ok(Some((line, record)));
+        }
+    }
+
+    /// Skip whitespace and comments between records of concatenated JSON.<br>
+    /// If a slash not starting a comment is found, it is returned as the start of the next record.
+    fn skip_trivia(&mut self) -> Result<Option<u8>> {
+        loop {
+            match self.peek_byte()? {
+                Some(b) if b.is_ascii_whitespace() => {
+                    self.next_byte()?;
+                }
+                Some(b'/') if self.options.allow_comments => {
+                    self.next_byte()?;
+                    match self.peek_byte()? {
+                        Some(b'/') => while !matches!(self.next_byte()?, None | Some(b'\n')) {},
+                        Some(b'*') => {
+                            self.next_byte()?;
+                            let mut last = 0;
+                            loop {
+                                match self.next_byte()? {
+                                    None => bail!("unterminated comment"),
+                                    Some(b'/') if last == b'*' => break,
+                                    Some(b) => last = b,
+                                }
+                            }
+                        }
+                        _ => return Ok(Some(b'/')),
+                    }
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    /// Read bytes of the next record of concatenated JSON, along with its line number.<br>
+    /// Brackets are matched outside of strings and comments, other values end before whitespace or bracket.
+    fn read_concatenated_record(&mut self) -> Result<Option<(usize, Vec<u8>)>> {
+        let mut record = Vec::new();
+        if let Some(slash) = self.skip_trivia()? {
+            record.push(slash);
+        }
+        let line = self.line;
+        let first = match record.first() {
+            Some(b) => *b,
+            None => match self.push_byte(&mut record)? {
+                Some(b) => b,
+                None => return Ok(None),
+            },
+        };
+
+        let allow_single_quotes = self.options.allow_single_quotes;
+        let is_quote = |b: u8| b == b'"' || (b == b'\'' && allow_single_quotes);
+        let allow_comments = self.options.allow_comments;
+        let mut depth = 0usize;
+        let mut quote = None;
+        let mut is_escape = false;
+        match first {
+            b'{' | b'[' => depth = 1,
+            b if is_quote(b) => quote = Some(b),
+            b'}' | b']' | b',' | b':' => return Ok(Some((line, record))),
+            _ => (),
+        }
+
+        let is_scalar = depth == 0 && quote.is_none();
+        while depth > 0 || quote.is_some() || is_scalar {
+            let b = match self.peek_byte()? {
+                Some(b) => b,
+                None => break,
+            };
+            if is_scalar && (b.is_ascii_whitespace() || b"{}[]\"".contains(&b)) {
+                break;
+            }
+
+            self.push_byte(&mut record)?;
+            match (quote, b) {
+                (Some(_), b'\\') if !is_escape => {
+                    is_escape = true;
+                    continue;
+                }
+                (Some(q), b) if b == q && !is_escape => quote = None,
+                (Some(_), _) => (),
+                (None, b'/') if allow_comments && depth > 0 => match self.peek_byte()? {
+                    Some(b'/') => {
+                        while !matches!(self.push_byte(&mut record)?, None | Some(b'\n')) {}
+                    }
+                    Some(b'*') => {
+                        self.push_byte(&mut record)?;
+                        let mut last = 0;
+                        while let Some(b) = self.push_byte(&mut record)? {
+                            if last == b'*' && b == b'/' {
+                                break;
+                            }
+                            last = b;
+                        }
+                    }
+                    _ => (),
+                },
+                (None, b'{' | b'[') if depth > 0 => depth += 1,
+                (None, b'}' | b']') if depth > 0 => depth -= 1,
+                (None, b) if is_quote(b) && depth > 0 => quote = Some(b),
+                _ => (),
+            }
+
+            is_escape = false;
+        }
+
+        Ok(Some((line, record)))
+    }
+
+    /// Read and parse the next record.
+    fn read_record(&mut self) -> Result<Option<JsonRecord>> {
+        let read = match self.format {
+            SeqFormat::JsonLines => self.read_line_record(),
+            SeqFormat::Concatenated => self.read_concatenated_record(),
+            SeqFormat::JsonTextSequence => self.read_rs_record(),
+        };
+        let (line, record) = match read {
+            Ok(Some(read)) => read,
+            Ok(None) => return Ok(None),
+            Err(e) if e.downcast_ref::<RecordError>().is_some() => return Err(e),
+            Err(e) => {
+                self.is_done = true;
+                return Err(e.context(RecordError { line: self.line }));
+            }
+        };
+
+        let node = JsonNode::parse_single_node_with_options(&record[..], &self.options)
+            .map_err(|e| e.context(RecordError { line }))?;
+        if self.format == SeqFormat::JsonTextSequence && !record.ends_with(b"\n") {
+            // RFC 7464: a number, true, false or null not followed by line feed may be truncated
+            if let JsonNode::PlainNumber(_) | JsonNode::PlainBoolean(_) | JsonNode::PlainNull = node
+            {
+                bail!(RecordError { line });
+            }
+        }
+
+        Ok(Some(JsonRecord { line, node }))
+    }
+}
+
+impl<R: BufRead> Iterator for JsonSeqReader<R> {
+    type Item = Result<JsonRecord>;
+
+    /// Read the next record, or an error of it.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        match self.read_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => {
+                self.is_done = true;
+                None
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Writer of a JSON sequence, writes one JSON node as a record at a time.
+pub struct JsonSeqWriter<W: Write> {
+    writer: W,
+    format: SeqFormat,
+    options: WriteOptions,
+}
+
+impl<W: Write> JsonSeqWriter<W> {
+    /// Create a JSON sequence writer, writing compact records.
+    pub fn new(writer: W, format: SeqFormat) -> Self {
+        JsonSeqWriter::with_options(writer, format, WriteOptions::compact())
+    }
+
+    /// Create a JSON sequence writer with specified write options.<br>
+    /// Records of JSON Lines are always written on a single line, and every record ends with a line feed.
+    pub fn with_options(writer: W, format: SeqFormat, options: WriteOptions) -> Self {
+        let options = WriteOptions {
+            pretty: options.pretty && format != SeqFormat::JsonLines,
+            line_ending: LineEnding::Lf,
+            trailing_newline: false,
+            ..options
+        };
+        JsonSeqWriter {
+            writer,
+            format,
+            options,
+        }
+    }
+
+    /// Write a JSON node as a record.
+    pub fn write_node(&mut self, json_node: &JsonNode) -> Result<()> {
+        if self.format == SeqFormat::JsonTextSequence {
+            self.writer.write_all(&[RS])?;
+        }
+
+        let record = json_node.to_string_with_options(&self.options);
+        self.writer.write_all(record.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Flush internal writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Get internal writer back.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod json_seq_tests {
+    use super::*;
+    use std::io::{self, BufReader};
+
+    /// Read all records of a JSON sequence, as line numbers and JSON strings, or line numbers of errors.
+    fn read_all(input: &str, format: SeqFormat) -> Vec<Result<(usize, String), usize>> {
+        JsonSeqReader::new(input.as_bytes(), format)
+            .map(|record| match record {
+                Ok(record) => Ok((record.line, record.node.to_string())),
+                Err(e) => Err(e.downcast_ref::<RecordError>().map_or(0, |e| e.line)),
+            })
+            .collect()
+    }
+
+    /// Test reading JSON Lines, with an invalid line not losing the others.
+    #[test]
+    fn test_json_lines() -> Result<()> {
+        let input = "{\"a\": 1}\n\n  not json\n[1, 2]\r\n\"last\"";
+        assert_eq!(
+            read_all(input, SeqFormat::JsonLines),
+            vec![
+                Ok((1, String::from(r#"{"a": 1}"#))),
+                Err(3),
+                Ok((4, String::from("[1, 2]"))),
+                Ok((5, String::from(r#""last""#))),
+            ]
+        );
+
+        Ok(())
+    }
+
+    /// Test reading concatenated JSON.
+    #[test]
+    fn test_concatenated() -> Result<()> {
+        let input = "{\"a\":1}{\"b\":[\"]\"]} \"x\\\"\" 3 true\n[1,\n2] } null";
+        assert_eq!(
+            read_all(input, SeqFormat::Concatenated),
+            vec![
+                Ok((1, String::from(r#"{"a": 1}"#))),
+                Ok((1, String::from(r#"{"b": ["]"]}"#))),
+                Ok((1, String::from(r#""x\"""#))),
+                Ok((1, String::from("3"))),
+                Ok((1, String::from("true"))),
+                Ok((2, String::from("[1, 2]"))),
+                Err(3),
+                Ok((3, String::from("null"))),
+            ]
+        );
+
+        Ok(())
+    }
+
+    /// Test reading RFC 7464 JSON text sequence, with a truncated number detected.
+    #[test]
+    fn test_json_text_sequence() -> Result<()> {
+        let input = "x\n\u{1e}{\"a\":\n1}\n\u{1e}\u{1e}1\n\u{1e}[2]\n\u{1e}3";
+        assert_eq!(
+            read_all(input, SeqFormat::JsonTextSequence),
+            vec![
+                Err(1),
+                Ok((2, String::from(r#"{"a": 1}"#))),
+                Ok((4, String::from("1"))),
+                Ok((5, String::from("[2]"))),
+                Err(6),
+            ]
+        );
+
+        Ok(())
+    }
+
+    /// Test the input limit fails a record as soon as it is exceeded, even if the record never ends,
+    /// while an oversized JSON line fails alone and the next line is read.
+    #[test]
+    fn test_record_input_limit() -> Result<()> {
+        let options = ParseOptions {
+            limits: Limits {
+                max_input_len: Some(1024),
+                ..Limits::default()
+            },
+            ..ParseOptions::strict()
+        };
+        let endless = BufReader::new(io::repeat(b'1'));
+        let mut reader =
+            JsonSeqReader::with_options(endless, SeqFormat::JsonTextSequence, &options);
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::InputTooLarge(1024))
+        );
+        assert!(reader.next().is_none());
+
+        let input = format!("[1]\n{}\n[2]\n", "1".repeat(100_000));
+        let input = BufReader::with_capacity(64, input.as_bytes());
+        let mut reader = JsonSeqReader::with_options(input, SeqFormat::JsonLines, &options);
+        assert_eq!(reader.next().unwrap()?.line, 1);
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::InputTooLarge(1024))
+        );
+        assert_eq!(
+            err.downcast_ref::<RecordError>(),
+            Some(&RecordError { line: 2 })
+        );
+        let record = reader.next().unwrap()?;
+        assert_eq!(
+            (record.line, record.node.to_string()),
+            (3, String::from("[2]"))
+        );
+        assert!(reader.next().is_none());
+
+        Ok(())
+    }
+
+    /// Test writing records and reading them back.
+    #[test]
+    fn test_write() -> Result<()> {
+        let nodes = vec![
+            JsonNode::parse_single_node(r#"{"a": [1, 2]}"#.as_bytes())?,
+            JsonNode::parse_single_node(r#""x\ny""#.as_bytes())?,
+        ];
+        let expected = [
+            (SeqFormat::JsonLines, "{\"a\":[1,2]}\n\"x\\ny\"\n"),
+            (SeqFormat::Concatenated, "{\"a\":[1,2]}\n\"x\\ny\"\n"),
+            (
+                SeqFormat::JsonTextSequence,
+                "\u{1e}{\"a\":[1,2]}\n\u{1e}\"x\\ny\"\n",
+            ),
+        ];
+        for (format, output) in expected {
+            let mut writer =
+                JsonSeqWriter::with_options(Vec::new(), format, WriteOptions::compact());
+            for node in &nodes {
+                writer.write_node(node)?;
+            }
+            let written = String::from_utf8(writer.into_inner())?;
+            assert_eq!(written, output);
+
+            let read: Vec<JsonNode> = JsonSeqReader::new(written.as_bytes(), format)
+                .map(|record| record.map(|r| r.node))
+                .collect::<Result<_>>()?;
+            assert_eq!(read, nodes);
+        }
+
+        let mut writer =
+            JsonSeqWriter::with_options(Vec::new(), SeqFormat::JsonLines, WriteOptions::pretty());
+        writer.write_node(&nodes[0])?;
+        assert_eq!(writer.into_inner(), b"{\"a\": [1, 2]}\n");
+
+        Ok(())
+    }
+}
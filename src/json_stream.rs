@@ -0,0 +1,705 @@
+//! Streaming readers over JSON tags read one at a time, so memory use stays proportional to one value:
+//! a reader of elements of a (possibly huge) JSON array, and a JSONPath matcher that materializes
+//! only matching values and skips the others.
+
+use anyhow::{bail, Result};
+use std::io::Read;
+
+use crate::json_node::*;
+use crate::json_path::*;
+use crate::json_tag::*;
+use crate::limits::*;
+use crate::parse_options::*;
+
+/// JSON tags read one at a time, with one tag of read ahead.<br>
+/// Limits on input length and tag count apply to each value read instead of the whole input.
+struct TagStream<R: Read> {
+    json_tag_reader: JsonTagReader<R>,
+    /// JSON tag read ahead but not consumed yet.
+    peeked: Option<JsonTag>,
+}
+
+impl<R: Read> TagStream<R> {
+    /// Create a JSON tag stream, with specified options.
+    fn new(reader: R, options: &ParseOptions) -> Self {
+        let tag_options = ParseOptions {
+            limits: Limits {
+                max_input_len: None,
+                max_tags: None,
+                ..options.limits.clone()
+            },
+            ..options.clone()
+        };
+
+        TagStream {
+            json_tag_reader: JsonTagReader::with_options(reader, &tag_options),
+            peeked: None,
+        }
+    }
+
+    /// Read the next JSON tag, either the one read ahead or a new one.
+    fn read_tag(&mut self) -> Result<Option<JsonTag>> {
+        match self.peeked.take() {
+            Some(json_tag) => Ok(Some(json_tag)),
+            None => self.json_tag_reader.next().transpose(),
+        }
+    }
+
+    /// Read the next JSON tag, bailing at end of input.
+    fn expect_tag(&mut self) -> Result<JsonTag> {
+        match self.read_tag()? {
+            Some(json_tag) => Ok(json_tag),
+            None => bail!("unexpected end of json"),
+        }
+    }
+
+    /// Skip a JSON value starting with specified tag, without validating it.
+    fn skip_value(&mut self, first_tag: JsonTag) -> Result<()> {
+        let mut depth = match first_tag {
+            JsonTag::Literal(_, _) => return Ok(()),
+            JsonTag::LeftCurly | JsonTag::LeftSquare => 1,
+            _ => bail!("unexpected tag: {}", JsonTag::to_string(&[first_tag])),
+        };
+        while depth > 0 {
+            match self.expect_tag()? {
+                JsonTag::LeftCurly | JsonTag::LeftSquare => depth += 1,
+                JsonTag::RightCurly | JsonTag::RightSquare => depth -= 1,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the tags of a JSON value starting with specified tag, checking limits on the value.
+    fn read_value_tags(&mut self, first_tag: JsonTag, limits: &Limits) -> Result<Vec<JsonTag>> {
+        let start = self.json_tag_reader.bytes_read();
+        let mut depth = 0;
+        let mut value_tags = Vec::new();
+        let mut json_tag = first_tag;
+        loop {
+            match json_tag {
+                JsonTag::LeftCurly | JsonTag::LeftSquare => depth += 1,
+                JsonTag::RightCurly | JsonTag::RightSquare => depth -= 1,
+                _ => (),
+            }
+            value_tags.push(json_tag);
+            limits.check_tags(value_tags.len())?;
+            if depth <= 0 {
+                break;
+            }
+
+            let bytes_read = self.json_tag_reader.bytes_read();
+            limits.check_input_len(bytes_read.saturating_sub(start))?;
+            json_tag = self.expect_tag()?;
+        }
+
+        Ok(value_tags)
+    }
+}
+
+/// Step to descend from a JSON node to one of its child nodes.
+#[derive(Debug)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// Streaming reader of array elements, yields one JSON node per element of a top-level array,
+/// or of a nested array located by a JSONPath such as $.data.items.<br>
+/// Limits on input length and tag count apply to each element instead of the whole input,
+/// other limits apply as usual. Reading stops at the first error, and input after the array is not read.
+pub struct JsonArrayReader<R: Read> {
+    tag_stream: TagStream<R>,
+    options: ParseOptions,
+    /// Steps to descend to the array, cleared once the array is entered.
+    steps: Vec<Step>,
+    is_entered: bool,
+    has_element: bool,
+    is_done: bool,
+}
+
+impl<R: Read> JsonArrayReader<R> {
+    /// Create a reader of elements of the top-level array, with default options.
+    pub fn new(reader: R) -> Self {
+        JsonArrayReader::with_options(reader, &ParseOptions::default())
+    }
+
+    /// Create a reader of elements of the top-level array, with specified options.
+    pub fn with_options(reader: R, options: &ParseOptions) -> Self {
+        JsonArrayReader {
+            tag_stream: TagStream::new(reader, options),
+            options: options.clone(),
+            steps: Vec::new(),
+            is_entered: false,
+            has_element: false,
+            is_done: false,
+        }
+    }
+
+    /// Create a reader of elements of the array located by a JSONPath, with default options.<br>
+    /// The JSONPath may only contain names and single index selectors, such as $.data\[1\].items.
+    pub fn at_path(reader: R, json_path: &str) -> Result<Self> {
+        JsonArrayReader::at_path_with_options(reader, json_path, &ParseOptions::default())
+    }
+
+    /// Create a reader of elements of the array located by a JSONPath, with specified options.
+    pub fn at_path_with_options(
+        reader: R,
+        json_path: &str,
+        options: &ParseOptions,
+    ) -> Result<Self> {
+        let json_path = JsonPath::parse_with_limits(json_path, &options.limits)?;
+        let mut steps = Vec::new();
+        for part in json_path.parts {
+            if part.path_name != "$" {
+                steps.push(Step::Key(part.path_name));
+            }
+            match part.elem_selector {
+                None => (),
+                Some(ArrayElementSelector::Single(index)) => steps.push(Step::Index(index)),
+                Some(selector) => bail!("unsupported array element selector: {:?}", selector),
+            }
+        }
+
+        let mut json_array_reader = JsonArrayReader::with_options(reader, options);
+        json_array_reader.steps = steps;
+        Ok(json_array_reader)
+    }
+
+    /// Descend to the value of specified property of the object, leaving its first tag read ahead.
+    fn descend_to_key(&mut self, name: &str) -> Result<()> {
+        if self.tag_stream.expect_tag()? != JsonTag::LeftCurly {
+            bail!("json path not found, expecting object for: {}", name);
+        }
+        loop {
+            let prop_name = match self.tag_stream.expect_tag()? {
+                JsonTag::Comma => continue,
+                JsonTag::RightCurly => bail!("json path not found: {}", name),
+                JsonTag::Literal(literal, kind) => {
+                    JsonNode::parse_prop_name(&literal, kind, &self.options)?
+                }
+                json_tag => bail!("unexpected tag: {}", JsonTag::to_string(&[json_tag])),
+            };
+            let json_tag = match self.tag_stream.expect_tag()? {
+                JsonTag::Colon => self.tag_stream.expect_tag()?,
+                json_tag if self.options.allow_missing_colons => json_tag,
+                json_tag => bail!("expecting colon: {}", JsonTag::to_string(&[json_tag])),
+            };
+            if prop_name == name {
+                self.tag_stream.peeked = Some(json_tag);
+                return Ok(());
+            }
+
+            self.tag_stream.skip_value(json_tag)?;
+        }
+    }
+
+    /// Descend to the element at specified index of the array, leaving its first tag read ahead.
+    fn descend_to_index(&mut self, index: usize) -> Result<()> {
+        if self.tag_stream.expect_tag()? != JsonTag::LeftSquare {
+            bail!("json path not found, expecting array for: [{}]", index);
+        }
+        let mut i = 0;
+        loop {
+            let json_tag = match self.tag_stream.expect_tag()? {
+                JsonTag::Comma => continue,
+                JsonTag::RightSquare => bail!("json path not found: [{}]", index),
+                json_tag => json_tag,
+            };
+            if i == index {
+                self.tag_stream.peeked = Some(json_tag);
+                return Ok(());
+            }
+
+            self.tag_stream.skip_value(json_tag)?;
+            i += 1;
+        }
+    }
+
+    /// Descend along the JSONPath steps and read the opening square bracket of the array.
+    fn enter(&mut self) -> Result<()> {
+        let steps = std::mem::take(&mut self.steps);
+        for step in &steps {
+            match step {
+                Step::Key(name) => self.descend_to_key(name)?,
+                Step::Index(index) => self.descend_to_index(*index)?,
+            }
+        }
+        match self.tag_stream.read_tag()? {
+            Some(JsonTag::LeftSquare) => Ok(()),
+            Some(json_tag) => bail!("expecting array: {}", JsonTag::to_string(&[json_tag])),
+            None => bail!("expecting array, but found nothing"),
+        }
+    }
+
+    /// Read the tags of the next element, skipping commas in between, or None at the end of array.
+    fn read_element_tags(&mut self) -> Result<Option<Vec<JsonTag>>> {
+        let mut after_comma = false;
+        let json_tag = loop {
+            match self.tag_stream.expect_tag()? {
+                JsonTag::Comma => {
+                    if (!self.has_element || after_comma) && !self.options.allow_extra_commas {
+                        bail!("unexpected comma in json array");
+                    }
+                    after_comma = true;
+                }
+                JsonTag::RightSquare => {
+                    if after_comma
+                        && !self.options.allow_extra_commas
+                        && !self.options.allow_trailing_commas
+                    {
+                        bail!("unexpected trailing comma in json array");
+                    }
+                    return Ok(None);
+                }
+                json_tag => break json_tag,
+            }
+        };
+        if self.has_element && !after_comma && !self.options.allow_missing_commas {
+            bail!("expecting comma: {}", JsonTag::to_string(&[json_tag]));
+        }
+
+        let element_tags = self
+            .tag_stream
+            .read_value_tags(json_tag, &self.options.limits)?;
+        Ok(Some(element_tags))
+    }
+
+    /// Read the next element of the array, or None at the end of array.
+    fn read_next(&mut self) -> Result<Option<JsonNode>> {
+        if !self.is_entered {
+            self.enter()?;
+            self.is_entered = true;
+        }
+        loop {
+            let element_tags = match self.read_element_tags()? {
+                Some(element_tags) => element_tags,
+                None => return Ok(None),
+            };
+            let mut json_nodes = JsonNode::parse_tags_with_options(&element_tags, &self.options)?;
+            match json_nodes.pop() {
+                Some(json_node) => {
+                    self.has_element = true;
+                    return Ok(Some(json_node));
+                }
+                None if self.options.allow_stray_tags => continue,
+                None => bail!("unexpected tag: {}", JsonTag::to_string(&element_tags)),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for JsonArrayReader<R> {
+    type Item = Result<JsonNode>;
+
+    /// Read the next element of the array, or an error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        let json_node = self.read_next();
+        self.is_done = !matches!(json_node, Ok(Some(_)));
+        json_node.transpose()
+    }
+}
+
+/// Selector of child nodes, compiled from a JSONPath part.
+#[derive(Debug)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Elements(ArrayElementSelector),
+}
+
+impl Selector {
+    /// Whether the selector selects a child of the object or the array at all.
+    fn applies_to(&self, is_object: bool) -> bool {
+        match self {
+            Selector::Name(_) => is_object,
+            Selector::Wildcard => true,
+            Selector::Elements(_) => !is_object,
+        }
+    }
+
+    /// Whether the selector selects the child of specified name or index.
+    fn selects(&self, name: Option<&str>, index: usize) -> bool {
+        match (self, name) {
+            (Selector::Name(n), Some(name)) => n == name,
+            (Selector::Wildcard, _) => true,
+            (Selector::Elements(selector), None) => match selector {
+                ArrayElementSelector::All => true,
+                ArrayElementSelector::Single(i) => *i == index,
+                ArrayElementSelector::Multiple(il) => il.contains(&index),
+                ArrayElementSelector::Range(s, e) => {
+                    s.is_none_or(|s| index >= s as usize) && e.is_none_or(|e| index < e as usize)
+                }
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the selector selects no more children after the child of specified index,
+    /// which was selected or not.
+    fn is_exhausted_after(&self, index: usize, is_selected: bool) -> bool {
+        match self {
+            // only the first property of the name is selected
+            Selector::Name(_) => is_selected,
+            Selector::Wildcard => false,
+            Selector::Elements(selector) => match selector {
+                ArrayElementSelector::All => false,
+                ArrayElementSelector::Single(i) => index >= *i,
+                ArrayElementSelector::Multiple(il) => il.iter().all(|i| index >= *i),
+                ArrayElementSelector::Range(_, e) => e.is_some_and(|e| index + 1 >= e as usize),
+            },
+        }
+    }
+}
+
+/// Object or array being walked through by the JSONPath matcher.
+struct Frame {
+    is_object: bool,
+    /// Count of children read so far.
+    count: usize,
+    /// JSONPath fragment of the current child, such as ['name'] or \[0\].
+    path_frag: String,
+    /// Whether no more children can be selected, so the rest is not worth reading.
+    is_exhausted: bool,
+}
+
+/// A JSON value matched by the JSONPath matcher.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonMatch {
+    /// Normalized JSONPath of the value, such as $\['data'\]\[0\].
+    pub path: String,
+
+    /// JSON node of the value.
+    pub node: JsonNode,
+}
+
+/// Streaming JSONPath matcher, runs a JSONPath against JSON tags as they are read,
+/// and yields matching values in document order.<br>
+/// The JSONPath may contain names, wildcards(*), indexes, index lists, and non-negative slices,
+/// such as $.data\[*\].items\[0:2\]. Negative indexes and filters are not supported.<br>
+/// Matching values are materialized and validated against the options, non-matching ones are skipped
+/// without validation, and reading stops as soon as no more values can match, so the rest of input
+/// is not read. Limits on input length and tag count apply to each matching value.
+pub struct JsonPathMatcher<R: Read> {
+    tag_stream: TagStream<R>,
+    options: ParseOptions,
+    selectors: Vec<Selector>,
+    frames: Vec<Frame>,
+    is_started: bool,
+    is_done: bool,
+}
+
+impl<R: Read> JsonPathMatcher<R> {
+    /// Create a JSONPath matcher, with default options.
+    pub fn new(reader: R, json_path: &str) -> Result<Self> {
+        JsonPathMatcher::with_options(reader, json_path, &ParseOptions::default())
+    }
+
+    /// Create a JSONPath matcher, with specified options.
+    pub fn with_options(reader: R, json_path: &str, options: &ParseOptions) -> Result<Self> {
+        let json_path = JsonPath::parse_with_limits(json_path, &options.limits)?;
+        let mut selectors = Vec::new();
+        for part in json_path.parts {
+            match part.path_name.as_str() {
+                "$" | "@" => (),
+                "*" => selectors.push(Selector::Wildcard),
+                _ => selectors.push(Selector::Name(part.path_name)),
+            }
+            match part.elem_selector {
+                None => (),
+                Some(ArrayElementSelector::Range(s, e))
+                    if s.is_some_and(|s| s < 0) || e.is_some_and(|e| e < 0) =>
+                {
+                    bail!("negative array index not supported by streaming json path matcher")
+                }
+                Some(selector) => selectors.push(Selector::Elements(selector)),
+            }
+        }
+
+        Ok(JsonPathMatcher {
+            tag_stream: TagStream::new(reader, options),
+            options: options.clone(),
+            selectors,
+            frames: Vec::new(),
+            is_started: false,
+            is_done: false,
+        })
+    }
+
+    /// Normalized JSONPath of the current value.
+    fn path(&self) -> String {
+        let mut path = String::from("$");
+        for frame in &self.frames {
+            path.push_str(&frame.path_frag);
+        }
+
+        path
+    }
+
+    /// Visit a JSON value starting with specified tag, whose path matches the JSONPath so far.<br>
+    /// It is materialized if the whole JSONPath matches, otherwise it is entered if it is an object or an array.
+    fn visit(&mut self, first_tag: JsonTag) -> Result<Option<JsonMatch>> {
+        let depth = self.frames.len();
+        if depth == self.selectors.len() {
+            let value_tags = self
+                .tag_stream
+                .read_value_tags(first_tag, &self.options.limits)?;
+            let mut json_nodes = JsonNode::parse_tags_with_options(&value_tags, &self.options)?;
+            if json_nodes.len() != 1 {
+                bail!("unexpected tag: {}", JsonTag::to_string(&value_tags));
+            }
+
+            let json_match = JsonMatch {
+                path: self.path(),
+                node: json_nodes.remove(0),
+            };
+            return Ok(Some(json_match));
+        }
+
+        let is_object = match first_tag {
+            JsonTag::LeftCurly => true,
+            JsonTag::LeftSquare => false,
+            JsonTag::Literal(_, _) => return Ok(None),
+            _ => bail!("unexpected tag: {}", JsonTag::to_string(&[first_tag])),
+        };
+        self.frames.push(Frame {
+            is_object,
+            count: 0,
+            path_frag: String::new(),
+            is_exhausted: !self.selectors[depth].applies_to(is_object),
+        });
+        Ok(None)
+    }
+
+    /// Read the next child of the current object or array, and visit it if selected.
+    fn read_child(&mut self) -> Result<Option<JsonMatch>> {
+        let depth = self.frames.len() - 1;
+        let is_object = self.frames[depth].is_object;
+        let (name, first_tag) = match self.tag_stream.expect_tag()? {
+            JsonTag::Comma => return Ok(None),
+            JsonTag::RightCurly if is_object => {
+                self.frames.pop();
+                return Ok(None);
+            }
+            JsonTag::RightSquare if !is_object => {
+                self.frames.pop();
+                return Ok(None);
+            }
+            JsonTag::Literal(literal, kind) if is_object => {
+                let name = JsonNode::parse_prop_name(&literal, kind, &self.options)?;
+                let first_tag = match self.tag_stream.expect_tag()? {
+                    JsonTag::Colon => self.tag_stream.expect_tag()?,
+                    json_tag if self.options.allow_missing_colons => json_tag,
+                    json_tag => bail!("expecting colon: {}", JsonTag::to_string(&[json_tag])),
+                };
+                (Some(name), first_tag)
+            }
+            JsonTag::Colon if !self.options.allow_stray_tags => bail!("unexpected tag: :"),
+            JsonTag::Colon => return Ok(None),
+            json_tag if !is_object => (None, json_tag),
+            json_tag => bail!("unexpected tag: {}", JsonTag::to_string(&[json_tag])),
+        };
+
+        let selector = &self.selectors[depth];
+        let frame = &mut self.frames[depth];
+        let index = frame.count;
+        let is_selected = !frame.is_exhausted && selector.selects(name.as_deref(), index);
+        frame.count += 1;
+        frame.is_exhausted = frame.is_exhausted || selector.is_exhausted_after(index, is_selected);
+        if !is_selected {
+            self.tag_stream.skip_value(first_tag)?;
+            return Ok(None);
+        }
+
+        frame.path_frag = match name {
+            Some(name) => format!("['{}']", name),
+            None => format!("[{}]", index),
+        };
+        self.visit(first_tag)
+    }
+
+    /// Read the next matching value, or None if no more values can match.
+    fn read_next(&mut self) -> Result<Option<JsonMatch>> {
+        if !self.is_started {
+            self.is_started = true;
+            match self.tag_stream.read_tag()? {
+                None => return Ok(None),
+                Some(first_tag) => {
+                    if let Some(json_match) = self.visit(first_tag)? {
+                        return Ok(Some(json_match));
+                    }
+                }
+            }
+        }
+        while !self.frames.iter().all(|f| f.is_exhausted) {
+            if let Some(json_match) = self.read_child()? {
+                return Ok(Some(json_match));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<R: Read> Iterator for JsonPathMatcher<R> {
+    type Item = Result<JsonMatch>;
+
+    /// Read the next matching value, or an error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        let json_match = self.read_next();
+        self.is_done = !matches!(json_match, Ok(Some(_)));
+        json_match.transpose()
+    }
+}
+
+#[cfg(test)]
+mod json_stream_tests {
+    use super::*;
+
+    /// Test reading elements of the top-level array.
+    #[test]
+    fn test_top_level_array() -> Result<()> {
+        let json = r#"[1, "two", {"three": [3]}, [4, 4], null]"#;
+        let elements = JsonArrayReader::new(json.as_bytes()).collect::<Result<Vec<_>>>()?;
+        let expected = JsonNode::parse_single_node(json.as_bytes())?;
+        assert_eq!(JsonNode::Array(elements), expected);
+
+        let elements = JsonArrayReader::new(" [ ] ".as_bytes()).collect::<Result<Vec<_>>>()?;
+        assert!(elements.is_empty());
+
+        let mut reader = JsonArrayReader::new(r#"[1, {"a": 2"#.as_bytes());
+        assert_eq!(
+            reader.next().transpose()?,
+            Some(JsonNode::parse_single_node("1".as_bytes())?)
+        );
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+
+        assert!(JsonArrayReader::new(r#"{"a": 1}"#.as_bytes())
+            .next()
+            .unwrap()
+            .is_err());
+        Ok(())
+    }
+
+    /// Test reading elements of the array located by a JSONPath.
+    #[test]
+    fn test_array_at_path() -> Result<()> {
+        let json = r#"{"meta": {"items": [0]}, "data": [{"items": [9]}, {"skip": [1, {}], "items": [1, 2, 3]}]}"#;
+        let elements = JsonArrayReader::at_path(json.as_bytes(), "$.data[1].items")?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements[2].to_string(), "3");
+
+        let elements =
+            JsonArrayReader::at_path(json.as_bytes(), "$.data")?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(elements.len(), 2);
+
+        let elements = JsonArrayReader::at_path("[[], [5, 6]]".as_bytes(), "$[1]")?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(elements.len(), 2);
+
+        assert!(
+            JsonArrayReader::at_path(json.as_bytes(), "$.data[2].items")?
+                .next()
+                .unwrap()
+                .is_err()
+        );
+        assert!(JsonArrayReader::at_path(json.as_bytes(), "$.none")?
+            .next()
+            .unwrap()
+            .is_err());
+        assert!(JsonArrayReader::at_path(json.as_bytes(), "$.meta")?
+            .next()
+            .unwrap()
+            .is_err());
+        assert!(JsonArrayReader::at_path(json.as_bytes(), "$.data[*]").is_err());
+        Ok(())
+    }
+
+    /// Test reading elements with strict options and limits.
+    #[test]
+    fn test_strict_and_limits() -> Result<()> {
+        let strict = ParseOptions::strict();
+        for json in ["[1,, 2]", "[1 2]", "[1, 2,]", "[, 1]", "[1, 2"] {
+            let result =
+                JsonArrayReader::with_options(json.as_bytes(), &strict).collect::<Result<Vec<_>>>();
+            assert!(result.is_err(), "{}", json);
+        }
+        let elements = JsonArrayReader::new("[1,, 2 3,]".as_bytes()).collect::<Result<Vec<_>>>()?;
+        assert_eq!(elements.len(), 3);
+
+        let options = ParseOptions::with_limits(&Limits {
+            max_input_len: Some(16),
+            max_tags: Some(5),
+            ..Limits::default()
+        });
+        let json = format!("[{}]", vec!["[1, 2]"; 100].join(", "));
+        let count = JsonArrayReader::with_options(json.as_bytes(), &options)
+            .collect::<Result<Vec<_>>>()?
+            .len();
+        assert_eq!(count, 100);
+
+        let mut reader = JsonArrayReader::with_options("[[1, 2, 3]]".as_bytes(), &options);
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::TooManyTags(5))
+        );
+        Ok(())
+    }
+
+    /// Test matching JSONPath over JSON tags.
+    #[test]
+    fn test_json_path_matcher() -> Result<()> {
+        let json = r#"{"meta": {"version": "1.2"}, "data": [{"id": 1, "tags": ["a", "b"]}, {"id": 2}, {"id": 3, "tags": ["c"]}]}"#;
+        let matched = |json_path: &str| -> Result<Vec<String>> {
+            JsonPathMatcher::new(json.as_bytes(), json_path)?
+                .map(|m| m.map(|m| format!("{}={}", m.path, m.node)))
+                .collect()
+        };
+
+        assert_eq!(
+            matched("$.meta.version")?,
+            vec![r#"$['meta']['version']="1.2""#]
+        );
+        assert_eq!(matched("$.data[1].id")?, vec!["$['data'][1]['id']=2"]);
+        assert_eq!(
+            matched("$.data[*].tags[0]")?,
+            vec![
+                r#"$['data'][0]['tags'][0]="a""#,
+                r#"$['data'][2]['tags'][0]="c""#
+            ]
+        );
+        assert_eq!(matched("$.data[0:2].id")?.len(), 2);
+        assert_eq!(matched("$.data[2,0].id")?.len(), 2);
+        assert_eq!(matched("$.*.version")?.len(), 1);
+        assert_eq!(matched("$")?.len(), 1);
+        assert!(matched("$.none")?.is_empty());
+        assert!(matched("$.meta[0]")?.is_empty());
+        assert!(JsonPathMatcher::new(json.as_bytes(), "$.data[-1:]").is_err());
+
+        // the rest of input is not read once no more values can match
+        let truncated = r#"{"meta": {"version": "1.2"}, "data": [{"id": 1"#;
+        let mut matcher = JsonPathMatcher::new(truncated.as_bytes(), "$.meta.version")?;
+        assert!(matcher.next().transpose()?.is_some());
+        assert!(matcher.next().is_none());
+        let mut matcher = JsonPathMatcher::new(truncated.as_bytes(), "$.data[*].id")?;
+        assert!(matcher.next().transpose()?.is_some());
+        assert!(matcher.next().unwrap().is_err());
+
+        let strict = ParseOptions::strict();
+        let mut matcher = JsonPathMatcher::with_options("[1, 'a']".as_bytes(), "$[1]", &strict)?;
+        assert!(matcher.next().unwrap().is_err());
+        Ok(())
+    }
+}
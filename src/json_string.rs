@@ -0,0 +1,428 @@
+//! JSON string escape decoding and encoding.
+
+use anyhow::{bail, Result};
+use std::fmt::{self, Write};
+
+/// Options of escaping strings on output.<br>
+/// Quotes, backslashes and control chars are always escaped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EscapeOptions {
+    /// Escape all non-ASCII chars as \uXXXX, using surrogate pairs beyond the basic multilingual plane.
+    pub ascii_only: bool,
+
+    /// Escape &lt;, &gt;, &amp;, ' and line/paragraph separators(U+2028, U+2029),
+    /// so the output could be embedded in HTML or JavaScript safely.
+    pub html_safe: bool,
+}
+
+impl EscapeOptions {
+    /// Escape options producing pure ASCII output.
+    pub fn ascii_only() -> Self {
+        EscapeOptions {
+            ascii_only: true,
+            html_safe: false,
+        }
+    }
+
+    /// Escape options producing output safe to embed in HTML.
+    pub fn html_safe() -> Self {
+        EscapeOptions {
+            ascii_only: false,
+            html_safe: true,
+        }
+    }
+}
+
+/// Escape syntax of quoted strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EscapeSyntax {
+    /// RFC 8259 escapes.
+    Json,
+
+    /// JSON5 escapes, such as \x41, \v, \0 and escaped line breaks, any other char escaped as itself.
+    Json5,
+
+    /// Python escapes, such as \x41, \U0001F600 and octal \101, unknown escapes keep their backslash.
+    Python,
+}
+
+/// Read specified count of hex digits of an escape, such as 4 digits of \uXXXX.
+fn read_hex(chars: &mut std::str::Chars, digits: usize) -> Option<u32> {
+    let hex: String = chars.by_ref().take(digits).collect();
+    if hex.len() != digits || !hex.chars().all(|h| h.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+/// Decode escapes of the inner part of a quoted string, which is quoted by specified quote char.<br>
+/// Surrogate pairs are combined, lone surrogates are replaced by U+FFFD.<br>
+/// If JSON5 syntax, JSON5 escapes are decoded as well, and escaped line breaks are removed.<br>
+/// If Python syntax, the escapes are decoded as Python does.<br>
+/// If lenient, invalid escapes are kept as they are, otherwise an error is returned.
+pub(crate) fn unescape(
+    inner: &str,
+    quote: char,
+    lenient: bool,
+    syntax: EscapeSyntax,
+) -> Result<String> {
+    if syntax == EscapeSyntax::Python {
+        return unescape_python(inner, lenient);
+    }
+
+    let json5 = syntax == EscapeSyntax::Json5;
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        let escaped = chars.clone().next();
+        match escaped {
+            Some('"') => result.push('"'),
+            Some('\\') => result.push('\\'),
+            Some('/') => result.push('/'),
+            Some('b') => result.push('\u{8}'),
+            Some('f') => result.push('\u{c}'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some(q) if q == quote => result.push(q),
+            Some('u') => {
+                let mut lookahead = chars.clone();
+                lookahead.next();
+                match read_hex(&mut lookahead, 4) {
+                    Some(high @ 0xD800..=0xDBFF) => {
+                        let mut pair = lookahead.clone();
+                        let low = match (pair.next(), pair.next()) {
+                            (Some('\\'), Some('u')) => read_hex(&mut pair, 4),
+                            _ => None,
+                        };
+                        match low {
+                            Some(low @ 0xDC00..=0xDFFF) => {
+                                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                                result.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                                lookahead = pair;
+                            }
+                            _ => result.push('\u{FFFD}'),
+                        }
+                    }
+                    Some(code) => result.push(char::from_u32(code).unwrap_or('\u{FFFD}')),
+                    None if lenient => {
+                        result.push(c);
+                        continue;
+                    }
+                    None => bail!("invalid unicode escape: {}", inner),
+                }
+
+                chars = lookahead;
+                continue;
+            }
+            Some('v') if json5 => result.push('\u{b}'),
+            Some('0') if json5 && !matches!(chars.clone().nth(1), Some('0'..='9')) => {
+                result.push('\0')
+            }
+            Some('x') if json5 => {
+                let mut lookahead = chars.clone();
+                lookahead.next();
+                let hex: String = lookahead.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(code) if hex.len() == 2 && hex.chars().all(|h| h.is_ascii_hexdigit()) => {
+                        result.push(char::from(code));
+                        chars = lookahead;
+                        continue;
+                    }
+                    _ if lenient => {
+                        result.push(c);
+                        continue;
+                    }
+                    _ => bail!("invalid hex escape: {}", inner),
+                }
+            }
+            Some('\r') if json5 => {
+                chars.next();
+                if chars.clone().next() == Some('\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            Some('\n' | '\u{2028}' | '\u{2029}') if json5 => (),
+            Some(other) if json5 && !other.is_ascii_digit() => result.push(other),
+            _ if lenient => {
+                result.push(c);
+                continue;
+            }
+            _ => bail!("invalid escape: {}", inner),
+        }
+
+        chars.next();
+    }
+
+    Ok(result)
+}
+
+/// Decode escapes of the inner part of a Python string, such as repr() output.<br>
+/// \xhh, \uXXXX, \UXXXXXXXX, octal escapes such as \101, \a, \v and escaped line breaks are decoded,
+/// lone surrogates are replaced by U+FFFD, and unknown escapes such as \d keep their backslash.<br>
+/// Named escapes(\N{...}) are not supported, they are kept as they are if lenient, otherwise an error is returned.
+fn unescape_python(inner: &str, lenient: bool) -> Result<String> {
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        let mut lookahead = chars.clone();
+        let code = match lookahead.next() {
+            Some('\r') => {
+                if lookahead.clone().next() == Some('\n') {
+                    lookahead.next();
+                }
+                None
+            }
+            Some('\n') => None,
+            Some(e @ ('\\' | '\'' | '"')) => Some(e as u32),
+            Some('a') => Some(0x7),
+            Some('b') => Some(0x8),
+            Some('f') => Some(0xc),
+            Some('n') => Some(0xa),
+            Some('r') => Some(0xd),
+            Some('t') => Some(0x9),
+            Some('v') => Some(0xb),
+            Some(first @ '0'..='7') => {
+                let mut code = first as u32 - '0' as u32;
+                for _ in 0..2 {
+                    match lookahead.clone().next() {
+                        Some(digit @ '0'..='7') => {
+                            code = code * 8 + (digit as u32 - '0' as u32);
+                            lookahead.next();
+                        }
+                        _ => break,
+                    }
+                }
+                Some(code)
+            }
+            Some(x @ ('x' | 'u' | 'U')) => {
+                let digits = match x {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                match read_hex(&mut lookahead, digits) {
+                    Some(code) if code <= 0x10FFFF => Some(code),
+                    _ if lenient => {
+                        result.push(c);
+                        continue;
+                    }
+                    _ => bail!("invalid hex escape: {}", inner),
+                }
+            }
+            Some('N') if lenient => {
+                result.push(c);
+                continue;
+            }
+            Some('N') => bail!("named unicode escape not supported: {}", inner),
+            _ => {
+                result.push(c);
+                continue;
+            }
+        };
+
+        if let Some(code) = code {
+            result.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+        }
+        chars = lookahead;
+    }
+
+    Ok(result)
+}
+
+/// Find the byte index of the first quote char not escaped.
+pub(crate) fn find_quote(s: &str, quote: char) -> Option<usize> {
+    let mut is_escape = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            c if c == quote && !is_escape => return Some(i),
+            '\\' => is_escape = !is_escape,
+            _ => is_escape = false,
+        }
+    }
+
+    None
+}
+
+/// Check whether a quoted literal is closed by the quote it starts with.
+pub(crate) fn is_closed(literal: &str, quote: char) -> bool {
+    let inner = &literal[quote.len_utf8()..];
+    match find_quote(inner, quote) {
+        Some(i) => i + quote.len_utf8() == inner.len(),
+        None => false,
+    }
+}
+
+/// Check whether a string is an ECMAScript identifier name, which could be an unquoted JSON5 property name.
+pub(crate) fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '$' || c == '_' => (),
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_alphanumeric() || matches!(c, '$' | '_' | '\u{200c}' | '\u{200d}'))
+}
+
+/// Write a string quoted by double quotes, with escapes as specified by escape options.
+pub(crate) fn write_escaped<W: Write>(w: &mut W, s: &str, options: &EscapeOptions) -> fmt::Result {
+    write_quoted(w, s, '"', options)
+}
+
+/// Write a string quoted by specified quote char, with escapes as specified by escape options.
+pub(crate) fn write_quoted<W: Write>(
+    w: &mut W,
+    s: &str,
+    quote: char,
+    options: &EscapeOptions,
+) -> fmt::Result {
+    w.write_char(quote)?;
+    for c in s.chars() {
+        match c {
+            c if c == quote => write!(w, "\\{}", c)?,
+            '\\' => w.write_str("\\\\")?,
+            '\u{8}' => w.write_str("\\b")?,
+            '\u{c}' => w.write_str("\\f")?,
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\t' => w.write_str("\\t")?,
+            c if c < '\u{20}' => write!(w, "\\u{:04x}", c as u32)?,
+            '<' | '>' | '&' | '\'' | '\u{2028}' | '\u{2029}' if options.html_safe => {
+                write!(w, "\\u{:04x}", c as u32)?
+            }
+            c if !c.is_ascii() && options.ascii_only => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    write!(w, "\\u{:04x}", unit)?;
+                }
+            }
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char(quote)
+}
+
+#[cfg(test)]
+mod json_string_tests {
+    use super::*;
+    use crate::JsonNode;
+
+    /// Escape a string and quote it by double quotes.
+    fn escape(s: &str, options: &EscapeOptions) -> String {
+        let mut result = String::new();
+        write_escaped(&mut result, s, options).unwrap();
+        result
+    }
+
+    /// Test decoding of all RFC 8259 escapes.
+    #[test]
+    fn test_unescape() -> Result<()> {
+        let inner = r#"\"\\\/\b\f\n\r\t\u0041\u00e9\uD83D\uDE00\uD800x"#;
+        assert_eq!(
+            unescape(inner, '"', false, EscapeSyntax::Json)?,
+            "\"\\/\u{8}\u{c}\n\r\tAé😀\u{FFFD}x"
+        );
+        assert!(unescape(r#"\a"#, '"', false, EscapeSyntax::Json).is_err());
+        assert!(unescape(r#"\u12"#, '"', false, EscapeSyntax::Json).is_err());
+        assert_eq!(
+            unescape(r#"\a\u12"#, '"', true, EscapeSyntax::Json)?,
+            r#"\a\u12"#
+        );
+        assert_eq!(
+            unescape(r#"it\'s"#, '\'', false, EscapeSyntax::Json)?,
+            "it's"
+        );
+        assert_eq!(
+            unescape("\\v\\0\\x41\\a\\\r\nb", '\'', false, EscapeSyntax::Json5)?,
+            "\u{b}\0Aab"
+        );
+        assert!(unescape(r#"\01"#, '\'', false, EscapeSyntax::Json5).is_err());
+
+        Ok(())
+    }
+
+    /// Test decoding of Python escapes, as in repr() output.
+    #[test]
+    fn test_unescape_python() -> Result<()> {
+        let inner = r#"\U0001F600\u00e9\x41\101\0\a\v\'\"\\"#;
+        assert_eq!(
+            unescape(inner, '\'', false, EscapeSyntax::Python)?,
+            "😀éAA\0\u{7}\u{b}'\"\\"
+        );
+        assert_eq!(
+            unescape(r#"\d\/\q"#, '\'', false, EscapeSyntax::Python)?,
+            r#"\d\/\q"#
+        );
+        assert_eq!(
+            unescape(r#"\ud83d"#, '\'', false, EscapeSyntax::Python)?,
+            "\u{FFFD}"
+        );
+        assert!(unescape(r#"\N{DASH}"#, '\'', false, EscapeSyntax::Python).is_err());
+        assert!(unescape(r#"\U0001F6"#, '\'', false, EscapeSyntax::Python).is_err());
+        assert!(unescape(r#"\U00110000"#, '\'', false, EscapeSyntax::Python).is_err());
+        assert_eq!(
+            unescape(r#"\N{DASH}\x4"#, '\'', true, EscapeSyntax::Python)?,
+            r#"\N{DASH}\x4"#
+        );
+
+        Ok(())
+    }
+
+    /// Test escaping with different escape options.
+    #[test]
+    fn test_escape() -> Result<()> {
+        let s = "a\"b\\c\nd\u{1}é😀</script>&'\u{2028}";
+        assert_eq!(
+            escape(s, &EscapeOptions::default()),
+            "\"a\\\"b\\\\c\\nd\\u0001é😀</script>&'\u{2028}\""
+        );
+        assert_eq!(
+            escape(s, &EscapeOptions::ascii_only()),
+            "\"a\\\"b\\\\c\\nd\\u0001\\u00e9\\ud83d\\ude00</script>&'\\u2028\""
+        );
+        assert_eq!(
+            escape(s, &EscapeOptions::html_safe()),
+            "\"a\\\"b\\\\c\\nd\\u0001é😀\\u003c/script\\u003e\\u0026\\u0027\\u2028\""
+        );
+
+        Ok(())
+    }
+
+    /// Test strings set by user input are escaped on output.
+    #[test]
+    fn test_node_string_round_trip() -> Result<()> {
+        let json = r#"{"say \"hi\"": "line1\nline2 \u00e9\ud83d\ude00"}"#;
+        let mut json_node = JsonNode::parse_single_node(json.as_bytes())?;
+        assert_eq!(
+            json_node.get_str(r#"$['say "hi"']"#)?,
+            Some(String::from("line1\nline2 é😀"))
+        );
+        assert_eq!(
+            json_node.to_string(),
+            r#"{"say \"hi\"": "line1\nline2 é😀"}"#
+        );
+
+        json_node.set_str(r#"$['say "hi"']"#, "quote\" and \\ and \t")?;
+        let output = json_node.to_string();
+        assert_eq!(output, r#"{"say \"hi\"": "quote\" and \\ and \t"}"#);
+        assert_eq!(JsonNode::parse_single_node(output.as_bytes())?, json_node);
+        assert_eq!(
+            json_node.escaped(&EscapeOptions::ascii_only()).to_string(),
+            r#"{"say \"hi\"": "quote\" and \\ and \t"}"#
+        );
+
+        Ok(())
+    }
+}
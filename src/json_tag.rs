@@ -1,12 +1,106 @@
-//! Low-level JSON fragments such as brackets({, }, \[, \]), colon(:), comma(,), and literal(bool, number, string, null).
+//! Low-level JSON fragments such as brackets({, }, \[, \]), colon(:), comma(,), and literal(bool, number, string, null),
+//! plus whitespace and comments(trivia) if parsed losslessly.
 
-use anyhow::Result;
-use std::io::Read;
+use anyhow::{bail, Result};
+use std::{collections::VecDeque, fmt, io::Read};
 
+use crate::highlight::*;
+use crate::json_number::*;
+use crate::json_writer::*;
+use crate::limits::*;
+use crate::parse_options::*;
 use crate::peekable_codepoints::*;
 
+/// Check whether a char is whitespace, either JSON whitespace or not, including byte order mark(U+FEFF).
+fn is_whitespace(c: char) -> bool {
+    c.is_whitespace() || c == '\u{feff}'
+}
+
+/// Kind of a literal, classified by the rules of parse options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralKind {
+    /// Quoted string, carrying its quote char, either double quote or single quote.
+    String(char),
+    Number,
+    /// Number in a form which is allowed, but whose value cannot be represented, such as a hex number out of range.
+    MalformedNumber,
+    Boolean,
+    Null,
+    /// Unquoted word which is not a number, boolean or null, such as an identifier.
+    BareWord,
+}
+
+impl LiteralKind {
+    /// Classify a literal by the rules of specified parse options.<br>
+    /// For example, 'a' is a string if single quotes are allowed, otherwise a bare word.
+    pub fn classify(literal: &str, options: &ParseOptions) -> LiteralKind {
+        match JsonNumber::parse(literal, options) {
+            Ok(Some(_)) => LiteralKind::Number,
+            Err(_) => LiteralKind::MalformedNumber,
+            Ok(None) => LiteralKind::classify_non_number(literal, options),
+        }
+    }
+
+    /// Whether a literal of this kind is allowed by specified parse options, which may be stricter than
+    /// the options it is classified with, for example a single-quoted string is not allowed in strict mode.
+    pub fn is_allowed(self, literal: &str, options: &ParseOptions) -> bool {
+        match self {
+            // number forms are checked when the number is parsed
+            LiteralKind::Number | LiteralKind::MalformedNumber => true,
+            LiteralKind::String(quote) => {
+                (quote == '"' || options.allow_single_quotes)
+                    && (string_prefix_len(literal) == 0 || options.allow_python_literals)
+            }
+            LiteralKind::Boolean | LiteralKind::Null => {
+                LiteralKind::classify_non_number(literal, options) == self
+            }
+            LiteralKind::BareWord => options.allow_bare_words,
+        }
+    }
+
+    /// Classify a literal which is not a number by the rules of specified parse options.
+    fn classify_non_number(literal: &str, options: &ParseOptions) -> LiteralKind {
+        match literal {
+            "true" | "false" => LiteralKind::Boolean,
+            "null" => LiteralKind::Null,
+            "True" | "TRUE" | "False" | "FALSE" if options.allow_case_insensitive_literals => {
+                LiteralKind::Boolean
+            }
+            "Null" | "NULL" if options.allow_case_insensitive_literals => LiteralKind::Null,
+            "True" | "False" if options.allow_python_literals => LiteralKind::Boolean,
+            "None" if options.allow_python_literals => LiteralKind::Null,
+            "undefined" if options.allow_undefined => LiteralKind::Null,
+            _ if literal.starts_with('"') => LiteralKind::String('"'),
+            _ if literal.starts_with('\'') && options.allow_single_quotes => {
+                LiteralKind::String('\'')
+            }
+            _ if options.allow_python_literals && string_prefix_len(literal) > 0 => {
+                LiteralKind::String(
+                    literal
+                        .chars()
+                        .nth(string_prefix_len(literal))
+                        .unwrap_or('"'),
+                )
+            }
+            _ => LiteralKind::BareWord,
+        }
+    }
+}
+
+/// Get the length of the prefix of a Python string literal, such as b in b'x' or rb in rb'x', 0 if none.
+pub(crate) fn string_prefix_len(literal: &str) -> usize {
+    let prefix_len = literal
+        .bytes()
+        .take_while(|b| matches!(b, b'b' | b'B' | b'u' | b'U' | b'r' | b'R'))
+        .count();
+    match literal.as_bytes().get(prefix_len) {
+        Some(b'"' | b'\'') if prefix_len <= 2 => prefix_len,
+        _ => 0,
+    }
+}
+
 /// Low-level JSON fragments
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum JsonTag {
     LeftCurly,
     RightCurly,
@@ -14,11 +108,16 @@ pub enum JsonTag {
     RightSquare,
     Colon,
     Comma,
-    Literal(String),
+    /// Literal along with its kind, classified by the rules of parse options when it is read.
+    Literal(String, LiteralKind),
+    /// Run of whitespace chars, only produced by lossless parsing.
+    Whitespace(String),
+    /// Line comment(// ...) or block comment(/* ... */), only produced by lossless parsing.
+    Comment(String),
 }
 
 impl JsonTag {
-    /// Convert JSON tags to string representation.
+    /// Convert JSON tags to string representation, re-spaced with whitespace and comments skipped.
     pub fn to_string(json_tags: &[JsonTag]) -> String {
         let mut result = String::new();
         for tag in json_tags {
@@ -29,88 +128,356 @@ impl JsonTag {
                 JsonTag::RightSquare => result.push(']'),
                 JsonTag::Comma => result.push_str(", "),
                 JsonTag::Colon => result.push_str(": "),
-                JsonTag::Literal(s) => result.push_str(s),
+                JsonTag::Literal(s, _) => result.push_str(s),
+                JsonTag::Whitespace(_) | JsonTag::Comment(_) => (),
             }
         }
 
         result
     }
 
+    /// Convert JSON tags to string representation as they are, without any spacing added.<br>
+    /// For JSON tags parsed losslessly, it reproduces the original input exactly.
+    pub fn to_raw_string(json_tags: &[JsonTag]) -> String {
+        let mut result = String::new();
+        for tag in json_tags {
+            match tag {
+                JsonTag::LeftCurly => result.push('{'),
+                JsonTag::RightCurly => result.push('}'),
+                JsonTag::LeftSquare => result.push('['),
+                JsonTag::RightSquare => result.push(']'),
+                JsonTag::Comma => result.push(','),
+                JsonTag::Colon => result.push(':'),
+                JsonTag::Literal(s, _) | JsonTag::Whitespace(s) | JsonTag::Comment(s) => {
+                    result.push_str(s)
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Whether the JSON tag is whitespace or comment.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, JsonTag::Whitespace(_) | JsonTag::Comment(_))
+    }
+
+    /// Kind of the literal, None if not a literal.
+    pub fn literal_kind(&self) -> Option<LiteralKind> {
+        match self {
+            JsonTag::Literal(_, kind) => Some(*kind),
+            _ => None,
+        }
+    }
+
+    /// Convert JSON tags to string representation, spaced and highlighted as specified by write options.
+    pub fn to_string_with_options(json_tags: &[JsonTag], options: &WriteOptions) -> String {
+        let mut result = String::new();
+        // writing to a String never fails
+        let _ = JsonTag::fmt_tags(&mut result, json_tags, options);
+        if options.trailing_newline {
+            result.push_str(options.line_ending.as_str());
+        }
+
+        result
+    }
+
+    /// Get highlighting style of a literal by its kind and the tag following it, None if it is a bare word.
+    fn literal_style(kind: LiteralKind, next: Option<&JsonTag>) -> Option<Style> {
+        match kind {
+            LiteralKind::String(_) if next == Some(&JsonTag::Colon) => Some(Style::Key),
+            LiteralKind::String(_) => Some(Style::String),
+            LiteralKind::Number | LiteralKind::MalformedNumber => Some(Style::Number),
+            LiteralKind::Boolean => Some(Style::Boolean),
+            LiteralKind::Null => Some(Style::Null),
+            LiteralKind::BareWord => None,
+        }
+    }
+
+    /// Compose a JSON string representation of JSON tags, spaced as specified by write options.<br>
+    /// Whitespace and comments are skipped.
+    pub(crate) fn fmt_tags<F: fmt::Write>(
+        f: &mut F,
+        json_tags: &[JsonTag],
+        options: &WriteOptions,
+    ) -> fmt::Result {
+        let json_tags: Vec<&JsonTag> = json_tags.iter().filter(|t| !t.is_trivia()).collect();
+        let mut level: usize = 0;
+        for (i, tag) in json_tags.iter().copied().enumerate() {
+            let next = json_tags.get(i + 1).copied();
+            match tag {
+                JsonTag::LeftCurly | JsonTag::LeftSquare => {
+                    options.write_bracket(f, if *tag == JsonTag::LeftCurly { '{' } else { '[' })?;
+                    level += 1;
+                    if !matches!(next, Some(JsonTag::RightCurly | JsonTag::RightSquare)) {
+                        options.write_line_break(f, level)?;
+                    }
+                }
+                JsonTag::RightCurly | JsonTag::RightSquare => {
+                    level = level.saturating_sub(1);
+                    options.write_bracket(
+                        f,
+                        if *tag == JsonTag::RightCurly {
+                            '}'
+                        } else {
+                            ']'
+                        },
+                    )?;
+                }
+                JsonTag::Comma => {
+                    options.write_comma(f)?;
+                    options.write_line_break(f, level)?;
+                }
+                JsonTag::Colon => options.write_colon(f)?,
+                JsonTag::Literal(s, kind) => match JsonTag::literal_style(*kind, next) {
+                    Some(style) => options.write_styled(f, style, |f| f.write_str(s))?,
+                    None => f.write_str(s)?,
+                },
+                JsonTag::Whitespace(_) | JsonTag::Comment(_) => (),
+            }
+
+            let is_open = matches!(tag, JsonTag::LeftCurly | JsonTag::LeftSquare);
+            if !is_open && matches!(next, Some(JsonTag::RightCurly | JsonTag::RightSquare)) {
+                options.write_line_break(f, level.saturating_sub(1))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read a literal from codepoint reader.<br>
+    /// If stop_at_comment, the literal also ends before a comment.
+    fn read_literal<R>(
+        peekable_cp: &mut PeekableCodePoints<R>,
+        options: &ParseOptions,
+        stop_at_comment: bool,
+    ) -> Result<JsonTag>
+    where
+        R: Read,
+    {
+        let limits = &options.limits;
+        let mut end = 0;
+        let mut quote = None;
+        let mut is_escape = false;
+        let mut is_continuation = false;
+        let mut quote_as_literal = false;
+        loop {
+            limits.check_literal_len(end)?;
+            limits.check_input_len(peekable_cp.bytes_read())?;
+
+            let after_escaped_cr = is_continuation;
+            is_continuation = false;
+            match peekable_cp.peek_char(end)? {
+                None => break,
+                Some(c) => match c {
+                    '\\' if !is_escape => {
+                        is_escape = true;
+
+                        end += 1;
+                        continue;
+                    }
+
+                    '\'' | '"'
+                        if !is_escape
+                            && !quote_as_literal
+                            && (c == '"' || options.allow_single_quotes) =>
+                    {
+                        match quote {
+                            None => quote = Some(c),
+                            Some(q) if q == c => quote = None,
+                            _ => (),
+                        }
+
+                        end += 1;
+                        continue;
+                    }
+
+                    '\r' | '\n'
+                        if quote.is_some()
+                            && (options.allow_json5_strings || options.allow_python_strings)
+                            && (is_escape || after_escaped_cr) =>
+                    {
+                        is_continuation = c == '\r' && is_escape;
+                        is_escape = false;
+
+                        end += 1;
+                        continue;
+                    }
+                    '\r' | '\n' if quote.is_some() => {
+                        if !options.allow_multiline_strings {
+                            bail!("unterminated string: {}", peekable_cp.peek(end)?);
+                        }
+
+                        quote_as_literal = true;
+
+                        quote = None;
+                        is_escape = false;
+
+                        end = 0;
+                        continue;
+                    }
+
+                    c if is_whitespace(c) && quote.is_none() => break,
+
+                    '/' if stop_at_comment && quote.is_none() => {
+                        if let Some('/' | '*') = peekable_cp.peek_char(end + 1)? {
+                            break;
+                        }
+                    }
+
+                    '{' | '}' | '[' | ']' | ',' | ':' if !is_escape && quote.is_none() => break,
+                    '(' | ')' if options.allow_tuples && !is_escape && quote.is_none() => break,
+
+                    _ => (),
+                },
+            }
+
+            is_escape = false;
+            end += 1;
+        }
+
+        let literal = peekable_cp.pop(end)?;
+        let kind = LiteralKind::classify(&literal, options);
+        Ok(JsonTag::Literal(literal, kind))
+    }
+
+    /// Read whitespace or comment from codepoint reader, whose first char is specified.<br>
+    /// If it is neither whitespace nor comment, return None.
+    fn read_trivia<R>(
+        peekable_cp: &mut PeekableCodePoints<R>,
+        options: &ParseOptions,
+        first: char,
+    ) -> Result<Option<JsonTag>>
+    where
+        R: Read,
+    {
+        let limits = &options.limits;
+        let mut end = 0;
+        if is_whitespace(first) {
+            while let Some(c) = peekable_cp.peek_char(end)? {
+                match c {
+                    ' ' | '\t' | '\n' | '\r' => (),
+                    c if is_whitespace(c) => {
+                        if !options.allow_non_json_whitespace {
+                            bail!("unexpected whitespace char: {:?}", c);
+                        }
+                    }
+                    _ => break,
+                }
+
+                end += 1;
+                limits.check_input_len(peekable_cp.bytes_read())?;
+            }
+
+            return Ok(Some(JsonTag::Whitespace(peekable_cp.pop(end)?)));
+        }
+
+        if first != '/' {
+            return Ok(None);
+        }
+        match peekable_cp.peek_char(1)? {
+            Some('/') => {
+                end = 2;
+                while let Some(c) = peekable_cp.peek_char(end)? {
+                    if c == '\n' || c == '\r' {
+                        break;
+                    }
+
+                    end += 1;
+                    limits.check_literal_len(end)?;
+                    limits.check_input_len(peekable_cp.bytes_read())?;
+                }
+            }
+            Some('*') => {
+                end = 2;
+                loop {
+                    match peekable_cp.peek_char(end)? {
+                        None => bail!("unterminated comment: {}", peekable_cp.peek(end)?),
+                        Some('*') if peekable_cp.peek_char(end + 1)? == Some('/') => {
+                            end += 2;
+                            break;
+                        }
+                        _ => end += 1,
+                    }
+
+                    limits.check_literal_len(end)?;
+                    limits.check_input_len(peekable_cp.bytes_read())?;
+                }
+            }
+            _ => return Ok(None),
+        }
+
+        Ok(Some(JsonTag::Comment(peekable_cp.pop(end)?)))
+    }
+
     /// Read one single JSON tag from codepoint reader.
-    /// If end-of-input found, return None.
-    fn read_json_tag<R>(peekable_cp: &mut PeekableCodePoints<R>) -> Result<Option<JsonTag>>
+    /// If end-of-input found, return None.<br>
+    /// If tuples allowed, whether each open square bracket is a tuple is tracked in specified stack,
+    /// so that a tuple closed by ']' or an array closed by ')' fails.
+    fn read_json_tag<R>(
+        peekable_cp: &mut PeekableCodePoints<R>,
+        options: &ParseOptions,
+        lossless: bool,
+        tuples: &mut Vec<bool>,
+    ) -> Result<Option<JsonTag>>
     where
         R: Read,
     {
+        let limits = &options.limits;
         let json_tag = loop {
             match peekable_cp.peek_char(0)? {
                 None => break None,
                 Some(c) => match c {
-                    c if c.is_whitespace() => {
+                    c if lossless && (is_whitespace(c) || c == '/') => {
+                        match JsonTag::read_trivia(peekable_cp, options, c)? {
+                            Some(trivia) => break Some(trivia),
+                            None => break Some(JsonTag::read_literal(peekable_cp, options, true)?),
+                        }
+                    }
+                    '/' if options.allow_comments => {
+                        match JsonTag::read_trivia(peekable_cp, options, c)? {
+                            Some(_) => continue,
+                            None => break Some(JsonTag::read_literal(peekable_cp, options, true)?),
+                        }
+                    }
+                    ' ' | '\t' | '\n' | '\r' => {
+                        peekable_cp.skip(1)?;
+                        limits.check_input_len(peekable_cp.bytes_read())?;
+                        continue;
+                    }
+                    c if is_whitespace(c) => {
+                        if !options.allow_non_json_whitespace {
+                            bail!("unexpected whitespace char: {:?}", c);
+                        }
+
                         peekable_cp.skip(1)?;
+                        limits.check_input_len(peekable_cp.bytes_read())?;
                         continue;
                     }
                     '{' => break Some(JsonTag::LeftCurly),
                     '}' => break Some(JsonTag::RightCurly),
+                    '[' | '(' if options.allow_tuples => {
+                        tuples.push(c == '(');
+                        break Some(JsonTag::LeftSquare);
+                    }
+                    ']' | ')' if options.allow_tuples => {
+                        let is_tuple = c == ')';
+                        if tuples.pop().is_some_and(|t| t != is_tuple) {
+                            bail!("mismatched closing bracket: {}", c);
+                        }
+                        break Some(JsonTag::RightSquare);
+                    }
                     '[' => break Some(JsonTag::LeftSquare),
                     ']' => break Some(JsonTag::RightSquare),
                     ',' => break Some(JsonTag::Comma),
                     ':' => break Some(JsonTag::Colon),
                     _ => {
-                        let mut end = 0;
-                        let mut quote = None;
-                        let mut is_escape = false;
-                        let mut quote_as_literal = false;
-                        loop {
-                            match peekable_cp.peek_char(end)? {
-                                None => break,
-                                Some(c) => match c {
-                                    '\\' => {
-                                        is_escape = true;
-
-                                        end += 1;
-                                        continue;
-                                    }
-
-                                    '\'' | '"' if !is_escape && !quote_as_literal => {
-                                        match quote {
-                                            None => quote = Some(c),
-                                            Some(q) if q == c => quote = None,
-                                            _ => (),
-                                        }
-
-                                        end += 1;
-                                        continue;
-                                    }
-
-                                    '\r' | '\n' if quote.is_some() => {
-                                        quote_as_literal = true;
-
-                                        quote = None;
-                                        is_escape = false;
-
-                                        end = 0;
-                                        continue;
-                                    }
-
-                                    c if c.is_whitespace() && quote.is_none() => break,
-
-                                    '{' | '}' | '[' | ']' | ',' | ':'
-                                        if !is_escape && quote.is_none() =>
-                                    {
-                                        break
-                                    }
-
-                                    _ => (),
-                                },
-                            }
-
-                            is_escape = false;
-                            end += 1;
-                        }
-
-                        let literal = peekable_cp.pop(end)?;
-                        break Some(JsonTag::Literal(literal));
+                        let stop_at_comment = lossless || options.allow_comments;
+                        break Some(JsonTag::read_literal(
+                            peekable_cp,
+                            options,
+                            stop_at_comment,
+                        )?);
                     }
                 },
             }
@@ -130,29 +497,229 @@ impl JsonTag {
         Ok(json_tag)
     }
 
-    /// Parse JSON tags from a instance that implements Read trait.
+    /// Parse JSON tags from a instance that implements Read trait, with default limits.
     pub fn parse<R>(reader: R) -> Result<Vec<JsonTag>>
     where
         R: Read,
     {
-        let mut json_tag_list = Vec::new();
-        let mut peekable_cp = PeekableCodePoints::new(reader);
+        JsonTag::parse_with_limits(reader, &Limits::default())
+    }
+
+    /// Parse JSON tags from a instance that implements Read trait, with specified limits.
+    pub fn parse_with_limits<R>(reader: R, limits: &Limits) -> Result<Vec<JsonTag>>
+    where
+        R: Read,
+    {
+        JsonTag::parse_with_options(reader, &ParseOptions::with_limits(limits))
+    }
+
+    /// Parse JSON tags from a instance that implements Read trait, with specified options.
+    pub fn parse_with_options<R>(reader: R, options: &ParseOptions) -> Result<Vec<JsonTag>>
+    where
+        R: Read,
+    {
+        JsonTag::parse_tags_from(reader, options, false)
+    }
+
+    /// Parse JSON tags losslessly from a instance that implements Read trait, with default limits.<br>
+    /// Whitespace and comments are kept as JSON tags, so JsonTag::to_raw_string() reproduces the input exactly.
+    pub fn parse_lossless<R>(reader: R) -> Result<Vec<JsonTag>>
+    where
+        R: Read,
+    {
+        JsonTag::parse_lossless_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Parse JSON tags losslessly from a instance that implements Read trait, with specified options.
+    pub fn parse_lossless_with_options<R>(reader: R, options: &ParseOptions) -> Result<Vec<JsonTag>>
+    where
+        R: Read,
+    {
+        JsonTag::parse_tags_from(reader, options, true)
+    }
+
+    /// Parse JSON tags from a instance that implements Read trait, keeping whitespace and comments if lossless.
+    fn parse_tags_from<R>(reader: R, options: &ParseOptions, lossless: bool) -> Result<Vec<JsonTag>>
+    where
+        R: Read,
+    {
+        let json_tag_reader = JsonTagReader {
+            lossless,
+            ..JsonTagReader::with_options(reader, options)
+        };
+        json_tag_reader.collect()
+    }
+}
+
+/// Streaming reader of JSON tags, reads one JSON tag at a time from a instance that implements Read trait,
+/// so memory use does not grow with input.<br>
+/// Limits apply to the whole input read so far, and reading stops at the first error.
+pub struct JsonTagReader<R: Read> {
+    peekable_cp: PeekableCodePoints<R>,
+    options: ParseOptions,
+    lossless: bool,
+    depth: usize,
+    /// Whether each open square bracket is a tuple, only tracked if tuples allowed.
+    tuples: Vec<bool>,
+    /// Open parentheses not yet known to be tuples, with their depth and the JSON tags read within them.
+    groups: Vec<(usize, Vec<JsonTag>)>,
+    /// JSON tags ready to be returned.
+    pending: VecDeque<JsonTag>,
+    count: usize,
+    is_done: bool,
+}
+
+impl<R: Read> JsonTagReader<R> {
+    /// Create a JSON tag reader, with default options.
+    pub fn new(reader: R) -> Self {
+        JsonTagReader::with_options(reader, &ParseOptions::default())
+    }
+
+    /// Create a JSON tag reader, with specified options.
+    pub fn with_options(reader: R, options: &ParseOptions) -> Self {
+        JsonTagReader {
+            peekable_cp: PeekableCodePoints::with_encoding(
+                reader,
+                options.encoding,
+                options.allow_invalid_bytes,
+            ),
+            options: options.clone(),
+            lossless: false,
+            depth: 0,
+            tuples: Vec::new(),
+            groups: Vec::new(),
+            pending: VecDeque::new(),
+            count: 0,
+            is_done: false,
+        }
+    }
+
+    /// Create a JSON tag reader keeping whitespace and comments as JSON tags, with specified options.
+    pub fn lossless_with_options(reader: R, options: &ParseOptions) -> Self {
+        JsonTagReader {
+            lossless: true,
+            ..JsonTagReader::with_options(reader, options)
+        }
+    }
+
+    /// Count of bytes read from underlying reader so far.
+    pub fn bytes_read(&self) -> usize {
+        self.peekable_cp.bytes_read()
+    }
+
+    /// Read the next JSON tag.<br>
+    /// If tuples allowed, JSON tags within parentheses are held until a comma at their top level is found,
+    /// so that a parenthesized value without comma, such as (1), is read as the value itself,
+    /// while (1,) and () are read as arrays.
+    fn read_next(&mut self) -> Result<Option<JsonTag>> {
         loop {
-            let json_tag = JsonTag::read_json_tag(&mut peekable_cp)?;
-            if json_tag.is_none() {
-                break;
+            if let Some(json_tag) = self.pending.pop_front() {
+                return Ok(Some(json_tag));
             }
 
-            json_tag_list.push(json_tag.unwrap());
+            let is_paren_open = |tuples: &Vec<bool>| tuples.last() == Some(&true);
+            let was_paren_open = is_paren_open(&self.tuples);
+            let json_tag = match self.read_tag()? {
+                Some(json_tag) => json_tag,
+                None if self.groups.is_empty() => return Ok(None),
+                None => {
+                    // unclosed parentheses are read as tuples, so that they fail as unclosed arrays
+                    while !self.groups.is_empty() {
+                        self.close_group_as_tuple();
+                    }
+                    continue;
+                }
+            };
+
+            let group_depth = self.groups.last().map(|(depth, _)| *depth);
+            match json_tag {
+                JsonTag::LeftSquare if is_paren_open(&self.tuples) => {
+                    self.groups.push((self.depth, Vec::new()));
+                }
+                JsonTag::Comma if group_depth == Some(self.depth) => {
+                    self.close_group_as_tuple();
+                    self.emit(json_tag);
+                }
+                JsonTag::RightSquare if was_paren_open && group_depth == Some(self.depth + 1) => {
+                    if let Some((_, json_tags)) = self.groups.pop() {
+                        if json_tags.iter().all(JsonTag::is_trivia) {
+                            self.emit(JsonTag::LeftSquare);
+                            json_tags.into_iter().for_each(|t| self.emit(t));
+                            self.emit(json_tag);
+                        } else {
+                            json_tags.into_iter().for_each(|t| self.emit(t));
+                        }
+                    }
+                }
+                _ => self.emit(json_tag),
+            }
         }
+    }
 
-        Ok(json_tag_list)
+    /// Close the innermost open parentheses as a tuple, emitting the JSON tags held within them.
+    fn close_group_as_tuple(&mut self) {
+        if let Some((_, json_tags)) = self.groups.pop() {
+            self.emit(JsonTag::LeftSquare);
+            json_tags.into_iter().for_each(|t| self.emit(t));
+        }
+    }
+
+    /// Emit a JSON tag, held by the innermost open parentheses if any, otherwise ready to be returned.
+    fn emit(&mut self, json_tag: JsonTag) {
+        match self.groups.last_mut() {
+            Some((_, json_tags)) => json_tags.push(json_tag),
+            None => self.pending.push_back(json_tag),
+        }
+    }
+
+    /// Read the next JSON tag from underlying reader, checking limits.
+    fn read_tag(&mut self) -> Result<Option<JsonTag>> {
+        let limits = &self.options.limits;
+        let json_tag = JsonTag::read_json_tag(
+            &mut self.peekable_cp,
+            &self.options,
+            self.lossless,
+            &mut self.tuples,
+        )?;
+        limits.check_input_len(self.peekable_cp.bytes_read())?;
+        let json_tag = match json_tag {
+            Some(json_tag) => json_tag,
+            None => return Ok(None),
+        };
+        match json_tag {
+            JsonTag::LeftCurly | JsonTag::LeftSquare => {
+                self.depth += 1;
+                limits.check_depth(self.depth)?;
+            }
+            JsonTag::RightCurly | JsonTag::RightSquare => self.depth = self.depth.saturating_sub(1),
+            _ => (),
+        }
+
+        self.count += 1;
+        limits.check_tags(self.count)?;
+        Ok(Some(json_tag))
+    }
+}
+
+impl<R: Read> Iterator for JsonTagReader<R> {
+    type Item = Result<JsonTag>;
+
+    /// Read the next JSON tag, or an error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        let json_tag = self.read_next();
+        self.is_done = !matches!(json_tag, Ok(Some(_)));
+        json_tag.transpose()
     }
 }
 
 #[cfg(test)]
 mod json_tag_tests {
     use super::*;
+    use crate::JsonNode;
 
     /// Test JSON tag parsing using a single line of JSON string.
     #[test]
@@ -165,30 +732,30 @@ mod json_tag_tests {
                 // {
                 JsonTag::LeftCurly,
                 // "simple": 123
-                JsonTag::Literal(String::from(r#""simple""#)),
+                JsonTag::Literal(String::from(r#""simple""#), LiteralKind::String('"')),
                 JsonTag::Colon,
-                JsonTag::Literal(String::from(r#"123"#)),
+                JsonTag::Literal(String::from(r#"123"#), LiteralKind::Number),
                 // ,
                 JsonTag::Comma,
                 // "array": ["a", "b", "c\""]
-                JsonTag::Literal(String::from(r#""array""#)),
+                JsonTag::Literal(String::from(r#""array""#), LiteralKind::String('"')),
                 JsonTag::Colon,
                 JsonTag::LeftSquare,
-                JsonTag::Literal(String::from(r#""a""#)),
+                JsonTag::Literal(String::from(r#""a""#), LiteralKind::String('"')),
                 JsonTag::Comma,
-                JsonTag::Literal(String::from(r#""b""#)),
+                JsonTag::Literal(String::from(r#""b""#), LiteralKind::String('"')),
                 JsonTag::Comma,
-                JsonTag::Literal(String::from(r#""c\"""#)),
+                JsonTag::Literal(String::from(r#""c\"""#), LiteralKind::String('"')),
                 JsonTag::RightSquare,
                 // ,
                 JsonTag::Comma,
                 // "object": {"prop": "{true]"}
-                JsonTag::Literal(String::from(r#""object""#)),
+                JsonTag::Literal(String::from(r#""object""#), LiteralKind::String('"')),
                 JsonTag::Colon,
                 JsonTag::LeftCurly,
-                JsonTag::Literal(String::from(r#""prop""#)),
+                JsonTag::Literal(String::from(r#""prop""#), LiteralKind::String('"')),
                 JsonTag::Colon,
-                JsonTag::Literal(String::from(r#""{true]""#)),
+                JsonTag::Literal(String::from(r#""{true]""#), LiteralKind::String('"')),
                 JsonTag::RightCurly,
                 // }
                 JsonTag::RightCurly,
@@ -219,30 +786,30 @@ mod json_tag_tests {
                 // {
                 JsonTag::LeftCurly,
                 // "simple": 123
-                JsonTag::Literal(String::from(r#""simple""#)),
+                JsonTag::Literal(String::from(r#""simple""#), LiteralKind::String('"')),
                 JsonTag::Colon,
-                JsonTag::Literal(String::from(r#"123"#)),
+                JsonTag::Literal(String::from(r#"123"#), LiteralKind::Number),
                 // ,
                 JsonTag::Comma,
                 // "array": ["a", "b", "c\""]
-                JsonTag::Literal(String::from(r#""array""#)),
+                JsonTag::Literal(String::from(r#""array""#), LiteralKind::String('"')),
                 JsonTag::Colon,
                 JsonTag::LeftSquare,
-                JsonTag::Literal(String::from(r#""a""#)),
+                JsonTag::Literal(String::from(r#""a""#), LiteralKind::String('"')),
                 JsonTag::Comma,
-                JsonTag::Literal(String::from(r#""b""#)),
+                JsonTag::Literal(String::from(r#""b""#), LiteralKind::String('"')),
                 JsonTag::Comma,
-                JsonTag::Literal(String::from(r#""c\"""#)),
+                JsonTag::Literal(String::from(r#""c\"""#), LiteralKind::String('"')),
                 JsonTag::RightSquare,
                 // ,
                 JsonTag::Comma,
                 // "object": {"prop": "{true]"}
-                JsonTag::Literal(String::from(r#""object""#)),
+                JsonTag::Literal(String::from(r#""object""#), LiteralKind::String('"')),
                 JsonTag::Colon,
                 JsonTag::LeftCurly,
-                JsonTag::Literal(String::from(r#""prop""#)),
+                JsonTag::Literal(String::from(r#""prop""#), LiteralKind::String('"')),
                 JsonTag::Colon,
-                JsonTag::Literal(String::from(r#""{true]""#)),
+                JsonTag::Literal(String::from(r#""{true]""#), LiteralKind::String('"')),
                 JsonTag::RightCurly,
                 // }
                 JsonTag::RightCurly,
@@ -274,32 +841,32 @@ mod json_tag_tests {
                 // {
                 JsonTag::LeftCurly,
                 // "simple": 123
-                JsonTag::Literal(String::from(r#""simple""#)),
+                JsonTag::Literal(String::from(r#""simple""#), LiteralKind::String('"')),
                 JsonTag::Colon,
-                JsonTag::Literal(String::from(r#"123"#)),
+                JsonTag::Literal(String::from(r#"123"#), LiteralKind::Number),
                 // ,
                 JsonTag::Comma,
                 // "array": ["a", "b", "c\""]
-                JsonTag::Literal(String::from(r#""array""#)),
+                JsonTag::Literal(String::from(r#""array""#), LiteralKind::String('"')),
                 JsonTag::Colon,
                 JsonTag::LeftSquare,
-                JsonTag::Literal(String::from(r#""a""#)),
+                JsonTag::Literal(String::from(r#""a""#), LiteralKind::String('"')),
                 JsonTag::Comma,
-                JsonTag::Literal(String::from(r#""b""#)),
+                JsonTag::Literal(String::from(r#""b""#), LiteralKind::String('"')),
                 JsonTag::Comma,
-                JsonTag::Literal(String::from(r#""c\"""#)),
+                JsonTag::Literal(String::from(r#""c\"""#), LiteralKind::String('"')),
                 JsonTag::RightSquare,
                 // ,
                 JsonTag::Comma,
                 // "obj
                 // ect": {"prop": "{true]"}
-                JsonTag::Literal(String::from(r#""obj"#)),
-                JsonTag::Literal(String::from(r#"ect""#)),
+                JsonTag::Literal(String::from(r#""obj"#), LiteralKind::String('"')),
+                JsonTag::Literal(String::from(r#"ect""#), LiteralKind::BareWord),
                 JsonTag::Colon,
                 JsonTag::LeftCurly,
-                JsonTag::Literal(String::from(r#""prop""#)),
+                JsonTag::Literal(String::from(r#""prop""#), LiteralKind::String('"')),
                 JsonTag::Colon,
-                JsonTag::Literal(String::from(r#""{true]""#)),
+                JsonTag::Literal(String::from(r#""{true]""#), LiteralKind::String('"')),
                 JsonTag::RightCurly,
                 // }
                 JsonTag::RightCurly,
@@ -307,4 +874,115 @@ mod json_tag_tests {
         );
         Ok(())
     }
+
+    /// Test lossless parsing keeps whitespace and comments and reproduces the input exactly.
+    #[test]
+    fn test_lossless_round_trip() -> Result<()> {
+        let json = "{\r\n  // line comment\n  \"a\" :1/* block\n comment */,\t\"b\": [ true ,\"x // y\" ]\n} \n";
+        let json_tags = JsonTag::parse_lossless(json.as_bytes())?;
+        assert_eq!(JsonTag::to_raw_string(&json_tags), json);
+        assert_eq!(
+            json_tags[..7],
+            [
+                JsonTag::LeftCurly,
+                JsonTag::Whitespace(String::from("\r\n  ")),
+                JsonTag::Comment(String::from("// line comment")),
+                JsonTag::Whitespace(String::from("\n  ")),
+                JsonTag::Literal(String::from("\"a\""), LiteralKind::String('"')),
+                JsonTag::Whitespace(String::from(" ")),
+                JsonTag::Colon,
+            ]
+        );
+        assert_eq!(
+            json_tags[7],
+            JsonTag::Literal(String::from("1"), LiteralKind::Number)
+        );
+        assert_eq!(
+            json_tags[8],
+            JsonTag::Comment(String::from("/* block\n comment */"))
+        );
+
+        let json_node = JsonNode::parse_tags(&json_tags)?;
+        assert_eq!(
+            json_node[0].to_string(),
+            r#"{"a": 1, "b": [true, "x // y"]}"#
+        );
+
+        assert!(JsonTag::parse_lossless("[1 /* oops".as_bytes()).is_err());
+
+        Ok(())
+    }
+
+    /// Test classifying literals and decoding their values under different parse options.
+    #[test]
+    fn test_literal_kinds() -> Result<()> {
+        let strict = ParseOptions::strict();
+        let json5 = ParseOptions::json5();
+        let kinds = |json: &str, options: &ParseOptions| -> Result<Vec<LiteralKind>> {
+            let json_tags = JsonTag::parse_with_options(json.as_bytes(), options)?;
+            Ok(json_tags.iter().filter_map(|t| t.literal_kind()).collect())
+        };
+
+        let json = r#"["a", 'b', 1.5, 0x1F, Infinity, true, null, c]"#;
+        assert_eq!(
+            kinds(json, &json5)?,
+            [
+                LiteralKind::String('"'),
+                LiteralKind::String('\''),
+                LiteralKind::Number,
+                LiteralKind::Number,
+                LiteralKind::Number,
+                LiteralKind::Boolean,
+                LiteralKind::Null,
+                LiteralKind::BareWord,
+            ]
+        );
+        assert_eq!(
+            kinds(json, &strict)?[1..5],
+            [
+                LiteralKind::BareWord,
+                LiteralKind::Number,
+                LiteralKind::BareWord,
+                LiteralKind::BareWord
+            ]
+        );
+        assert_eq!(
+            LiteralKind::classify("TRUE", &strict),
+            LiteralKind::BareWord
+        );
+        assert_eq!(
+            LiteralKind::classify("TRUE", &ParseOptions::lenient()),
+            LiteralKind::Boolean
+        );
+
+        let literal = JsonTag::Literal(String::from("'a\\tb'"), LiteralKind::String('\''));
+        assert_eq!(
+            literal.literal_value(&json5)?,
+            Some(JsonNode::PlainString(String::from("a\tb")))
+        );
+        assert!(literal.literal_value(&strict).is_err());
+        let literal = JsonTag::Literal(String::from("0x1F"), LiteralKind::Number);
+        let value = literal.literal_value(&json5)?;
+        assert!(matches!(value, Some(JsonNode::PlainNumber(n)) if n.as_u64() == Some(31)));
+        assert!(literal.literal_value(&strict).is_err());
+        assert_eq!(JsonTag::Comma.literal_value(&strict)?, None);
+
+        let json_tags = JsonTag::parse_with_options("[0x1FFFFFFFFFFFFFFFF]".as_bytes(), &json5)?;
+        assert_eq!(
+            json_tags[1].literal_kind(),
+            Some(LiteralKind::MalformedNumber)
+        );
+        assert!(json_tags[1].literal_value(&json5).is_err());
+
+        // the kind is classified when read, while stricter options still reject it
+        let json_tags = JsonTag::parse_with_options("True".as_bytes(), &ParseOptions::lenient())?;
+        assert_eq!(json_tags[0].literal_kind(), Some(LiteralKind::Boolean));
+        assert_eq!(
+            json_tags[0].literal_value(&ParseOptions::lenient())?,
+            Some(JsonNode::PlainBoolean(true))
+        );
+        assert!(json_tags[0].literal_value(&strict).is_err());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,313 @@
+//! Composable adapters transforming a stream of JSON events without materializing it,
+//! such as dropping properties, renaming keys, replacing values, and limiting array lengths.
+
+use anyhow::Result;
+use std::io::Write;
+
+use crate::json_event::*;
+use crate::json_node::*;
+
+/// Step of the path of a JSON value in a stream of JSON events, either a property name or an array index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathStep {
+    Key(String),
+    Index(usize),
+}
+
+/// Predicate of paths of JSON values.
+type PredicateFn<'a> = Box<dyn FnMut(&[PathStep]) -> bool + 'a>;
+
+/// Function returning the new name of an object property, or None to keep the name.
+type RenameFn<'a> = Box<dyn FnMut(&str) -> Option<String> + 'a>;
+
+/// Function of a path and a plain value returning the new value, or None to keep the value.
+type MapFn<'a> = Box<dyn FnMut(&[PathStep], &JsonNode) -> Option<JsonNode> + 'a>;
+
+/// Object or array being tracked by a path tracker.
+struct TrackFrame {
+    is_object: bool,
+    count: usize,
+    step: Option<PathStep>,
+}
+
+/// Tracker of the path of JSON values along a stream of JSON events.
+#[derive(Default)]
+struct PathTracker {
+    frames: Vec<TrackFrame>,
+    path: Vec<PathStep>,
+}
+
+impl PathTracker {
+    /// Update the path by a JSON event, after which the path is the one of the value of the event,
+    /// or of the property for a Key event.
+    fn update(&mut self, json_event: &JsonEvent) {
+        match json_event {
+            JsonEvent::Key(name) => {
+                if let Some(frame) = self.frames.last_mut() {
+                    frame.step = Some(PathStep::Key(name.clone()));
+                }
+            }
+            JsonEvent::StartObject | JsonEvent::StartArray | JsonEvent::Value(_) => {
+                if let Some(frame) = self.frames.last_mut() {
+                    if !frame.is_object {
+                        frame.step = Some(PathStep::Index(frame.count));
+                    }
+                    frame.count += 1;
+                }
+                if !matches!(json_event, JsonEvent::Value(_)) {
+                    self.frames.push(TrackFrame {
+                        is_object: *json_event == JsonEvent::StartObject,
+                        count: 0,
+                        step: None,
+                    });
+                }
+            }
+            JsonEvent::EndObject | JsonEvent::EndArray => {
+                self.frames.pop();
+            }
+        }
+
+        self.path = self.frames.iter().filter_map(|f| f.step.clone()).collect();
+    }
+
+    /// Count of objects and arrays the stream is in.
+    fn depth(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+/// Adapter dropping the JSON values whose path matches a predicate, along with their property names.<br>
+/// The top-level value is never dropped.
+pub struct DropValues<'a, I> {
+    inner: I,
+    tracker: PathTracker,
+    predicate: PredicateFn<'a>,
+    /// Depth at which the value being dropped ends.
+    skip_depth: Option<usize>,
+}
+
+impl<I: Iterator<Item = Result<JsonEvent>>> Iterator for DropValues<'_, I> {
+    type Item = Result<JsonEvent>;
+
+    /// Get the next JSON event which is not dropped.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let json_event = match self.inner.next()? {
+                Ok(json_event) => json_event,
+                Err(e) => return Some(Err(e)),
+            };
+            let depth = self.tracker.depth();
+            self.tracker.update(&json_event);
+            let is_start = matches!(json_event, JsonEvent::StartObject | JsonEvent::StartArray);
+            if let Some(skip_depth) = self.skip_depth {
+                // the value ends with a plain value or the end of object or array at the same depth
+                if self.tracker.depth() == skip_depth && !is_start {
+                    self.skip_depth = None;
+                }
+                continue;
+            }
+
+            let in_array = matches!(self.tracker.path.last(), Some(PathStep::Index(_)));
+            let is_entry = match json_event {
+                JsonEvent::Key(_) => true,
+                JsonEvent::Value(_) => in_array && depth > 0,
+                _ => is_start && in_array && depth > 0,
+            };
+            if is_entry && (self.predicate)(&self.tracker.path) {
+                if is_start || matches!(json_event, JsonEvent::Key(_)) {
+                    self.skip_depth = Some(depth);
+                }
+                continue;
+            }
+
+            return Some(Ok(json_event));
+        }
+    }
+}
+
+/// Adapter renaming object properties, by a function returning the new name, or None to keep the name.
+pub struct RenameKeys<'a, I> {
+    inner: I,
+    rename: RenameFn<'a>,
+}
+
+impl<I: Iterator<Item = Result<JsonEvent>>> Iterator for RenameKeys<'_, I> {
+    type Item = Result<JsonEvent>;
+
+    /// Get the next JSON event, with property name renamed if needed.
+    fn next(&mut self) -> Option<Self::Item> {
+        let json_event = match self.inner.next()? {
+            Ok(JsonEvent::Key(name)) => match (self.rename)(&name) {
+                Some(new_name) => JsonEvent::Key(new_name),
+                None => JsonEvent::Key(name),
+            },
+            json_event => return Some(json_event),
+        };
+
+        Some(Ok(json_event))
+    }
+}
+
+/// Adapter replacing plain values, by a function of the path and the value returning the new value,
+/// or None to keep the value.
+pub struct MapValues<'a, I> {
+    inner: I,
+    tracker: PathTracker,
+    map: MapFn<'a>,
+}
+
+impl<I: Iterator<Item = Result<JsonEvent>>> Iterator for MapValues<'_, I> {
+    type Item = Result<JsonEvent>;
+
+    /// Get the next JSON event, with plain value replaced if needed.
+    fn next(&mut self) -> Option<Self::Item> {
+        let json_event = match self.inner.next()? {
+            Ok(json_event) => json_event,
+            Err(e) => return Some(Err(e)),
+        };
+        self.tracker.update(&json_event);
+        let json_event = match json_event {
+            JsonEvent::Value(json_node) => match (self.map)(&self.tracker.path, &json_node) {
+                Some(new_node) => JsonEvent::Value(new_node),
+                None => JsonEvent::Value(json_node),
+            },
+            json_event => json_event,
+        };
+
+        Some(Ok(json_event))
+    }
+}
+
+/// Transformations of a stream of JSON events, such as JsonEventReader, which can be chained
+/// and finally written to a JsonEventWriter.<br>
+/// Paths passed to functions are paths in the input of each adapter, starting from the top-level value.
+pub trait JsonEventTransform: Iterator<Item = Result<JsonEvent>> + Sized {
+    /// Drop the object properties and array elements whose path matches a predicate.
+    fn drop_values<'a, F>(self, predicate: F) -> DropValues<'a, Self>
+    where
+        F: FnMut(&[PathStep]) -> bool + 'a,
+    {
+        DropValues {
+            inner: self,
+            tracker: PathTracker::default(),
+            predicate: Box::new(predicate),
+            skip_depth: None,
+        }
+    }
+
+    /// Drop every object property of specified names, at any depth.
+    fn drop_keys<'a>(self, names: &[&str]) -> DropValues<'a, Self> {
+        let names: Vec<String> = names.iter().map(|n| String::from(*n)).collect();
+        self.drop_values(
+            move |path| matches!(path.last(), Some(PathStep::Key(k)) if names.contains(k)),
+        )
+    }
+
+    /// Drop the elements of every array beyond specified length.
+    fn limit_arrays<'a>(self, max_len: usize) -> DropValues<'a, Self> {
+        self.drop_values(
+            move |path| matches!(path.last(), Some(PathStep::Index(i)) if *i >= max_len),
+        )
+    }
+
+    /// Rename object properties, by a function returning the new name, or None to keep the name.
+    fn rename_keys<'a, F>(self, rename: F) -> RenameKeys<'a, Self>
+    where
+        F: FnMut(&str) -> Option<String> + 'a,
+    {
+        RenameKeys {
+            inner: self,
+            rename: Box::new(rename),
+        }
+    }
+
+    /// Replace plain values, by a function of the path and the value returning the new value,
+    /// or None to keep the value.
+    fn map_values<'a, F>(self, map: F) -> MapValues<'a, Self>
+    where
+        F: FnMut(&[PathStep], &JsonNode) -> Option<JsonNode> + 'a,
+    {
+        MapValues {
+            inner: self,
+            tracker: PathTracker::default(),
+            map: Box::new(map),
+        }
+    }
+
+    /// Write all JSON events to a JSON event writer, stopping at the first error.
+    fn write_to<W: Write>(self, json_event_writer: &mut JsonEventWriter<W>) -> Result<()> {
+        for json_event in self {
+            json_event_writer.write_event(&json_event?)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<I: Iterator<Item = Result<JsonEvent>>> JsonEventTransform for I {}
+
+#[cfg(test)]
+mod json_transform_tests {
+    use super::*;
+    use crate::json_writer::WriteOptions;
+
+    /// Transform JSON events of a JSON string and write them compactly.
+    fn transform<F>(json: &str, f: F) -> Result<String>
+    where
+        F: FnOnce(JsonEventReader<&[u8]>, &mut JsonEventWriter<Vec<u8>>) -> Result<()>,
+    {
+        let mut writer = JsonEventWriter::new(Vec::new(), WriteOptions::compact());
+        f(JsonEventReader::new(json.as_bytes()), &mut writer)?;
+        Ok(String::from_utf8(writer.into_inner())?)
+    }
+
+    /// Test dropping values and limiting arrays.
+    #[test]
+    fn test_drop_values() -> Result<()> {
+        let json = r#"{"user": "a", "password": "x", "logs": [{"password": {"old": 1}, "n": 1}, [1, 2, 3], 3]}"#;
+        let output = transform(json, |r, w| r.drop_keys(&["password"]).write_to(w))?;
+        assert_eq!(output, r#"{"user":"a","logs":[{"n":1},[1,2,3],3]}"#);
+
+        let output = transform(json, |r, w| r.limit_arrays(2).write_to(w))?;
+        assert_eq!(
+            output,
+            r#"{"user":"a","password":"x","logs":[{"password":{"old":1},"n":1},[1,2]]}"#
+        );
+
+        let output = transform(json, |r, w| {
+            r.drop_values(|path| path == [PathStep::Key(String::from("logs")), PathStep::Index(0)])
+                .write_to(w)
+        })?;
+        assert_eq!(output, r#"{"user":"a","password":"x","logs":[[1,2,3],3]}"#);
+
+        let output = transform("[[1], 2]", |r, w| r.drop_values(|_| true).write_to(w))?;
+        assert_eq!(output, "[]");
+        Ok(())
+    }
+
+    /// Test renaming keys and replacing values, chained.
+    #[test]
+    fn test_chained() -> Result<()> {
+        let json = r#"{"name": "a", "tags": ["x", "y"], "meta": {"name": "b", "count": 2}}"#;
+        let output = transform(json, |r, w| {
+            r.rename_keys(|name| (name == "name").then(|| String::from("title")))
+                .map_values(|path, node| match (path.last(), node) {
+                    (Some(PathStep::Index(_)), JsonNode::PlainString(s)) => {
+                        Some(JsonNode::PlainString(s.to_uppercase()))
+                    }
+                    (_, JsonNode::PlainNumber(_)) => Some(JsonNode::PlainNull),
+                    _ => None,
+                })
+                .limit_arrays(1)
+                .write_to(w)
+        })?;
+        assert_eq!(
+            output,
+            r#"{"title":"a","tags":["X"],"meta":{"title":"b","count":null}}"#
+        );
+
+        let result = transform("[1, 2", |r, w| r.limit_arrays(1).write_to(w));
+        assert!(result.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,557 @@
+//! Validation of JSON tags against the JSON grammar, reporting every problem found with its position.
+
+use anyhow::Result;
+use std::{fmt, io::Read};
+
+use crate::json_node::*;
+use crate::json_repair::*;
+use crate::json_tag::*;
+use crate::parse_options::*;
+
+/// Kind of a problem found when validating JSON tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// Tag not allowed where it is, such as a stray colon, a stray closing bracket or a comment.
+    UnexpectedTag,
+    /// Missing comma between entries.
+    MissingComma,
+    /// Missing colon between property name and value.
+    MissingColon,
+    /// Missing value, such as a property name without value, or empty input.
+    MissingValue,
+    /// Leading, trailing or duplicated comma.
+    ExtraComma,
+    /// Closing bracket not matching the innermost open bracket.
+    MismatchedBracket,
+    /// Bracket not closed at the end of input.
+    UnclosedBracket,
+    /// Literal which is neither a valid property name nor a valid value, such as a bare word or an invalid string.
+    InvalidLiteral,
+    /// Another value after the top-level value.
+    ExtraValue,
+}
+
+/// A problem found when validating JSON tags, with its position and what is expected versus what is found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonDiagnostic {
+    /// Kind of the problem.
+    pub kind: DiagnosticKind,
+
+    /// Index of the JSON tag where the problem is found, which is the count of JSON tags at the end of input.
+    pub index: usize,
+
+    /// Char offset in the input where the problem is found.<br>
+    /// Offset, line and column are exact if JSON tags are parsed losslessly, otherwise whitespace is not counted.
+    pub offset: usize,
+
+    /// Line number, starting from 1.
+    pub line: usize,
+
+    /// Column number in chars, starting from 1.
+    pub column: usize,
+
+    /// Count of chars of the JSON tag where the problem is found, 0 at the end of input.
+    pub len: usize,
+
+    /// Description of what is expected, such as: ',' or ']'
+    pub expected: String,
+
+    /// Description of what is found, such as the text of the JSON tag, or: end of input
+    pub found: String,
+}
+
+impl fmt::Display for JsonDiagnostic {
+    /// Describe the problem, such as: MissingComma at 1:4: expected ',' or ']', found 2
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} at {}:{}: expected {}, found {}",
+            self.kind, self.line, self.column, self.expected, self.found
+        )
+    }
+}
+
+/// Position of a JSON tag in the input.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Position {
+    index: usize,
+    offset: usize,
+    line: usize,
+    column: usize,
+    len: usize,
+}
+
+/// Get the positions of JSON tags, followed by the position of the end of input.
+pub(crate) fn positions(json_tags: &[JsonTag]) -> Vec<Position> {
+    let mut position = Position {
+        index: 0,
+        offset: 0,
+        line: 1,
+        column: 1,
+        len: 0,
+    };
+    let mut positions = Vec::with_capacity(json_tags.len() + 1);
+    for json_tag in json_tags {
+        position.len = raw_len(json_tag);
+        positions.push(position);
+
+        position.index += 1;
+        position.offset += position.len;
+        match json_tag {
+            JsonTag::Literal(s, _) | JsonTag::Whitespace(s) | JsonTag::Comment(s) => {
+                let mut chars = s.chars().peekable();
+                while let Some(c) = chars.next() {
+                    if c == '\n' || (c == '\r' && chars.peek() != Some(&'\n')) {
+                        position.line += 1;
+                        position.column = 1;
+                    } else if c != '\r' {
+                        position.column += 1;
+                    }
+                }
+            }
+            _ => position.column += 1,
+        }
+    }
+
+    position.len = 0;
+    positions.push(position);
+    positions
+}
+
+/// An array or object being validated.
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    is_object: bool,
+    expect: Expect,
+    /// Position of the comma just read, which is a trailing comma if the container closes.
+    comma: Option<Position>,
+}
+
+/// State of validating, with diagnostics reported so far.
+struct Validator<'a> {
+    options: &'a ParseOptions,
+    frames: Vec<Frame>,
+    has_value: bool,
+    position: Position,
+    diagnostics: Vec<JsonDiagnostic>,
+}
+
+/// Describe the closing bracket of an array or object.
+pub(crate) fn closing(is_object: bool) -> &'static str {
+    if is_object {
+        "'}'"
+    } else {
+        "']'"
+    }
+}
+
+/// Describe what is expected instead of an invalid value literal of specified kind.
+pub(crate) fn expected_value(kind: LiteralKind) -> &'static str {
+    match kind {
+        LiteralKind::String(_) => "valid string",
+        LiteralKind::MalformedNumber => "number in range",
+        _ => "value",
+    }
+}
+
+/// Describe what is expected instead of an invalid property name literal of specified kind.
+pub(crate) fn expected_prop_name(kind: LiteralKind) -> &'static str {
+    match kind {
+        LiteralKind::String(_) => "valid string",
+        _ => "property name",
+    }
+}
+
+/// Get the options to tokenize input losslessly before validating it with specified options,
+/// accepting strings broken across lines and non-JSON whitespace, so that they are reported as diagnostics.
+pub(crate) fn tokenize_options(options: &ParseOptions) -> ParseOptions {
+    ParseOptions {
+        allow_multiline_strings: true,
+        allow_non_json_whitespace: true,
+        ..options.clone()
+    }
+}
+
+impl JsonDiagnostic {
+    /// Create a diagnostic at specified position.
+    pub(crate) fn new(
+        position: Position,
+        kind: DiagnosticKind,
+        expected: &str,
+        found: &str,
+    ) -> JsonDiagnostic {
+        JsonDiagnostic {
+            kind,
+            index: position.index,
+            offset: position.offset,
+            line: position.line,
+            column: position.column,
+            len: position.len,
+            expected: String::from(expected),
+            found: String::from(found),
+        }
+    }
+
+    /// Validate RFC 8259 JSON read from a instance that implements Read trait.
+    pub fn validate<R>(reader: R) -> Result<Vec<JsonDiagnostic>>
+    where
+        R: Read,
+    {
+        JsonDiagnostic::validate_with_options(reader, &ParseOptions::strict())
+    }
+
+    /// Validate JSON read from a instance that implements Read trait, accepting the leniencies of specified options.<br>
+    /// The input is tokenized losslessly, accepting strings broken across lines and non-JSON whitespace,
+    /// so that they are reported as diagnostics.
+    /// Only I/O errors, decoding errors, limit errors and unterminated comments fail.
+    pub fn validate_with_options<R>(
+        reader: R,
+        options: &ParseOptions,
+    ) -> Result<Vec<JsonDiagnostic>>
+    where
+        R: Read,
+    {
+        let json_tags = JsonTag::parse_lossless_with_options(reader, &tokenize_options(options))?;
+        Ok(JsonDiagnostic::validate_tags(&json_tags, options))
+    }
+
+    /// Validate JSON tags against the JSON grammar, accepting the leniencies of specified options,
+    /// and report every problem found, ordered by position.<br>
+    /// Pass JSON tags parsed losslessly, so the positions are exact.
+    pub fn validate_tags(json_tags: &[JsonTag], options: &ParseOptions) -> Vec<JsonDiagnostic> {
+        let positions = positions(json_tags);
+        let mut validator = Validator {
+            options,
+            frames: Vec::new(),
+            has_value: false,
+            position: positions[0],
+            diagnostics: Vec::new(),
+        };
+
+        for (json_tag, position) in json_tags.iter().zip(&positions) {
+            validator.position = *position;
+            match json_tag {
+                JsonTag::Whitespace(s) => validator.whitespace(s),
+                JsonTag::Comment(comment) => validator.comment(comment),
+                JsonTag::Literal(literal, kind) => validator.literal(literal, *kind),
+                JsonTag::LeftCurly => validator.open(true),
+                JsonTag::LeftSquare => validator.open(false),
+                JsonTag::RightCurly => validator.close(true),
+                JsonTag::RightSquare => validator.close(false),
+                JsonTag::Comma => validator.comma(),
+                JsonTag::Colon => validator.colon(),
+            }
+        }
+
+        validator.position = positions[json_tags.len()];
+        validator.finish();
+        validator.diagnostics.sort_by_key(|d| d.offset);
+        validator.diagnostics
+    }
+}
+
+impl Validator<'_> {
+    /// Report a problem at the current JSON tag.
+    fn report(&mut self, kind: DiagnosticKind, expected: &str, found: &str) {
+        self.report_at(self.position, kind, expected, found);
+    }
+
+    /// Report a problem at specified position.
+    fn report_at(&mut self, position: Position, kind: DiagnosticKind, expected: &str, found: &str) {
+        self.diagnostics
+            .push(JsonDiagnostic::new(position, kind, expected, found));
+    }
+
+    /// Describe what is expected next.
+    fn expected(&self) -> &'static str {
+        match self.frames.last() {
+            None if self.has_value => "end of input",
+            None => "value",
+            Some(frame) => match (frame.is_object, frame.expect) {
+                (true, Expect::First) => "property name or '}'",
+                (true, Expect::Key) => "property name",
+                (true, Expect::Colon) => "':'",
+                (true, Expect::Next) => "',' or '}'",
+                (false, Expect::First) => "value or ']'",
+                (false, Expect::Next) => "',' or ']'",
+                _ => "value",
+            },
+        }
+    }
+
+    /// Check whitespace chars are JSON whitespace.
+    fn whitespace(&mut self, whitespace: &str) {
+        if !self.options.allow_non_json_whitespace
+            && !whitespace
+                .chars()
+                .all(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        {
+            let expected = self.expected();
+            self.report(
+                DiagnosticKind::UnexpectedTag,
+                expected,
+                &format!("{:?}", whitespace),
+            );
+        }
+    }
+
+    /// Check a comment is allowed.
+    fn comment(&mut self, comment: &str) {
+        if !self.options.allow_comments {
+            let expected = self.expected();
+            self.report(DiagnosticKind::UnexpectedTag, expected, comment);
+        }
+    }
+
+    /// Check what is missing before a value, such as comma or colon, and mark the value as an entry.
+    fn before_value(&mut self, found: &str) {
+        let expected = self.expected();
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => {
+                if self.has_value {
+                    self.report(DiagnosticKind::ExtraValue, expected, found);
+                }
+                self.has_value = true;
+                return;
+            }
+        };
+
+        let kind = match (frame.is_object, frame.expect) {
+            (true, Expect::First | Expect::Key | Expect::Next) => {
+                Some(DiagnosticKind::UnexpectedTag)
+            }
+            (true, Expect::Colon) if !self.options.allow_missing_colons => {
+                Some(DiagnosticKind::MissingColon)
+            }
+            (false, Expect::Next) if !self.options.allow_missing_commas => {
+                Some(DiagnosticKind::MissingComma)
+            }
+            _ => None,
+        };
+        frame.expect = Expect::Next;
+        frame.comma = None;
+        if let Some(kind) = kind {
+            self.report(kind, expected, found);
+        }
+    }
+
+    /// Check a literal of specified kind, as property name or value.
+    fn literal(&mut self, literal: &str, kind: LiteralKind) {
+        let frame = match self.frames.last_mut() {
+            Some(frame)
+                if frame.is_object
+                    && frame.expect != Expect::Colon
+                    && frame.expect != Expect::Value =>
+            {
+                frame
+            }
+            _ => {
+                self.before_value(literal);
+                if JsonNode::parse_plain(literal, kind, self.options).is_err() {
+                    let expected = expected_value(kind);
+                    self.report(DiagnosticKind::InvalidLiteral, expected, literal);
+                }
+                return;
+            }
+        };
+
+        let is_missing_comma = frame.expect == Expect::Next && !self.options.allow_missing_commas;
+        frame.expect = Expect::Colon;
+        frame.comma = None;
+        if is_missing_comma {
+            self.report(DiagnosticKind::MissingComma, "',' or '}'", literal);
+        }
+        if JsonNode::parse_prop_name(literal, kind, self.options).is_err() {
+            let expected = expected_prop_name(kind);
+            self.report(DiagnosticKind::InvalidLiteral, expected, literal);
+        }
+    }
+
+    /// Open an array or object.
+    fn open(&mut self, is_object: bool) {
+        self.before_value(if is_object { "'{'" } else { "'['" });
+        self.frames.push(Frame {
+            is_object,
+            expect: Expect::First,
+            comma: None,
+        });
+    }
+
+    /// Close an array or object, reporting unclosed containers nested in it.<br>
+    /// If there is no such container open, the bracket is stray.
+    fn close(&mut self, is_object: bool) {
+        let found = closing(is_object);
+        if !self.frames.iter().any(|f| f.is_object == is_object) {
+            if !self.options.allow_stray_tags {
+                let expected = self.expected();
+                self.report(DiagnosticKind::UnexpectedTag, expected, found);
+            }
+            return;
+        }
+
+        while let Some(frame) = self.frames.pop() {
+            if frame.is_object != is_object {
+                self.report(
+                    DiagnosticKind::MismatchedBracket,
+                    closing(frame.is_object),
+                    found,
+                );
+                continue;
+            }
+
+            match (frame.is_object, frame.expect, frame.comma) {
+                (true, Expect::Colon, _) => self.report(DiagnosticKind::MissingValue, "':'", found),
+                (true, Expect::Value, _) => {
+                    self.report(DiagnosticKind::MissingValue, "value", found)
+                }
+                (_, Expect::Key | Expect::Value, Some(comma))
+                    if !self.options.allow_trailing_commas && !self.options.allow_extra_commas =>
+                {
+                    self.report_at(comma, DiagnosticKind::ExtraComma, found, "','");
+                }
+                _ => (),
+            }
+            break;
+        }
+    }
+
+    /// Check a comma is expected.
+    fn comma(&mut self) {
+        let expected = self.expected();
+        let options = self.options;
+        let position = self.position;
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => {
+                if !options.allow_stray_tags {
+                    self.report(DiagnosticKind::UnexpectedTag, expected, "','");
+                }
+                return;
+            }
+        };
+
+        let kind = match (frame.is_object, frame.expect) {
+            (_, Expect::First | Expect::Key) | (false, Expect::Value) => {
+                if !options.allow_extra_commas {
+                    self.report(DiagnosticKind::ExtraComma, expected, "','");
+                }
+                return;
+            }
+            (true, Expect::Colon | Expect::Value) => Some(DiagnosticKind::MissingValue),
+            _ => None,
+        };
+        frame.expect = if frame.is_object {
+            Expect::Key
+        } else {
+            Expect::Value
+        };
+        frame.comma = Some(position);
+        if let Some(kind) = kind {
+            self.report(kind, expected, "','");
+        }
+    }
+
+    /// Check a colon is expected.
+    fn colon(&mut self) {
+        match self.frames.last_mut() {
+            Some(frame) if frame.is_object && frame.expect == Expect::Colon => {
+                frame.expect = Expect::Value;
+            }
+            _ => {
+                if !self.options.allow_stray_tags {
+                    let expected = self.expected();
+                    self.report(DiagnosticKind::UnexpectedTag, expected, "':'");
+                }
+            }
+        }
+    }
+
+    /// Report what is missing at the end of input.
+    fn finish(&mut self) {
+        let found = "end of input";
+        while let Some(frame) = self.frames.pop() {
+            match (frame.is_object, frame.expect) {
+                (true, Expect::Colon) => self.report(DiagnosticKind::MissingValue, "':'", found),
+                (true, Expect::Value) => self.report(DiagnosticKind::MissingValue, "value", found),
+                _ => (),
+            }
+
+            let expected = closing(frame.is_object);
+            self.report(DiagnosticKind::UnclosedBracket, expected, found);
+        }
+
+        if !self.has_value {
+            self.report(DiagnosticKind::MissingValue, "value", found);
+        }
+    }
+}
+
+#[cfg(test)]
+mod json_validate_tests {
+    use super::*;
+
+    /// Validate a JSON string, and describe the diagnostics.
+    fn validate(json: &str, options: &ParseOptions) -> Result<Vec<String>> {
+        let diagnostics = JsonDiagnostic::validate_with_options(json.as_bytes(), options)?;
+        Ok(diagnostics.iter().map(|d| d.to_string()).collect())
+    }
+
+    /// Test valid JSON has no diagnostics.
+    #[test]
+    fn test_valid() -> Result<()> {
+        let json = "{\n  \"a\": [1, 2.5e3, \"x\"],\n  \"b\": {\"c\": null, \"d\": true}\n}\n";
+        assert!(JsonDiagnostic::validate(json.as_bytes())?.is_empty());
+
+        let json = "// config\n{a: 'x', b: [1 2,],}";
+        assert!(validate(json, &ParseOptions::default())?.is_empty());
+        assert_eq!(validate(json, &ParseOptions::strict())?.len(), 7);
+        Ok(())
+    }
+
+    /// Test every problem is reported with its position.
+    #[test]
+    fn test_diagnostics() -> Result<()> {
+        let strict = ParseOptions::strict();
+        let json = "{\"a\": 1 \"b\": [1,, 2],\n \"c\", \"d\": x, :}";
+        assert_eq!(
+            validate(json, &strict)?,
+            [
+                "MissingComma at 1:9: expected ',' or '}', found \"b\"",
+                "ExtraComma at 1:17: expected value, found ','",
+                "MissingValue at 2:5: expected ':', found ','",
+                "InvalidLiteral at 2:12: expected value, found x",
+                "ExtraComma at 2:13: expected '}', found ','",
+                "UnexpectedTag at 2:15: expected property name, found ':'",
+            ]
+        );
+
+        let json = "[{\"a\": [1, 2}, 3,]\n]";
+        let diagnostics = JsonDiagnostic::validate(json.as_bytes())?;
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::MismatchedBracket);
+        assert_eq!((diagnostics[0].index, diagnostics[0].offset), (10, 12));
+        assert_eq!(diagnostics[1].kind, DiagnosticKind::ExtraComma);
+        assert_eq!(diagnostics[2].kind, DiagnosticKind::UnexpectedTag);
+        assert_eq!((diagnostics[2].line, diagnostics[2].column), (2, 1));
+
+        assert_eq!(
+            validate("[\"a\" 1 {\"b\": 'c'", &strict)?,
+            [
+                "MissingComma at 1:6: expected ',' or ']', found 1",
+                "MissingComma at 1:8: expected ',' or ']', found '{'",
+                "InvalidLiteral at 1:14: expected value, found 'c'",
+                "UnclosedBracket at 1:17: expected '}', found end of input",
+                "UnclosedBracket at 1:17: expected ']', found end of input",
+            ]
+        );
+        assert_eq!(
+            validate("1 2", &strict)?,
+            ["ExtraValue at 1:3: expected end of input, found 2"]
+        );
+        assert_eq!(
+            validate(" ", &strict)?,
+            ["MissingValue at 1:2: expected value, found end of input"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,506 @@
+//! Configurable JSON serializer writing JSON nodes or JSON tags to an instance which implements trait std::io::Write.
+
+use anyhow::{bail, Result};
+use std::{
+    fmt::{self, Write as FmtWrite},
+    io::{self, Write},
+};
+
+use crate::encoding::*;
+use crate::highlight::*;
+use crate::json_node::*;
+use crate::json_string::*;
+use crate::json_tag::*;
+
+/// Indentation of each nesting level in pretty output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+/// Line ending in pretty output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// Get string representation of line ending.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Options of writing JSON.<br>
+/// The default options write single-line JSON with a space after each colon and comma,
+/// same as Display trait implementation of JsonNode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Write each array element and object property on its own line, indented.
+    pub pretty: bool,
+
+    /// Indentation of each nesting level, used in pretty output.
+    pub indent: Indent,
+
+    /// Write a space after each colon.
+    pub space_after_colon: bool,
+
+    /// Write a space after each comma, used in single-line output.
+    pub space_after_comma: bool,
+
+    /// Write object properties sorted by name.
+    pub sort_keys: bool,
+
+    /// Line ending, used in pretty output and trailing newline.
+    pub line_ending: LineEnding,
+
+    /// Write a line ending after each written JSON node.
+    pub trailing_newline: bool,
+
+    /// Maximum line width of pretty output, containers fitting within it are kept on one line.<br>
+    /// None means every container is broken into lines. A tab counts as 4 columns.
+    pub max_width: Option<usize>,
+
+    /// Options of escaping strings.
+    pub escape: EscapeOptions,
+
+    /// Theme of ANSI syntax highlighting, None means plain output.
+    pub theme: Option<Theme>,
+
+    /// Encoding of output written by writers, strings returned are always Unicode.
+    pub encoding: Encoding,
+
+    /// Write a byte order mark of the encoding at the beginning of output written by writers.
+    pub byte_order_mark: bool,
+
+    /// Write JSON5: property names which are identifiers unquoted, strings single-quoted unless
+    /// double quotes need fewer escapes, and a trailing comma after the last entry in pretty output.
+    pub json5: bool,
+}
+
+impl Default for WriteOptions {
+    /// Default options, single-line with a space after each colon and comma.
+    fn default() -> Self {
+        WriteOptions {
+            pretty: false,
+            indent: Indent::Spaces(4),
+            space_after_colon: true,
+            space_after_comma: true,
+            sort_keys: false,
+            line_ending: LineEnding::Lf,
+            trailing_newline: false,
+            max_width: None,
+            escape: EscapeOptions::default(),
+            theme: None,
+            encoding: Encoding::Utf8,
+            byte_order_mark: false,
+            json5: false,
+        }
+    }
+}
+
+impl WriteOptions {
+    /// Options of minified output, without any whitespace.
+    pub fn compact() -> Self {
+        WriteOptions {
+            space_after_colon: false,
+            space_after_comma: false,
+            ..WriteOptions::default()
+        }
+    }
+
+    /// Options of pretty output, indented by 4 spaces.
+    pub fn pretty() -> Self {
+        WriteOptions {
+            pretty: true,
+            ..WriteOptions::default()
+        }
+    }
+
+    /// Options of pretty output, indented by 4 spaces,
+    /// keeping containers on one line if they fit within specified line width.
+    pub fn pretty_with_width(max_width: usize) -> Self {
+        WriteOptions {
+            pretty: true,
+            max_width: Some(max_width),
+            ..WriteOptions::default()
+        }
+    }
+
+    /// Options of idiomatic JSON5 output, pretty and indented by 4 spaces.
+    pub fn json5() -> Self {
+        WriteOptions {
+            pretty: true,
+            json5: true,
+            ..WriteOptions::default()
+        }
+    }
+
+    /// Get count of columns taken by indentation of specified nesting level.
+    pub(crate) fn indent_columns(&self, level: usize) -> usize {
+        match self.indent {
+            Indent::Spaces(width) => width * level,
+            Indent::Tabs => 4 * level,
+        }
+    }
+
+    /// Get count of columns taken by a colon and the space after it.
+    pub(crate) fn colon_columns(&self) -> usize {
+        if self.space_after_colon {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Get options of single-line output, same as these options otherwise.
+    pub(crate) fn single_line(&self) -> Self {
+        WriteOptions {
+            pretty: false,
+            ..self.clone()
+        }
+    }
+
+    /// Write output of a formatting function, highlighted in a style if a theme is configured.
+    pub(crate) fn write_styled<F, G>(&self, f: &mut F, style: Style, g: G) -> fmt::Result
+    where
+        F: FmtWrite,
+        G: FnOnce(&mut F) -> fmt::Result,
+    {
+        write_styled(f, self.theme.as_ref(), style, g)
+    }
+
+    /// Write a bracket, highlighted if a theme is configured.
+    pub(crate) fn write_bracket<F: FmtWrite>(&self, f: &mut F, bracket: char) -> fmt::Result {
+        self.write_styled(f, Style::Punctuation, |f| f.write_char(bracket))
+    }
+
+    /// Get quote char of a string, single quote is preferred in JSON5 unless double quotes need fewer escapes.
+    fn quote_for(&self, s: &str) -> char {
+        if self.json5 && s.matches('\'').count() <= s.matches('"').count() {
+            '\''
+        } else {
+            '"'
+        }
+    }
+
+    /// Write a quoted string value, escaped as configured.
+    pub(crate) fn write_str_value<F: FmtWrite>(&self, f: &mut F, s: &str) -> fmt::Result {
+        write_quoted(f, s, self.quote_for(s), &self.escape)
+    }
+
+    /// Write an object property name, quoted unless it is an identifier in JSON5 output.
+    pub(crate) fn write_prop_name<F: FmtWrite>(&self, f: &mut F, name: &str) -> fmt::Result {
+        if self.json5 && is_identifier(name) {
+            return f.write_str(name);
+        }
+
+        self.write_str_value(f, name)
+    }
+
+    /// Write a trailing comma after the last entry of a container, only in pretty JSON5 output.
+    pub(crate) fn write_trailing_comma<F: FmtWrite>(&self, f: &mut F) -> fmt::Result {
+        if self.json5 && self.pretty {
+            self.write_bracket(f, ',')?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a comma, followed by a space if configured.
+    pub(crate) fn write_comma<F: FmtWrite>(&self, f: &mut F) -> fmt::Result {
+        self.write_bracket(f, ',')?;
+        if !self.pretty && self.space_after_comma {
+            f.write_char(' ')?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a colon, followed by a space if configured.
+    pub(crate) fn write_colon<F: FmtWrite>(&self, f: &mut F) -> fmt::Result {
+        self.write_bracket(f, ':')?;
+        if self.space_after_colon {
+            f.write_char(' ')?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a line ending and indentation of specified nesting level, only in pretty output.
+    pub(crate) fn write_line_break<F: FmtWrite>(&self, f: &mut F, level: usize) -> fmt::Result {
+        if !self.pretty {
+            return Ok(());
+        }
+
+        f.write_str(self.line_ending.as_str())?;
+        match self.indent {
+            Indent::Spaces(width) => write!(f, "{:indent$}", "", indent = width * level),
+            Indent::Tabs => write!(f, "{:\t<indent$}", "", indent = level),
+        }
+    }
+}
+
+/// Writer which discards output and fails once the written chars exceed its capacity, used to measure output.
+pub(crate) struct BoundedWriter {
+    remaining: usize,
+}
+
+impl BoundedWriter {
+    /// Create a bounded writer with capacity of specified count of chars.
+    pub(crate) fn new(capacity: usize) -> Self {
+        BoundedWriter {
+            remaining: capacity,
+        }
+    }
+}
+
+impl FmtWrite for BoundedWriter {
+    /// Count chars of string, failing if capacity is exceeded.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let count = s.chars().count();
+        if count > self.remaining {
+            return Err(fmt::Error);
+        }
+
+        self.remaining -= count;
+        Ok(())
+    }
+}
+
+/// Adapter writing formatted output to an instance which implements trait std::io::Write.
+pub(crate) struct IoAdapter<'a, W: Write> {
+    writer: &'a mut W,
+    encoding: Encoding,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> FmtWrite for IoAdapter<'_, W> {
+    /// Write string to internal writer, keeping the I/O error if any.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.encoding.write_str(self.writer, s).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Write formatted output to an instance which implements trait std::io::Write through an adapter,
+/// encoded in specified encoding, converting formatting error to I/O error if any.
+pub(crate) fn write_fmt_to<W, G>(writer: &mut W, encoding: Encoding, g: G) -> Result<()>
+where
+    W: Write,
+    G: FnOnce(&mut IoAdapter<W>) -> fmt::Result,
+{
+    let mut adapter = IoAdapter {
+        writer,
+        encoding,
+        error: None,
+    };
+    let result = g(&mut adapter);
+    if let Some(e) = adapter.error {
+        return Err(e.into());
+    }
+    if result.is_err() {
+        bail!("failed to format json");
+    }
+
+    Ok(())
+}
+
+/// JSON writer, writes JSON nodes or JSON tags to an instance which implements trait std::io::Write.
+pub struct JsonWriter<W: Write> {
+    writer: W,
+    options: WriteOptions,
+    /// Whether anything has been written, byte order mark is written before the first output.
+    is_started: bool,
+}
+
+impl<W: Write> JsonWriter<W> {
+    /// Create a JSON writer from an instance which implements trait std::io::Write and write options.
+    pub fn new(writer: W, options: WriteOptions) -> Self {
+        JsonWriter {
+            writer,
+            options,
+            is_started: false,
+        }
+    }
+
+    /// Write formatted output through an adapter, followed by a line ending if configured.
+    fn write_fmt_with<G>(&mut self, g: G) -> Result<()>
+    where
+        G: FnOnce(&mut IoAdapter<W>, &WriteOptions) -> fmt::Result,
+    {
+        let options = &self.options;
+        let encoding = options.encoding;
+        if !self.is_started && options.byte_order_mark {
+            encoding.write_str(&mut self.writer, "\u{feff}")?;
+        }
+        self.is_started = true;
+
+        write_fmt_to(&mut self.writer, encoding, |f| g(f, options))?;
+        if options.trailing_newline {
+            encoding.write_str(&mut self.writer, options.line_ending.as_str())?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a JSON node.
+    pub fn write_node(&mut self, json_node: &JsonNode) -> Result<()> {
+        self.write_fmt_with(|f, options| json_node.fmt_indent(f, 0, options))
+    }
+
+    /// Write JSON tags, re-spaced as specified by write options.
+    pub fn write_tags(&mut self, json_tags: &[JsonTag]) -> Result<()> {
+        self.write_fmt_with(|f, options| JsonTag::fmt_tags(f, json_tags, options))
+    }
+
+    /// Flush internal writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Get internal writer back.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod json_writer_tests {
+    use super::*;
+
+    const JSON: &str = r#"{"b": [1, 2, {}], "a": {"d": "x", "c": null}, "e": []}"#;
+
+    /// Write a JSON node to a string with specified options.
+    fn write_to_string(json_node: &JsonNode, options: WriteOptions) -> Result<String> {
+        let mut writer = JsonWriter::new(Vec::new(), options);
+        writer.write_node(json_node)?;
+        Ok(String::from_utf8(writer.into_inner())?)
+    }
+
+    /// Test compact output without any whitespace.
+    #[test]
+    fn test_compact() -> Result<()> {
+        let json_node = JsonNode::parse_single_node(JSON.as_bytes())?;
+        let output = write_to_string(&json_node, WriteOptions::compact())?;
+        assert_eq!(output, r#"{"b":[1,2,{}],"a":{"d":"x","c":null},"e":[]}"#);
+
+        Ok(())
+    }
+
+    /// Test pretty output indented by tabs, with sorted keys, CRLF line endings and a trailing newline.
+    #[test]
+    fn test_pretty_options() -> Result<()> {
+        let json_node = JsonNode::parse_single_node(JSON.as_bytes())?;
+        let options = WriteOptions {
+            indent: Indent::Tabs,
+            sort_keys: true,
+            line_ending: LineEnding::CrLf,
+            trailing_newline: true,
+            ..WriteOptions::pretty()
+        };
+        let output = write_to_string(&json_node, options)?;
+        assert_eq!(
+            output,
+            "{\r\n\t\"a\": {\r\n\t\t\"c\": null,\r\n\t\t\"d\": \"x\"\r\n\t},\r\n\t\"b\": [\r\n\t\t1,\r\n\t\t2,\r\n\t\t{}\r\n\t],\r\n\t\"e\": []\r\n}\r\n"
+        );
+
+        Ok(())
+    }
+
+    /// Test pretty output indented by 2 spaces.
+    #[test]
+    fn test_indent_width() -> Result<()> {
+        let json_node = JsonNode::parse_single_node(r#"{"a": [1]}"#.as_bytes())?;
+        let options = WriteOptions {
+            indent: Indent::Spaces(2),
+            space_after_colon: false,
+            ..WriteOptions::pretty()
+        };
+        let output = write_to_string(&json_node, options)?;
+        assert_eq!(output, "{\n  \"a\":[\n    1\n  ]\n}");
+
+        Ok(())
+    }
+
+    /// Test width-aware pretty output keeps short containers on one line.
+    #[test]
+    fn test_max_width() -> Result<()> {
+        let json = r#"{"type": "LineString", "coordinates": [[102.0, 0.0], [103.0, 1.0], [104.0, 0.0]], "bbox": [1, 2, 3, 4], "props": {}}"#;
+        let json_node = JsonNode::parse_single_node(json.as_bytes())?;
+        let output = write_to_string(&json_node, WriteOptions::pretty_with_width(40))?;
+        assert_eq!(
+            output,
+            r#"{
+    "type": "LineString",
+    "coordinates": [
+        [102.0, 0.0],
+        [103.0, 1.0],
+        [104.0, 0.0]
+    ],
+    "bbox": [1, 2, 3, 4],
+    "props": {}
+}"#
+        );
+
+        let output = write_to_string(&json_node, WriteOptions::pretty_with_width(200))?;
+        assert_eq!(output, json);
+
+        let json_node = JsonNode::parse_single_node("[1, 2, 3]".as_bytes())?;
+        let output = write_to_string(&json_node, WriteOptions::pretty_with_width(8))?;
+        assert_eq!(output, "[\n    1,\n    2,\n    3\n]");
+        let output = write_to_string(&json_node, WriteOptions::pretty_with_width(9))?;
+        assert_eq!(output, "[1, 2, 3]");
+
+        Ok(())
+    }
+
+    /// Test writing JSON tags with write options.
+    #[test]
+    fn test_write_tags() -> Result<()> {
+        let json_tags = JsonTag::parse(JSON.as_bytes())?;
+
+        let mut writer = JsonWriter::new(Vec::new(), WriteOptions::compact());
+        writer.write_tags(&json_tags)?;
+        let output = String::from_utf8(writer.into_inner())?;
+        assert_eq!(output, r#"{"b":[1,2,{}],"a":{"d":"x","c":null},"e":[]}"#);
+
+        let mut writer = JsonWriter::new(Vec::new(), WriteOptions::pretty());
+        writer.write_tags(&json_tags[..11])?;
+        let output = String::from_utf8(writer.into_inner())?;
+        assert_eq!(
+            output,
+            "{\n    \"b\": [\n        1,\n        2,\n        {}\n    ]"
+        );
+
+        Ok(())
+    }
+
+    /// Test JSON5 output with unquoted keys, single quotes and trailing commas.
+    #[test]
+    fn test_json5() -> Result<()> {
+        let json = r#"{"a": "x", "b-c": [1], "d": "it's"}"#;
+        let json_node = JsonNode::parse_single_node(json.as_bytes())?;
+        let output = write_to_string(&json_node, WriteOptions::json5())?;
+        assert_eq!(
+            output,
+            "{\n    a: 'x',\n    'b-c': [\n        1,\n    ],\n    d: \"it's\",\n}"
+        );
+        let options = WriteOptions {
+            pretty: false,
+            ..WriteOptions::json5()
+        };
+        let output = write_to_string(&json_node, options)?;
+        assert_eq!(output, "{a: 'x', 'b-c': [1], d: \"it's\"}");
+
+        Ok(())
+    }
+}
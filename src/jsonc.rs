@@ -0,0 +1,433 @@
+//! JSON with comments(JSONC), a JSON node along with comments attached to its nodes and properties.
+
+use anyhow::Result;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Formatter},
+    io::Read,
+};
+
+use crate::highlight::*;
+use crate::json_document::*;
+use crate::json_node::*;
+use crate::json_path::*;
+use crate::json_tag::*;
+use crate::json_writer::*;
+use crate::parse_options::*;
+
+/// Comments attached to a JSON node, or to an object property along with its value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeComments {
+    /// Comments before the node, each written on its own line.
+    pub leading: Vec<String>,
+
+    /// Comments after the node on the same line, or at the end of the document for the root node.
+    pub trailing: Vec<String>,
+
+    /// Comments inside a container not followed by any entry: inside an empty container or after the last entry.
+    pub dangling: Vec<String>,
+}
+
+impl NodeComments {
+    /// Whether there is no comment at all.
+    pub fn is_empty(&self) -> bool {
+        self.leading.is_empty() && self.trailing.is_empty() && self.dangling.is_empty()
+    }
+}
+
+/// Kinds of comments attached to a JSON node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommentKind {
+    Leading,
+    Trailing,
+    Dangling,
+}
+
+/// JSON node parsed from JSONC, along with comments attached to its nodes.<br>
+/// Values could be read or changed through the JsonNode API of the node field, comments are written back in place.<br>
+/// Comments are attached by positions of array elements and object properties,
+/// so adding or removing entries of a container moves comments of the entries after them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsoncNode {
+    /// The JSON node.
+    pub node: JsonNode,
+
+    /// Comments by locations of attached JSON nodes.
+    comments: BTreeMap<NodeLocation, NodeComments>,
+}
+
+impl JsoncNode {
+    /// Create a JSONC node from a JSON node, without any comment.
+    pub fn new(node: JsonNode) -> Self {
+        JsoncNode {
+            node,
+            comments: BTreeMap::new(),
+        }
+    }
+
+    /// Parse a JSONC node from a instance that implements Read trait, with default options.
+    pub fn parse<R>(reader: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        JsoncNode::parse_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Parse a JSONC node from a instance that implements Read trait, with specified options.<br>
+    /// Comments must be allowed by the options.
+    pub fn parse_with_options<R>(reader: R, options: &ParseOptions) -> Result<Self>
+    where
+        R: Read,
+    {
+        let document = JsonDocument::parse_with_options(reader, options)?;
+        let tags = document.tags();
+        let root = document.root_span()?;
+
+        let mut comments: BTreeMap<NodeLocation, NodeComments> = BTreeMap::new();
+        for (i, tag) in tags.iter().enumerate() {
+            if let JsonTag::Comment(comment) = tag {
+                let (location, kind) = JsoncNode::owner_of(tags, &root, i);
+                let node_comments = comments.entry(location).or_default();
+                let list = match kind {
+                    CommentKind::Leading => &mut node_comments.leading,
+                    CommentKind::Trailing => &mut node_comments.trailing,
+                    CommentKind::Dangling => &mut node_comments.dangling,
+                };
+                list.push(comment.clone());
+            }
+        }
+
+        let jsonc_node = JsoncNode {
+            node: document.to_node()?,
+            comments,
+        };
+        Ok(jsonc_node)
+    }
+
+    /// Get comments attached to the first JSON node selected by JSONPath.
+    pub fn get_comments(&mut self, json_path: &str) -> Result<Option<NodeComments>> {
+        let json_path = JsonPath::parse(json_path)?;
+        let locations = json_path.evaluate_locations(&mut self.node)?;
+        let comments = locations
+            .first()
+            .map(|l| self.comments.get(l).cloned().unwrap_or_default());
+
+        Ok(comments)
+    }
+
+    /// Set comments attached to the JSON nodes selected by JSONPath.
+    pub fn set_comments(&mut self, json_path: &str, comments: NodeComments) -> Result<()> {
+        let json_path = JsonPath::parse(json_path)?;
+        for location in json_path.evaluate_locations(&mut self.node)? {
+            if comments.is_empty() {
+                self.comments.remove(&location);
+            } else {
+                self.comments.insert(location, comments.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compose a JSONC string representation with specified write options.<br>
+    /// Output is always pretty, so that line comments could be kept.
+    pub fn to_string_with_options(&self, options: &WriteOptions) -> String {
+        let options = WriteOptions {
+            pretty: true,
+            max_width: None,
+            ..options.clone()
+        };
+
+        let mut result = String::new();
+        // writing to a String never fails
+        let _ = self.fmt_root(&mut result, &options);
+        if options.trailing_newline {
+            result.push_str(options.line_ending.as_str());
+        }
+
+        result
+    }
+
+    /// Find the JSON node which a comment at specified tag index is attached to, and how it is attached.
+    fn owner_of(tags: &[JsonTag], root: &NodeSpan, comment: usize) -> (NodeLocation, CommentKind) {
+        let mut location = NodeLocation::new();
+        if comment < root.start {
+            return (location, CommentKind::Leading);
+        }
+        if comment > root.end {
+            return (location, CommentKind::Trailing);
+        }
+
+        let mut span = root;
+        loop {
+            let entries = &span.entries;
+            let inner = entries
+                .iter()
+                .position(|e| e.value.start < comment && comment < e.value.end);
+            if let Some(i) = inner {
+                location.push(i);
+                span = &entries[i].value;
+                continue;
+            }
+
+            let between_name_and_value = entries
+                .iter()
+                .position(|e| e.start <= comment && comment < e.value.start);
+            if let Some(i) = between_name_and_value {
+                location.push(i);
+                return (location, CommentKind::Leading);
+            }
+
+            let prev = entries.iter().rposition(|e| e.value.end < comment);
+            if let Some(prev) = prev {
+                if JsoncNode::is_same_line(tags, entries[prev].value.end, comment) {
+                    location.push(prev);
+                    return (location, CommentKind::Trailing);
+                }
+            }
+
+            let next = prev.map_or(0, |prev| prev + 1);
+            if next < entries.len() {
+                location.push(next);
+                return (location, CommentKind::Leading);
+            }
+
+            return (location, CommentKind::Dangling);
+        }
+    }
+
+    /// Whether there is no line break between tags of specified indexes.
+    fn is_same_line(tags: &[JsonTag], from: usize, to: usize) -> bool {
+        tags[from + 1..to].iter().all(|t| match t {
+            JsonTag::Whitespace(s) | JsonTag::Comment(s) => !s.contains('\n'),
+            _ => true,
+        })
+    }
+
+    /// Compose a JSONC string representation of the root node, along with its comments.
+    fn fmt_root<F: fmt::Write>(&self, f: &mut F, options: &WriteOptions) -> fmt::Result {
+        let mut location = NodeLocation::new();
+        let comments = self.comments.get(&location);
+        for comment in comments.map_or(&[][..], |c| &c.leading) {
+            f.write_str(comment)?;
+            options.write_line_break(f, 0)?;
+        }
+
+        self.fmt_node(f, &self.node, &mut location, 0, options)?;
+        JsoncNode::fmt_trailing(f, comments, 0, options)
+    }
+
+    /// Write trailing comments after a node, a line comment ends the line so that comments after it start a new line.
+    fn fmt_trailing<F: fmt::Write>(
+        f: &mut F,
+        comments: Option<&NodeComments>,
+        level: usize,
+        options: &WriteOptions,
+    ) -> fmt::Result {
+        let mut line_ended = false;
+        for comment in comments.map_or(&[][..], |c| &c.trailing) {
+            if line_ended {
+                options.write_line_break(f, level)?;
+            } else {
+                f.write_char(' ')?;
+            }
+            f.write_str(comment)?;
+            line_ended = comment.starts_with("//");
+        }
+
+        Ok(())
+    }
+
+    /// Compose a JSONC string representation of a JSON node at specified location, nested at specified level.
+    fn fmt_node<F: fmt::Write>(
+        &self,
+        f: &mut F,
+        node: &JsonNode,
+        location: &mut NodeLocation,
+        level: usize,
+        options: &WriteOptions,
+    ) -> fmt::Result {
+        let (children, brackets): (Vec<(Option<&str>, &JsonNode)>, _) = match node {
+            JsonNode::Array(arr) => (arr.iter().map(|n| (None, n)).collect(), ('[', ']')),
+            JsonNode::Object(prop_list) => (
+                prop_list
+                    .iter()
+                    .map(|p| (Some(p.name.as_str()), &p.value))
+                    .collect(),
+                ('{', '}'),
+            ),
+            _ => return node.fmt_indent(f, level, options),
+        };
+
+        let mut order: Vec<usize> = (0..children.len()).collect();
+        if options.sort_keys {
+            order.sort_by_key(|i| children[*i].0);
+        }
+
+        let dangling = self
+            .comments
+            .get(location)
+            .map_or(&[][..], |c| c.dangling.as_slice());
+
+        options.write_bracket(f, brackets.0)?;
+        for (k, i) in order.iter().enumerate() {
+            let (name, child) = children[*i];
+            location.push(*i);
+            let comments = self.comments.get(location);
+
+            options.write_line_break(f, level + 1)?;
+            for comment in comments.map_or(&[][..], |c| &c.leading) {
+                f.write_str(comment)?;
+                options.write_line_break(f, level + 1)?;
+            }
+            if let Some(name) = name {
+                options.write_styled(f, Style::Key, |f| options.write_prop_name(f, name))?;
+                options.write_colon(f)?;
+            }
+            self.fmt_node(f, child, location, level + 1, options)?;
+            if k + 1 != order.len() {
+                options.write_comma(f)?;
+            } else {
+                options.write_trailing_comma(f)?;
+            }
+            JsoncNode::fmt_trailing(f, comments, level + 1, options)?;
+
+            location.pop();
+        }
+        for comment in dangling {
+            options.write_line_break(f, level + 1)?;
+            f.write_str(comment)?;
+        }
+        if !children.is_empty() || !dangling.is_empty() {
+            options.write_line_break(f, level)?;
+        }
+        options.write_bracket(f, brackets.1)
+    }
+}
+
+impl fmt::Display for JsoncNode {
+    /// Implement Display trait for JsoncNode, writing pretty JSONC along with comments.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_root(f, &WriteOptions::pretty())
+    }
+}
+
+#[cfg(test)]
+mod jsonc_tests {
+    use super::*;
+
+    const JSONC: &str = r#"// editor settings
+{
+    // font
+    "font.size": 14, // in pixels
+    "files.exclude": {
+        /* nothing yet */
+    },
+    "tabs": [4, /* or */ 2],
+    "theme": "dark"
+    // more to come
+}
+"#;
+
+    /// Test comments are attached to nodes and properties as leading, trailing or dangling.
+    #[test]
+    fn test_attach_comments() -> Result<()> {
+        let mut jsonc_node = JsoncNode::parse(JSONC.as_bytes())?;
+        assert_eq!(
+            jsonc_node.get_comments("$")?,
+            Some(NodeComments {
+                leading: vec![String::from("// editor settings")],
+                trailing: vec![],
+                dangling: vec![String::from("// more to come")],
+            })
+        );
+        let jsonc_node_with_end = JsoncNode::parse("[] // end\n// of file".as_bytes())?;
+        assert_eq!(jsonc_node_with_end.to_string(), "[] // end\n// of file");
+        assert_eq!(
+            jsonc_node.get_comments("$['font.size']")?,
+            Some(NodeComments {
+                leading: vec![String::from("// font")],
+                trailing: vec![String::from("// in pixels")],
+                dangling: vec![],
+            })
+        );
+        assert_eq!(
+            jsonc_node.get_comments("$['files.exclude']")?,
+            Some(NodeComments {
+                dangling: vec![String::from("/* nothing yet */")],
+                ..NodeComments::default()
+            })
+        );
+        assert_eq!(
+            jsonc_node.get_comments("$.tabs[0]")?,
+            Some(NodeComments {
+                trailing: vec![String::from("/* or */")],
+                ..NodeComments::default()
+            })
+        );
+        assert_eq!(jsonc_node.get_comments("$.missing")?, None);
+
+        assert!(JsoncNode::parse_with_options(JSONC.as_bytes(), &ParseOptions::strict()).is_err());
+
+        Ok(())
+    }
+
+    /// Test comments are written back in place after editing values.
+    #[test]
+    fn test_write_comments() -> Result<()> {
+        let mut jsonc_node = JsoncNode::parse(JSONC.as_bytes())?;
+        jsonc_node.node.set_number("$['font.size']", 16f64)?;
+        jsonc_node.set_comments(
+            "$.theme",
+            NodeComments {
+                trailing: vec![String::from("// or light")],
+                ..NodeComments::default()
+            },
+        )?;
+        assert_eq!(
+            jsonc_node.to_string(),
+            r#"// editor settings
+{
+    // font
+    "font.size": 16, // in pixels
+    "files.exclude": {
+        /* nothing yet */
+    },
+    "tabs": [
+        4, /* or */
+        2
+    ],
+    "theme": "dark" // or light
+    // more to come
+}"#
+        );
+
+        let reparsed = JsoncNode::parse(jsonc_node.to_string().as_bytes())?;
+        assert_eq!(reparsed, jsonc_node);
+
+        Ok(())
+    }
+
+    /// Test malformed input returns error rather than panicking, with cases found by fuzzing.
+    #[test]
+    fn test_malformed_jsonc() -> Result<()> {
+        for json in [
+            "}{",
+            "{:}",
+            "{\"a\" 1 2}",
+            "{\"a\": [1}, 2]",
+            "[\"\\uD83D\", '\\u', \"\\\"]",
+            "[0x, -., 1e, .e1, tru, True, NaN, -NaN]",
+            "[\u{feff}1, \u{2028}, \"é😀\0\"]",
+            "/* [1",
+            "// x\r[",
+            "{\"a\": {\"b\": [,]]",
+        ] {
+            if let Ok(jsonc_node) = JsoncNode::parse(json.as_bytes()) {
+                let _ = jsonc_node.to_string();
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -29,24 +29,109 @@
 //! }
 //! ```
 //!
+//! If you need to reject anything other than RFC 8259 JSON, use ParseOptions::strict():
+//! ```
+//! use plainjson::{JsonNode, ParseOptions};
+//!
+//! fn parse_strictly() {
+//!     let json = r#"{"a": True, 'b': test,}"#;
+//!     assert!(JsonNode::parse_single_node_with_options(json.as_bytes(), &ParseOptions::strict()).is_err());
+//!     assert!(JsonNode::parse_single_node(json.as_bytes()).is_ok());
+//! }
+//! ```
+//!
+//! If you need to write JSON to an instance which implements trait std::io::Write, use JsonWriter:
+//! ```
+//! use plainjson::{JsonNode, JsonWriter, WriteOptions};
+//!
+//! fn write_compact_json() {
+//!     let json = JsonNode::parse_single_node(r#"{"a": 123, "b": [3, 2, 1]}"#.as_bytes()).unwrap();
+//!     let mut writer = JsonWriter::new(Vec::new(), WriteOptions::compact());
+//!     writer.write_node(&json).unwrap();
+//!
+//!     assert_eq!(writer.into_inner(), br#"{"a":123,"b":[3,2,1]}"#);
+//! }
+//! ```
+//!
+//! If you need to edit a hand-maintained file without reformatting it, use JsonDocument:
+//! ```
+//! use plainjson::JsonDocument;
+//!
+//! fn edit_config() {
+//!     let json = "{\n  // release version\n  \"version\": \"1.0.0\"\n}";
+//!     let mut document = JsonDocument::parse(json.as_bytes()).unwrap();
+//!     document.set_str("$.version", "1.0.1").unwrap();
+//!
+//!     assert_eq!(document.to_string(), "{\n  // release version\n  \"version\": \"1.0.1\"\n}");
+//! }
+//! ```
+//!
+//! If you need to turn almost-JSON into valid JSON, use RepairedJson:
+//! ```
+//! use plainjson::{FixKind, RepairedJson};
+//!
+//! fn repair_json() {
+//!     let json = r#"Here you go: {name: 'plain', "tags": ["a" "b",], ok: True"#;
+//!     let repaired = RepairedJson::repair(json.as_bytes()).unwrap();
+//!
+//!     assert_eq!(repaired.to_string(), r#"{"name": "plain", "tags": ["a", "b"], "ok": true}"#);
+//!     assert_eq!(repaired.fixes[0].kind, FixKind::RemovedText);
+//! }
+//! ```
+//!
 //! If you need to access low-level JSON tags, use JsonTag:
 //! ```
-//! use plainjson::JsonTag;
+//! use plainjson::{JsonTag, LiteralKind};
 //!
 //! fn fix_json() {
 //!     let json = r#"{"a": test, "b": "world"}"#;
 //!     let mut tags = JsonTag::parse(json.as_bytes()).unwrap();
-//!     tags[3] = JsonTag::Literal(String::from(r#""test""#));
+//!     tags[3] = JsonTag::Literal(String::from(r#""test""#), LiteralKind::String('"'));
 //!
 //!     assert_eq!(JsonTag::to_string(&tags), r#"{"a": "test", "b": "world"}"#);
 //! }
 //! ```
 
+mod encoding;
 mod filter_expression;
+mod highlight;
+mod json_document;
+mod json_event;
 mod json_node;
+mod json_number;
+mod json_partial;
 mod json_path;
+mod json_recover;
+mod json_repair;
+mod json_seq;
+mod json_stream;
+mod json_string;
 mod json_tag;
+mod json_transform;
+mod json_validate;
+mod json_writer;
+mod jsonc;
+mod limits;
+mod parse_options;
 mod peekable_codepoints;
 
+pub use crate::encoding::{DecodeError, Encoding};
+pub use crate::highlight::Theme;
+pub use crate::json_document::JsonDocument;
+pub use crate::json_event::{JsonEvent, JsonEventReader, JsonEventWriter};
 pub use crate::json_node::JsonNode;
-pub use crate::json_tag::JsonTag;
+pub use crate::json_number::JsonNumber;
+pub use crate::json_partial::{PartialEnd, PartialNode};
+pub use crate::json_path::JsonPath;
+pub use crate::json_recover::RecoveredNode;
+pub use crate::json_repair::{FixKind, JsonFix, RepairedJson};
+pub use crate::json_seq::{JsonRecord, JsonSeqReader, JsonSeqWriter, RecordError, SeqFormat};
+pub use crate::json_stream::{JsonArrayReader, JsonMatch, JsonPathMatcher};
+pub use crate::json_string::EscapeOptions;
+pub use crate::json_tag::{JsonTag, JsonTagReader, LiteralKind};
+pub use crate::json_transform::{DropValues, JsonEventTransform, MapValues, PathStep, RenameKeys};
+pub use crate::json_validate::{DiagnosticKind, JsonDiagnostic};
+pub use crate::json_writer::{Indent, JsonWriter, LineEnding, WriteOptions};
+pub use crate::jsonc::{JsoncNode, NodeComments};
+pub use crate::limits::{LimitError, Limits};
+pub use crate::parse_options::ParseOptions;
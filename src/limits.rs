@@ -0,0 +1,239 @@
+//! Resource limits applied while parsing JSON tags, JSON nodes and JSONPath.
+
+use std::{error::Error, fmt};
+
+/// Resource limits for parsing untrusted input.<br>
+/// Every limit is optional, None means unlimited.<br>
+/// The default limits are meant for input from untrusted sources, use Limits::unlimited() for trusted data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum nesting depth of arrays and objects.
+    pub max_depth: Option<usize>,
+
+    /// Maximum count of chars of a single literal.
+    pub max_literal_len: Option<usize>,
+
+    /// Maximum count of JSON tags.
+    pub max_tags: Option<usize>,
+
+    /// Maximum count of properties of a single object.
+    pub max_object_props: Option<usize>,
+
+    /// Maximum count of bytes of the whole input.
+    pub max_input_len: Option<usize>,
+
+    /// Maximum count of chars of a JSONPath.
+    pub max_path_len: Option<usize>,
+}
+
+impl Default for Limits {
+    /// Default limits, sensible for untrusted input.
+    fn default() -> Self {
+        Limits {
+            max_depth: Some(128),
+            max_literal_len: Some(1 << 20),
+            max_tags: Some(1 << 24),
+            max_object_props: Some(1 << 16),
+            max_input_len: Some(1 << 26),
+            max_path_len: Some(1 << 12),
+        }
+    }
+}
+
+impl Limits {
+    /// Limits with all limits lifted, for trusted data only.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_depth: None,
+            max_literal_len: None,
+            max_tags: None,
+            max_object_props: None,
+            max_input_len: None,
+            max_path_len: None,
+        }
+    }
+
+    /// Check nesting depth against the limit.
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<(), LimitError> {
+        match self.max_depth {
+            Some(max) if depth > max => Err(LimitError::DepthExceeded(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check length of a literal against the limit.
+    pub(crate) fn check_literal_len(&self, len: usize) -> Result<(), LimitError> {
+        match self.max_literal_len {
+            Some(max) if len > max => Err(LimitError::LiteralTooLong(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check count of JSON tags against the limit.
+    pub(crate) fn check_tags(&self, count: usize) -> Result<(), LimitError> {
+        match self.max_tags {
+            Some(max) if count > max => Err(LimitError::TooManyTags(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check count of object properties against the limit.
+    pub(crate) fn check_object_props(&self, count: usize) -> Result<(), LimitError> {
+        match self.max_object_props {
+            Some(max) if count > max => Err(LimitError::ObjectTooLarge(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check count of input bytes against the limit.
+    pub(crate) fn check_input_len(&self, len: usize) -> Result<(), LimitError> {
+        match self.max_input_len {
+            Some(max) if len > max => Err(LimitError::InputTooLarge(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check length of a JSONPath against the limit.
+    pub(crate) fn check_path_len(&self, len: usize) -> Result<(), LimitError> {
+        match self.max_path_len {
+            Some(max) if len > max => Err(LimitError::PathTooLong(max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Error raised when a limit is exceeded, carrying the limit value.<br>
+/// It can be recovered from an anyhow::Error by downcast_ref::&lt;LimitError&gt;().
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitError {
+    DepthExceeded(usize),
+    LiteralTooLong(usize),
+    TooManyTags(usize),
+    ObjectTooLarge(usize),
+    InputTooLarge(usize),
+    PathTooLong(usize),
+}
+
+impl fmt::Display for LimitError {
+    /// Implement Display trait for LimitError
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::DepthExceeded(max) => write!(f, "nesting depth exceeds limit of {}", max),
+            LimitError::LiteralTooLong(max) => {
+                write!(f, "literal length exceeds limit of {} chars", max)
+            }
+            LimitError::TooManyTags(max) => write!(f, "json tag count exceeds limit of {}", max),
+            LimitError::ObjectTooLarge(max) => {
+                write!(f, "object property count exceeds limit of {}", max)
+            }
+            LimitError::InputTooLarge(max) => {
+                write!(f, "input size exceeds limit of {} bytes", max)
+            }
+            LimitError::PathTooLong(max) => {
+                write!(f, "json path length exceeds limit of {} chars", max)
+            }
+        }
+    }
+}
+
+impl Error for LimitError {}
+
+#[cfg(test)]
+mod limits_tests {
+    use super::*;
+    use crate::{JsonNode, JsonPath, JsonTag};
+    use anyhow::Result;
+
+    /// Test nesting depth limit on JSON tag and JSON node parsing.
+    #[test]
+    fn test_depth_limit() -> Result<()> {
+        let json = format!("{}{}", "[".repeat(200), "]".repeat(200));
+        let err = JsonTag::parse(json.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::DepthExceeded(128))
+        );
+
+        let tags = JsonTag::parse_with_limits(json.as_bytes(), &Limits::unlimited())?;
+        let err = JsonNode::parse_tags(&tags).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::DepthExceeded(128))
+        );
+
+        let nodes = JsonNode::parse_with_limits(json.as_bytes(), &Limits::unlimited())?;
+        assert_eq!(nodes.len(), 1);
+
+        Ok(())
+    }
+
+    /// Test literal length, tag count and input size limits on JSON tag parsing.
+    #[test]
+    fn test_tag_limits() -> Result<()> {
+        let limits = Limits {
+            max_literal_len: Some(8),
+            ..Limits::default()
+        };
+        let err = JsonTag::parse_with_limits(r#"["0123456789"]"#.as_bytes(), &limits).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::LiteralTooLong(8))
+        );
+
+        let limits = Limits {
+            max_tags: Some(4),
+            ..Limits::default()
+        };
+        let err = JsonTag::parse_with_limits("[1, 2, 3]".as_bytes(), &limits).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::TooManyTags(4))
+        );
+
+        let limits = Limits {
+            max_input_len: Some(16),
+            ..Limits::default()
+        };
+        let json = format!("[1{}]", " ".repeat(32));
+        let err = JsonTag::parse_with_limits(json.as_bytes(), &limits).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::InputTooLarge(16))
+        );
+
+        Ok(())
+    }
+
+    /// Test object size limit on JSON node parsing.
+    #[test]
+    fn test_object_props_limit() -> Result<()> {
+        let limits = Limits {
+            max_object_props: Some(2),
+            ..Limits::default()
+        };
+        let json = r#"{"a": 1, "b": 2, "c": 3}"#;
+        let err = JsonNode::parse_with_limits(json.as_bytes(), &limits).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::ObjectTooLarge(2))
+        );
+
+        Ok(())
+    }
+
+    /// Test JSONPath length limit.
+    #[test]
+    fn test_path_len_limit() -> Result<()> {
+        let limits = Limits {
+            max_path_len: Some(8),
+            ..Limits::default()
+        };
+        let err = JsonPath::parse_with_limits("$.store.book", &limits).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LimitError>(),
+            Some(&LimitError::PathTooLong(8))
+        );
+
+        Ok(())
+    }
+}
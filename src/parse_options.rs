@@ -0,0 +1,582 @@
+//! Options to toggle leniencies of JSON tag and JSON node parsing.
+
+use crate::encoding::*;
+use crate::limits::*;
+
+/// Parse options, each leniency could be toggled separately.<br>
+/// The default options are lenient, accepting messy input as much as possible,
+/// except that numbers follow RFC 8259 number grammar unless the number extras are allowed,
+/// and string escapes follow RFC 8259 unless JSON5 strings are allowed.<br>
+/// Use ParseOptions::strict() to accept RFC 8259 JSON only, ParseOptions::json5() to accept JSON5,
+/// or ParseOptions::python() and ParseOptions::javascript() to accept Python and JavaScript literals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Resource limits.
+    pub limits: Limits,
+
+    /// Encoding of input, None means detected from byte order mark or the pattern of zero bytes.<br>
+    /// A byte order mark of the encoding is skipped either way.
+    pub encoding: Option<Encoding>,
+
+    /// Accept True, TRUE, False, FALSE, Null, NULL as bool or null.
+    pub allow_case_insensitive_literals: bool,
+
+    /// Accept strings quoted by single quotes, such as 'hello'.
+    pub allow_single_quotes: bool,
+
+    /// Accept unquoted words such as test as strings.
+    pub allow_bare_words: bool,
+
+    /// Accept object properties without colon between name and value, such as {"a" 1}.
+    pub allow_missing_colons: bool,
+
+    /// Accept leading, trailing or duplicated commas, such as [,1,,2,].
+    pub allow_extra_commas: bool,
+
+    /// Accept a single trailing comma after the last entry, such as [1,2,].
+    pub allow_trailing_commas: bool,
+
+    /// Accept values without comma between them, such as [1 2].
+    pub allow_missing_commas: bool,
+
+    /// Accept numbers with leading plus sign, such as +1.
+    pub allow_leading_plus: bool,
+
+    /// Accept numbers with leading decimal point, such as .5.
+    pub allow_leading_decimal_point: bool,
+
+    /// Accept hexadecimal integers, such as 0x1F.
+    pub allow_hex_numbers: bool,
+
+    /// Accept numbers with trailing decimal point, such as 5. or 5.e3.
+    pub allow_trailing_decimal_point: bool,
+
+    /// Accept Infinity, -Infinity, +Infinity and NaN as numbers.
+    pub allow_infinity_and_nan: bool,
+
+    /// Accept unquoted object property names which are ECMAScript identifiers, such as {name: 1}.
+    pub allow_identifier_keys: bool,
+
+    /// Accept object property names which are numbers, booleans or null, such as {1: 'a'},
+    /// which are converted to their JSON text, such as "1".
+    pub allow_non_string_keys: bool,
+
+    /// Accept Python literals: None as null, True and False as bool, inf and nan as numbers,
+    /// and strings with prefixes such as b'x', u'x' or r'x', where escapes of raw strings(r'x') are kept as is.
+    pub allow_python_literals: bool,
+
+    /// Accept Python tuples, such as (1, 2), (1,) or (), which are read as arrays,
+    /// so parentheses are written as square brackets even by lossless JSON tags.
+    /// A parenthesized value without comma, such as (1), is read as the value itself.
+    /// A tuple closed by ']' or an array closed by ')' fails.
+    pub allow_tuples: bool,
+
+    /// Accept JavaScript undefined as null.
+    pub allow_undefined: bool,
+
+    /// Accept JSON5 strings: escapes such as \x41, \v, \0, escaped line breaks, any other char escaped as itself,
+    /// and unescaped control chars other than line breaks.
+    pub allow_json5_strings: bool,
+
+    /// Accept Python strings: escapes such as \x41, \U0001F600, octal \101 and escaped line breaks,
+    /// where unknown escapes such as \d keep their backslash, as Python does.
+    pub allow_python_strings: bool,
+
+    /// Accept strings broken across a newline, each line becomes a separate literal.
+    pub allow_multiline_strings: bool,
+
+    /// Accept strings with unescaped control chars, invalid escapes or missing closing quote.
+    pub allow_invalid_strings: bool,
+
+    /// Accept unexpected brackets, colons or commas, which are skipped.
+    pub allow_stray_tags: bool,
+
+    /// Accept whitespace chars other than space, tab, line feed and carriage return.
+    pub allow_non_json_whitespace: bool,
+
+    /// Accept line comments(// ...) and block comments(/* ... */), which are skipped.
+    pub allow_comments: bool,
+
+    /// Accept invalid byte sequences of the encoding, which are decoded as U+FFFD,
+    /// otherwise a DecodeError carrying the byte offset is raised.
+    pub allow_invalid_bytes: bool,
+}
+
+impl Default for ParseOptions {
+    /// Default options, lenient with default limits.
+    fn default() -> Self {
+        ParseOptions {
+            limits: Limits::default(),
+            encoding: None,
+            allow_case_insensitive_literals: true,
+            allow_single_quotes: true,
+            allow_bare_words: true,
+            allow_missing_colons: true,
+            allow_extra_commas: true,
+            allow_trailing_commas: true,
+            allow_missing_commas: true,
+            allow_leading_plus: false,
+            allow_leading_decimal_point: false,
+            allow_hex_numbers: false,
+            allow_trailing_decimal_point: false,
+            allow_infinity_and_nan: false,
+            allow_identifier_keys: true,
+            allow_non_string_keys: false,
+            allow_python_literals: false,
+            allow_tuples: false,
+            allow_undefined: false,
+            allow_json5_strings: false,
+            allow_python_strings: false,
+            allow_multiline_strings: true,
+            allow_invalid_strings: true,
+            allow_stray_tags: true,
+            allow_non_json_whitespace: true,
+            allow_comments: true,
+            allow_invalid_bytes: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Lenient options, same as default.
+    pub fn lenient() -> Self {
+        ParseOptions::default()
+    }
+
+    /// Strict options, accepting RFC 8259 JSON only.
+    pub fn strict() -> Self {
+        ParseOptions {
+            limits: Limits::default(),
+            encoding: None,
+            allow_case_insensitive_literals: false,
+            allow_single_quotes: false,
+            allow_bare_words: false,
+            allow_missing_colons: false,
+            allow_extra_commas: false,
+            allow_trailing_commas: false,
+            allow_missing_commas: false,
+            allow_leading_plus: false,
+            allow_leading_decimal_point: false,
+            allow_hex_numbers: false,
+            allow_trailing_decimal_point: false,
+            allow_infinity_and_nan: false,
+            allow_identifier_keys: false,
+            allow_non_string_keys: false,
+            allow_python_literals: false,
+            allow_tuples: false,
+            allow_undefined: false,
+            allow_json5_strings: false,
+            allow_python_strings: false,
+            allow_multiline_strings: false,
+            allow_invalid_strings: false,
+            allow_stray_tags: false,
+            allow_non_json_whitespace: false,
+            allow_comments: false,
+            allow_invalid_bytes: false,
+        }
+    }
+
+    /// Options accepting JSON5, as specified by https://spec.json5.org/.
+    pub fn json5() -> Self {
+        ParseOptions {
+            allow_single_quotes: true,
+            allow_trailing_commas: true,
+            allow_leading_plus: true,
+            allow_leading_decimal_point: true,
+            allow_hex_numbers: true,
+            allow_trailing_decimal_point: true,
+            allow_infinity_and_nan: true,
+            allow_identifier_keys: true,
+            allow_json5_strings: true,
+            allow_non_json_whitespace: true,
+            allow_comments: true,
+            ..ParseOptions::strict()
+        }
+    }
+
+    /// Options accepting Python literals, such as output of repr() of dicts and lists.<br>
+    /// None maps to null, True and False to bool, tuples to arrays, inf and nan to Infinity and NaN
+    /// (written as null in JSON output),
+    /// non-string keys to their JSON text(such as 1 to "1" and None to "null"),
+    /// and strings quoted by single or double quotes, with prefixes such as b'x', to strings decoded by Python escapes.
+    pub fn python() -> Self {
+        ParseOptions {
+            allow_single_quotes: true,
+            allow_trailing_commas: true,
+            allow_non_string_keys: true,
+            allow_python_literals: true,
+            allow_tuples: true,
+            allow_python_strings: true,
+            ..ParseOptions::strict()
+        }
+    }
+
+    /// Options accepting JavaScript object literals, which is JSON5 accepting undefined as null,
+    /// and non-string keys converted to their JSON text, such as 1 to "1".
+    pub fn javascript() -> Self {
+        ParseOptions {
+            allow_non_string_keys: true,
+            allow_undefined: true,
+            ..ParseOptions::json5()
+        }
+    }
+
+    /// Default options with specified limits.
+    pub fn with_limits(limits: &Limits) -> Self {
+        ParseOptions {
+            limits: limits.clone(),
+            ..ParseOptions::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod parse_options_tests {
+    use super::*;
+    use crate::JsonNode;
+    use anyhow::Result;
+
+    /// Accepted cases of JSONTestSuite, prefixed by y_.
+    const Y_CASES: &[&str] = &[
+        r#"[[]   ]"#,
+        r#"[""]"#,
+        r#"[]"#,
+        r#"["a"]"#,
+        r#"[false]"#,
+        r#"[null, 1, "1", {}]"#,
+        r#"[1
+]"#,
+        r#" [1]"#,
+        r#"[1,null,null,null,2]"#,
+        r#"[2] "#,
+        r#"[123e65]"#,
+        r#"[0e+1]"#,
+        r#"[0e1]"#,
+        r#"[ 4]"#,
+        r#"[-0.000000000000000000000000000000000000000000000000000000000000000000000000000001]"#,
+        r#"[20e1]"#,
+        r#"[-0]"#,
+        r#"[-123]"#,
+        r#"[-1]"#,
+        r#"[1E22]"#,
+        r#"[1E-2]"#,
+        r#"[1E+2]"#,
+        r#"[123.456e78]"#,
+        r#"[1e-2]"#,
+        r#"[123]"#,
+        r#"[123.456789]"#,
+        r#"{"asd":"sdf", "dfg":"fgh"}"#,
+        r#"{"a":"b","a":"c"}"#,
+        r#"{"":0}"#,
+        r#"{"foo\u0000bar": 42}"#,
+        r#"{ "min": -1.0e+28, "max": 1.0e+28 }"#,
+        r#"{"a":[]}"#,
+        r#"{"x":[{"id": "xxx"}], "id": "xxx"}"#,
+        r#"{}"#,
+        "{\n\"a\": \"b\"\n}",
+        r#"["`Īካ"]"#,
+        r#"["𐐷"]"#,
+        r#"["\"\\\/\b\f\n\r\t"]"#,
+        r#"["\\u0000"]"#,
+        r#"["\""]"#,
+        r#"["a/*b*/c/*d//e"]"#,
+        r#"["\\a"]"#,
+        r#"["\u0012"]"#,
+        r#"["asd"]"#,
+        r#"[ "asd"]"#,
+        r#"["new\u000Aline"]"#,
+        r#"["€𝄞"]"#,
+        r#"["a\u007fa"]"#,
+        r#"["\u0022"]"#,
+        r#""asd""#,
+        r#"false"#,
+        r#"42"#,
+        r#"-0.1"#,
+        r#"null"#,
+        r#"true"#,
+        r#""""#,
+        "[\"a\"]\n",
+        " [] ",
+    ];
+
+    /// Rejected cases of JSONTestSuite, prefixed by n_.
+    const N_CASES: &[&str] = &[
+        r#"[1 true]"#,
+        r#"["": 1]"#,
+        r#"[""],"#,
+        r#"[,1]"#,
+        r#"[1,,2]"#,
+        r#"["x",,]"#,
+        r#"["x"]]"#,
+        r#"["",]"#,
+        r#"["x""#,
+        r#"[x"#,
+        r#"[3[4]]"#,
+        r#"[1:2]"#,
+        r#"[,]"#,
+        r#"[-]"#,
+        r#"[   , ""]"#,
+        "[\"a\",\n4\n,1,",
+        r#"[1,]"#,
+        r#"[1,,]"#,
+        "[\"\u{b}a\"\\f]",
+        r#"[*]"#,
+        r#"[""#,
+        r#"[1,"#,
+        r#"[{}"#,
+        r#"[fals]"#,
+        r#"[nul]"#,
+        r#"[tru]"#,
+        "123\u{0}",
+        r#"[++1234]"#,
+        r#"[+1]"#,
+        r#"[+Inf]"#,
+        r#"[-01]"#,
+        r#"[-1.0.]"#,
+        r#"[-2.]"#,
+        r#"[-NaN]"#,
+        r#"[.-1]"#,
+        r#"[.2e-3]"#,
+        r#"[0.1.2]"#,
+        r#"[0.3e+]"#,
+        r#"[0.3e]"#,
+        r#"[0.e1]"#,
+        r#"[0E+]"#,
+        r#"[0E]"#,
+        r#"[1.0e-]"#,
+        r#"[1 000.0]"#,
+        r#"[1eE2]"#,
+        r#"[2.e+3]"#,
+        r#"[Inf]"#,
+        r#"[NaN]"#,
+        r#"[012]"#,
+        r#"[0x1]"#,
+        r#"[1ea]"#,
+        r#"[-foo]"#,
+        r#"[- 1]"#,
+        r#"[1.2a-3]"#,
+        "[\u{ff11}]",
+        r#"[Infinity]"#,
+        r#"["x", truth]"#,
+        r#"{[: "x"}"#,
+        r#"{"x", null}"#,
+        r#"{"x"::"b"}"#,
+        r#"{🇨🇭}"#,
+        r#"{"a":"a" 123}"#,
+        r#"{key: 'value'}"#,
+        r#"{"a" b}"#,
+        r#"{:"b"}"#,
+        r#"{"a" "b"}"#,
+        r#"{"a":"#,
+        r#"{"a""#,
+        r#"{1:1}"#,
+        r#"{null:null,null:null}"#,
+        r#"{"id":0,,,,,}"#,
+        r#"{'a':0}"#,
+        r#"{"id":0,}"#,
+        r#"{"a":"b"}/**/"#,
+        r#"{"a":"b"}//"#,
+        r#"{"a":"b",,"c":"d"}"#,
+        r#"{a: "b"}"#,
+        r#"{"a":"a"#,
+        r#"{ "foo" : "bar", "a" }"#,
+        r#"{"a":"b"}#"#,
+        r#" "#,
+        r#"["\uD800\"]"#,
+        r#"["\uD800\u"]"#,
+        r#"[é]"#,
+        "[\"\\\u{0}\"]",
+        r#"["\x00"]"#,
+        r#"["\\\"]"#,
+        "[\"\\\t\"]",
+        r#"["\🌀"]"#,
+        r#"["\"]"#,
+        r#"["\u00A"]"#,
+        r#"["\uD834\uDd"]"#,
+        r#"["\a"]"#,
+        r#"["\uqqqq"]"#,
+        r#"[\u0020"asd"]"#,
+        r#"[\n]"#,
+        r#"""#,
+        r#"['single quote']"#,
+        r#"abc"#,
+        r#"["\"#,
+        "[\"a\u{0}a\"]",
+        "[\"new\nline\"]",
+        "[\"\t\"]",
+        r#""\UA66D""#,
+        r#"""x"#,
+        r#"<.>"#,
+        r#"[1]x"#,
+        r#"[1]]"#,
+        r#"["asd]"#,
+        r#"aå"#,
+        r#"[True]"#,
+        r#"1]"#,
+        r#"{"x": true,"#,
+        r#"[][]"#,
+        r#"]"#,
+        r#"["#,
+        "[\u{0}]",
+        r#"2@"#,
+        r#"{}}"#,
+        r#"{"":"#,
+        r#"{"a":/*comment*/"b"}"#,
+        r#"{"a": true} "x""#,
+        r#"['"#,
+        r#"[,"#,
+        r#"{"#,
+        r#"*"#,
+        r#"{"a":"b"}#{}"#,
+        "[\u{2060}]",
+        r#"[\u000A""]"#,
+        r#"[1"#,
+        r#"å"#,
+        "[\u{c}]",
+        "",
+    ];
+
+    /// Test strict options against accepted cases of JSONTestSuite.
+    #[test]
+    fn test_strict_accepts_y_cases() -> Result<()> {
+        for json in Y_CASES {
+            let node =
+                JsonNode::parse_single_node_with_options(json.as_bytes(), &ParseOptions::strict());
+            assert!(
+                node.is_ok(),
+                "expecting accepted: {}, error: {:?}",
+                json,
+                node
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Test strict options against rejected cases of JSONTestSuite.
+    #[test]
+    fn test_strict_rejects_n_cases() -> Result<()> {
+        for json in N_CASES {
+            let node =
+                JsonNode::parse_single_node_with_options(json.as_bytes(), &ParseOptions::strict());
+            assert!(
+                node.is_err(),
+                "expecting rejected: {}, parsed: {:?}",
+                json,
+                node
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Test each leniency toggled separately.
+    #[test]
+    fn test_toggle_leniency() -> Result<()> {
+        let cases = [
+            (
+                r#"[True]"#,
+                ParseOptions {
+                    allow_case_insensitive_literals: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+            (
+                r#"['a']"#,
+                ParseOptions {
+                    allow_single_quotes: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+            (
+                r#"[test]"#,
+                ParseOptions {
+                    allow_bare_words: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+            (
+                r#"{"a" 1}"#,
+                ParseOptions {
+                    allow_missing_colons: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+            (
+                r#"[1,,2,]"#,
+                ParseOptions {
+                    allow_extra_commas: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+            (
+                r#"[1 2]"#,
+                ParseOptions {
+                    allow_missing_commas: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+            (
+                "[\"a\tb\"]",
+                ParseOptions {
+                    allow_invalid_strings: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+            (
+                r#"[1]]"#,
+                ParseOptions {
+                    allow_stray_tags: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+            (
+                "[\u{c}1]",
+                ParseOptions {
+                    allow_non_json_whitespace: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+            (
+                "[1, // one\n 2 /* two */]",
+                ParseOptions {
+                    allow_comments: true,
+                    ..ParseOptions::strict()
+                },
+            ),
+        ];
+        for (json, options) in cases {
+            assert!(JsonNode::parse_single_node_with_options(
+                json.as_bytes(),
+                &ParseOptions::strict()
+            )
+            .is_err());
+            assert!(
+                JsonNode::parse_single_node_with_options(json.as_bytes(), &options).is_ok(),
+                "{}",
+                json
+            );
+            assert!(
+                JsonNode::parse_single_node(json.as_bytes()).is_ok(),
+                "{}",
+                json
+            );
+        }
+
+        let json = "[\"a\nb\"\n]";
+        let options = ParseOptions {
+            allow_multiline_strings: false,
+            ..ParseOptions::lenient()
+        };
+        assert!(JsonNode::parse_single_node_with_options(json.as_bytes(), &options).is_err());
+        assert_eq!(
+            JsonNode::parse_single_node(json.as_bytes())?,
+            JsonNode::Array(vec![
+                JsonNode::PlainString(String::from(r#""a"#)),
+                JsonNode::PlainString(String::from(r#"b""#)),
+            ])
+        );
+
+        Ok(())
+    }
+}
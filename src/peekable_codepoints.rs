@@ -1,9 +1,10 @@
 //! A peekable codepoint reader.
 
-use std::io::{Bytes, Read};
+use std::io::Read;
 
-use anyhow::{Error, Result};
-use unicode_reader::CodePoints;
+use anyhow::Result;
+
+use crate::encoding::*;
 
 /// A codepoint reader supports peeking.
 /// Peek/pop char/string from internal char reader.
@@ -12,30 +13,41 @@ where
     R: Read,
 {
     /// internal char reader
-    codepoints: CodePoints<Bytes<R>>,
+    decoder: CharDecoder<R>,
 
     /// internal char buffer
     buffer: Vec<char>,
+
+    /// count of bytes read from internal char reader
+    bytes_read: usize,
 }
 
 impl<R: Read> PeekableCodePoints<R> {
-    /// Create a PeekableCodePoints from a instance that implements Read trait
-    pub fn new(reader: R) -> Self {
+    /// Create a PeekableCodePoints from a instance that implements Read trait, with specified encoding,
+    /// or detected encoding if None. Invalid byte sequences are decoded as U+FFFD if lossy.
+    pub fn with_encoding(reader: R, encoding: Option<Encoding>, lossy: bool) -> Self {
         PeekableCodePoints {
-            codepoints: CodePoints::from(reader),
+            decoder: CharDecoder::new(reader, encoding, lossy),
             buffer: Vec::new(),
+            bytes_read: 0,
         }
     }
 
+    /// Count of bytes read from underlying reader so far, including the ones still buffered.
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
     /// Fill internal buffer with chars of desired count.
     /// Returns actual count of chars filled if not enough chars found.
     fn feed_buffer(&mut self, count: usize) -> Result<usize> {
         for i in 0..count {
-            let item = self.codepoints.next();
-            match item {
+            match self.decoder.next_char()? {
                 None => return Ok(i),
-                Some(Err(e)) => return Err(Error::new(e)),
-                Some(Ok(c)) => self.buffer.push(c),
+                Some(c) => {
+                    self.bytes_read = self.decoder.offset();
+                    self.buffer.push(c);
+                }
             }
         }
 
@@ -71,7 +83,7 @@ impl<R: Read> PeekableCodePoints<R> {
     /// If not enough chars found, using actual number remaining.
     pub fn pop(&mut self, count: usize) -> Result<String> {
         let pop_str = self.peek(count)?;
-        self.discard_buffer(pop_str.len());
+        self.discard_buffer(pop_str.chars().count());
 
         Ok(pop_str)
     }